@@ -0,0 +1,231 @@
+//! builder
+//! This module does the opposite job of pkt_parser: it builds valid Ethernet/IP/transport frames starting from the
+//! values of their fields. The frames can be used to test the decoders, to generate traffic or to rebuild a capture file
+//! from summarized data.
+//!
+//! From now, the builder can produce Ethernet frames carrying IPv4 or IPv6, with a TCP or UDP segment inside.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::checksum::{internet_checksum, Checksum};
+use crate::pkt_parser::{Direction, PacketInfo, Protocol, TcpFlags};
+
+/// The placeholder MAC address used as source of the synthetic frames (a locally administered address).
+pub const PLACEHOLDER_SRC_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x01];
+/// The placeholder MAC address used as destination of the synthetic frames (a locally administered address).
+pub const PLACEHOLDER_DEST_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x02];
+/// The port used in place of the local one when it is unknown (the first dynamic port).
+pub const PLACEHOLDER_PORT: u16 = 49152;
+
+/// A custom error returned when the fields given to the builder cannot form a valid frame.
+#[derive(Debug, Clone)]
+pub struct BuildError {
+    pub msg: String
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Build error: {}", self.msg)
+    }
+}
+
+/// Builds a frame layer by layer. All the fields not set explicitly get a sensible default.
+#[derive(Debug, Clone)]
+pub struct FrameBuilder {
+    src_mac: [u8; 6],
+    dest_mac: [u8; 6],
+    src: IpAddr,
+    dest: IpAddr,
+    protocol: Protocol,
+    src_port: u16,
+    dest_port: u16,
+    tcp_flags: u8,
+    ttl: u8,
+    payload: Vec<u8>,
+}
+
+impl FrameBuilder {
+    pub fn new(src: IpAddr, dest: IpAddr, protocol: Protocol) -> Self {
+        FrameBuilder { src_mac: PLACEHOLDER_SRC_MAC, dest_mac: PLACEHOLDER_DEST_MAC, src, dest, protocol,
+            src_port: PLACEHOLDER_PORT, dest_port: PLACEHOLDER_PORT, tcp_flags: 0x10, ttl: 64, payload: Vec::new() }
+    }
+
+    /// Prepares a frame carrying the information of the packet, as if it has been received from the remote endpoint,
+    /// or sent to it when the direction of the packet is Transmitted.
+    /// The local endpoint and the MAC addresses are replaced by placeholders, the payload is made of zeros.
+    pub fn from_packet_info(info: &PacketInfo) -> Result<Self, BuildError> {
+        let src = match info.get_address().parse::<IpAddr>() {
+            Ok(address) => address,
+            Err(_) => return Err(BuildError{msg: format!("Cannot parse the address {}", info.get_address())})
+        };
+        let dest = match src {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+        };
+        let mut builder = if info.get_direction() == Some(Direction::Transmitted) {
+            let mut builder = FrameBuilder::new(dest, src, info.get_protocol());
+            builder.set_src_mac(PLACEHOLDER_DEST_MAC).set_dest_mac(PLACEHOLDER_SRC_MAC).set_ports(PLACEHOLDER_PORT, info.get_port());
+            builder
+        } else {
+            let mut builder = FrameBuilder::new(src, dest, info.get_protocol());
+            builder.set_ports(info.get_port(), PLACEHOLDER_PORT);
+            builder
+        };
+        builder.set_payload(vec![0; info.get_byte_transmitted()]);
+        if let Some(tcp_header) = info.get_tcp_header() {
+            builder.set_tcp_flags(tcp_header.get_flags());
+        }
+        Ok(builder)
+    }
+
+    pub fn set_src_mac(&mut self, mac: [u8; 6]) -> &mut Self { self.src_mac = mac; self }
+    pub fn set_dest_mac(&mut self, mac: [u8; 6]) -> &mut Self { self.dest_mac = mac; self }
+    pub fn set_ports(&mut self, src_port: u16, dest_port: u16) -> &mut Self {
+        self.src_port = src_port;
+        self.dest_port = dest_port;
+        self
+    }
+    pub fn set_tcp_flags(&mut self, flags: TcpFlags) -> &mut Self { self.tcp_flags = flags.into(); self }
+    /// Sets the TTL (IPv4) or the hop limit (IPv6).
+    pub fn set_ttl(&mut self, ttl: u8) -> &mut Self { self.ttl = ttl; self }
+    pub fn set_payload(&mut self, payload: Vec<u8>) -> &mut Self { self.payload = payload; self }
+
+    /// Builds the whole frame, computing lengths and checksums.
+    pub fn build(&self) -> Result<Vec<u8>, BuildError> {
+        let (protocol_number, mut segment) = match self.protocol {
+            Protocol::TCP => (0x06, self.tcp_segment()),
+            Protocol::UDP => (0x11, self.udp_datagram()),
+            Protocol::ICMP | Protocol::ICMPv6 | Protocol::SCTP | Protocol::GRE | Protocol::IPIP | Protocol::Unknown => return Err(BuildError{msg: format!("Cannot build a frame for the {} protocol", self.protocol.to_string())})
+        };
+
+        let mut frame = Vec::with_capacity(14 + 40 + segment.len());
+        frame.extend_from_slice(&self.dest_mac);
+        frame.extend_from_slice(&self.src_mac);
+        match (self.src, self.dest) {
+            (IpAddr::V4(src), IpAddr::V4(dest)) => {
+                if 20 + segment.len() > u16::MAX as usize {
+                    return Err(BuildError{msg: "The payload is too big for an ipv4 packet".to_string()})
+                }
+                let pseudo_header = [&src.octets()[..], &dest.octets()[..], &[0, protocol_number], &(segment.len() as u16).to_be_bytes()].concat();
+                self.fill_checksum(&mut segment, &pseudo_header);
+
+                let mut header = vec![0x45, 0];
+                header.extend_from_slice(&((20 + segment.len()) as u16).to_be_bytes());
+                header.extend_from_slice(&[0, 0, 0x40, 0, self.ttl, protocol_number, 0, 0]);
+                header.extend_from_slice(&src.octets());
+                header.extend_from_slice(&dest.octets());
+                let checksum = internet_checksum(&header);
+                header[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+                frame.extend_from_slice(&[0x08, 0x00]);
+                frame.extend_from_slice(&header);
+            },
+            (IpAddr::V6(src), IpAddr::V6(dest)) => {
+                if segment.len() > u16::MAX as usize {
+                    return Err(BuildError{msg: "The payload is too big for an ipv6 packet".to_string()})
+                }
+                let pseudo_header = [&src.octets()[..], &dest.octets()[..], &(segment.len() as u32).to_be_bytes(), &[0, 0, 0, protocol_number]].concat();
+                self.fill_checksum(&mut segment, &pseudo_header);
+
+                frame.extend_from_slice(&[0x86, 0xdd]);
+                frame.extend_from_slice(&[0x60, 0, 0, 0]);
+                frame.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+                frame.extend_from_slice(&[protocol_number, self.ttl]);
+                frame.extend_from_slice(&src.octets());
+                frame.extend_from_slice(&dest.octets());
+            },
+            _ => return Err(BuildError{msg: "Source and destination addresses must be of the same ip version".to_string()})
+        }
+        frame.extend_from_slice(&segment);
+        Ok(frame)
+    }
+
+    fn tcp_segment(&self) -> Vec<u8> {
+        let mut segment = Vec::with_capacity(20 + self.payload.len());
+        segment.extend_from_slice(&self.src_port.to_be_bytes());
+        segment.extend_from_slice(&self.dest_port.to_be_bytes());
+        // sequence and acknowledgement numbers, data offset of 5 words
+        segment.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0x50, self.tcp_flags, 0xff, 0xff, 0, 0, 0, 0]);
+        segment.extend_from_slice(&self.payload);
+        segment
+    }
+
+    fn udp_datagram(&self) -> Vec<u8> {
+        let mut datagram = Vec::with_capacity(8 + self.payload.len());
+        datagram.extend_from_slice(&self.src_port.to_be_bytes());
+        datagram.extend_from_slice(&self.dest_port.to_be_bytes());
+        datagram.extend_from_slice(&((8 + self.payload.len()) as u16).to_be_bytes());
+        datagram.extend_from_slice(&[0, 0]);
+        datagram.extend_from_slice(&self.payload);
+        datagram
+    }
+
+    fn fill_checksum(&self, segment: &mut [u8], pseudo_header: &[u8]) {
+        let position = match self.protocol { Protocol::TCP => 16, _ => 6 };
+        let mut checksum = Checksum::new().add(pseudo_header).add(segment).finish();
+        // for UDP a zero checksum means "no checksum", so it is sent as all ones
+        if checksum == 0 && self.protocol == Protocol::UDP { checksum = 0xffff }
+        segment[position..position + 2].copy_from_slice(&checksum.to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::{*};
+    use crate::pkt_parser::{EtherType, EthernetHeader, Header, Ipv4Header, TCPHeader, TimeVal, UDPHeader};
+
+    #[test]
+    fn test_packet_info_round_trip() {
+        let data = vec![220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 0x18, 250, 240, 0, 0, 0, 0];
+        let (tcp_header_result, _tcp_payload) = TCPHeader::decode(data);
+        let mut info = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 517, TimeVal::from(1657968204419346));
+        info.set_tcp_header(tcp_header_result.unwrap());
+
+        let frame = info.to_synthetic_frame().unwrap();
+        assert_eq!(frame.len(), 14 + 20 + 20 + 517);
+
+        let (ethernet_header_res, eth_payload) = EthernetHeader::decode(frame);
+        let ethernet_header = ethernet_header_res.unwrap();
+        assert_eq!(ethernet_header.get_src_address(), "020000000001".to_string());
+        assert_eq!(ethernet_header.get_ether_type(), EtherType::Ipv4);
+
+        let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode(eth_payload);
+        let ipv4_header = ipv4_header_result.unwrap();
+        assert_eq!(ipv4_header.get_protocol(), Protocol::TCP);
+
+        let (tcp_header_result, tcp_payload) = TCPHeader::decode(ipv4_payload);
+        let tcp_header = tcp_header_result.unwrap();
+        assert!(tcp_header.get_flags().is_ack());
+
+        let decoded = PacketInfo::new(ipv4_header.get_src_address(), tcp_header.get_src_port(), ipv4_header.get_protocol(), tcp_payload.len(), info.get_time_stamp());
+        assert_eq!(decoded.get_address(), info.get_address());
+        assert_eq!(decoded.get_port(), info.get_port());
+        assert_eq!(decoded.get_protocol(), info.get_protocol());
+        assert_eq!(decoded.get_byte_transmitted(), info.get_byte_transmitted());
+    }
+
+    #[test]
+    fn test_udp_frame_checksums() {
+        let info = PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 102, TimeVal::from(0));
+        let frame = FrameBuilder::from_packet_info(&info).unwrap().build().unwrap();
+        // a correct checksum sums (with the checksum itself) to all ones, so the complement is zero
+        assert_eq!(internet_checksum(&frame[14..34]), 0);
+        let pseudo_header = [&frame[26..34], &[0, 0x11], &frame[38..40]].concat();
+        assert_eq!(Checksum::new().add(&pseudo_header).add(&frame[34..]).finish(), 0);
+
+        let (udp_header_res, udp_payload) = UDPHeader::decode(Vec::from(&frame[34..]));
+        assert_eq!(udp_header_res.unwrap().get_src_port(), 53);
+        assert_eq!(udp_payload.len(), 102);
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        let unknown = PacketInfo::new("192.168.1.1".to_string(), 0, Protocol::Unknown, 0, TimeVal::from(0));
+        assert!(unknown.to_synthetic_frame().is_err());
+        let mixed = FrameBuilder::new("192.168.1.1".parse().unwrap(), "::1".parse().unwrap(), Protocol::UDP);
+        assert!(mixed.build().is_err());
+        let ipv6 = PacketInfo::new("fe80::5c2:b49d:95b:3f19".to_string(), 546, Protocol::UDP, 10, TimeVal::from(0));
+        assert_eq!(ipv6.to_synthetic_frame().unwrap().len(), 14 + 40 + 8 + 10);
+    }
+}
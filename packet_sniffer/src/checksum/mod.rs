@@ -0,0 +1,88 @@
+//! checksum
+//! This module computes the Internet checksum (RFC 1071), the 16 bit one's complement checksum shared by IPv4, ICMP, TCP
+//! and UDP, so that the builders and the verifiers of every layer use the same routine.
+//!
+//! The TCP and UDP checksums also cover a pseudo header made of fields of the IP header, so the checksum can be computed
+//! incrementally over several slices with Checksum.
+
+/// Accumulates the one's complement sum over one or more slices, as if they were a single buffer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Checksum {
+    sum: u32,
+    /// The last byte of a slice with odd length, that is the high byte of a word completed by the next slice.
+    pending: Option<u8>,
+}
+
+impl Checksum {
+    pub fn new() -> Self {
+        Checksum::default()
+    }
+
+    /// Adds the bytes to the sum.
+    pub fn add(&mut self, data: &[u8]) -> &mut Self {
+        let mut data = data;
+        if let (Some(high), Some((low, rest))) = (self.pending, data.split_first()) {
+            self.add_word(((high as u32) << 8) | *low as u32);
+            self.pending = None;
+            data = rest;
+        }
+        let mut chunks = data.chunks_exact(2);
+        for chunk in &mut chunks {
+            self.add_word(((chunk[0] as u32) << 8) | chunk[1] as u32);
+        }
+        if let [last] = chunks.remainder() {
+            self.pending = Some(*last);
+        }
+        self
+    }
+
+    fn add_word(&mut self, word: u32) {
+        self.sum += word;
+        self.sum = (self.sum & 0xffff) + (self.sum >> 16);
+    }
+
+    /// Returns the checksum of the bytes added so far. An odd total length is padded with a zero byte.
+    pub fn finish(&self) -> u16 {
+        let mut checksum = *self;
+        if let Some(high) = checksum.pending {
+            checksum.add_word((high as u32) << 8);
+        }
+        !(checksum.sum as u16)
+    }
+}
+
+/// Computes the checksum of the data. Computed over data that already holds its correct checksum, it returns zero.
+pub fn internet_checksum(data: &[u8]) -> u16 {
+    Checksum::new().add(data).finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checksum::{*};
+
+    #[test]
+    fn test_known_vectors() {
+        // the example of RFC 1071
+        assert_eq!(internet_checksum(&[0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7]), 0x220d);
+        // an ipv4 header with its checksum field set to zero, then with the right checksum
+        let mut header = vec![0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0xc0, 0xa8, 0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7];
+        assert_eq!(internet_checksum(&header), 0xb861);
+        header[10..12].copy_from_slice(&[0xb8, 0x61]);
+        assert_eq!(internet_checksum(&header), 0);
+        assert_eq!(internet_checksum(&[]), 0xffff);
+    }
+
+    #[test]
+    fn test_odd_lengths() {
+        // the last byte is padded with zero
+        assert_eq!(internet_checksum(&[0x01]), 0xfeff);
+        assert_eq!(internet_checksum(&[0x00, 0x01, 0xf2]), !0xf201);
+        // split at odd positions, the incremental sum matches the one over the whole buffer
+        let data: Vec<u8> = (0..=200u8).collect();
+        let whole = internet_checksum(&data);
+        for split in [1, 3, 100, 199] {
+            assert_eq!(Checksum::new().add(&data[..split]).add(&data[split..]).finish(), whole, "split at {}", split);
+        }
+        assert_eq!(Checksum::new().add(&data[..7]).add(&[]).add(&data[7..8]).add(&data[8..]).finish(), whole);
+    }
+}
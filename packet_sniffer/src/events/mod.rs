@@ -0,0 +1,136 @@
+//! events
+//! This module allows to follow a sniffing while it runs, without polling the report. The decoded packets, the alerts
+//! raised by the detectors and periodic statistics are published as CaptureEvent values on an EventBus, and every
+//! subscriber receives them through its own crossbeam channel, so that it can select over them together with other sources.
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use crate::detect::{NewHostDetector, NewHostEvent, ResetDetector, ResetFloodEvent, SpoofDetector, SpoofedPacketEvent};
+use crate::pkt_parser::PacketInfo;
+use crate::reassembly::FragmentAlert;
+use crate::stats::TrafficCounter;
+
+/// The alerts that can be published on the bus.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Alert {
+    NewHost(NewHostEvent),
+    Fragment(FragmentAlert),
+    ResetFlood(ResetFloodEvent),
+    Spoofed(SpoofedPacketEvent),
+}
+
+/// An event published during a sniffing.
+#[derive(Debug, Clone)]
+pub enum CaptureEvent {
+    /// A packet that has been decoded and accepted by the filters.
+    Packet(PacketInfo),
+    Alert(Alert),
+    /// The total traffic seen so far, published every stats_interval packets.
+    Stats(TrafficCounter),
+}
+
+/// Delivers the events to all the subscribers. The subscribers whose receiver has been dropped are removed at the
+/// first event that cannot be delivered to them.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    subscribers: Vec<Sender<CaptureEvent>>,
+    new_hosts: NewHostDetector,
+    resets: ResetDetector,
+    spoofs: SpoofDetector,
+    totals: TrafficCounter,
+    stats_interval: usize,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus::new()
+    }
+}
+
+impl EventBus {
+    /// Creates a bus without subscribers, that publishes the statistics every 100 packets.
+    pub fn new() -> Self {
+        EventBus { subscribers: Vec::new(), new_hosts: NewHostDetector::new(), resets: ResetDetector::default(), spoofs: SpoofDetector::default(), totals: TrafficCounter::default(), stats_interval: 100 }
+    }
+
+    /// Returns a new receiver, that will get all the events published from now on.
+    pub fn subscribe(&mut self) -> Receiver<CaptureEvent> {
+        let (tx, rx) = unbounded();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    pub fn get_subscriber_count(&self) -> usize { self.subscribers.len() }
+
+    /// Sets the detector of the hosts sending too many resets.
+    pub fn set_reset_detector(&mut self, detector: ResetDetector) { self.resets = detector }
+    /// Sets the detector of the spoofed packets, that knows the internal networks.
+    pub fn set_spoof_detector(&mut self, detector: SpoofDetector) { self.spoofs = detector }
+
+    pub fn get_stats_interval(&self) -> usize { self.stats_interval }
+    /// Sets how many packets are published between two Stats events, 0 disables them.
+    pub fn set_stats_interval(&mut self, stats_interval: usize) { self.stats_interval = stats_interval }
+
+    /// Sends the event to every subscriber.
+    pub fn publish(&mut self, event: CaptureEvent) {
+        self.subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    /// Publishes a decoded packet, followed by the alerts and the statistics it may cause.
+    pub fn process(&mut self, info: &PacketInfo) {
+        self.totals.add(info.get_byte_transmitted());
+        self.publish(CaptureEvent::Packet(info.clone()));
+        if let Some(event) = self.new_hosts.update(info) {
+            self.publish(CaptureEvent::Alert(Alert::NewHost(event)));
+        }
+        if let Some(event) = self.resets.update(info) {
+            self.publish(CaptureEvent::Alert(Alert::ResetFlood(event)));
+        }
+        if let Some(event) = self.spoofs.update(info) {
+            self.publish(CaptureEvent::Alert(Alert::Spoofed(event)));
+        }
+        // is_multiple_of(0) is false for any packet count but 0, so an interval of 0 never publishes
+        if self.totals.get_packets().is_multiple_of(self.stats_interval) {
+            self.publish(CaptureEvent::Stats(self.totals));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{*};
+    use crate::pkt_parser::{Protocol, TimeVal};
+
+    #[test]
+    fn test_subscribers_receive_events() {
+        let mut bus = EventBus::new();
+        bus.set_stats_interval(3);
+        let first = bus.subscribe();
+        let second = bus.subscribe();
+
+        bus.process(&PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(1_000_000)));
+        bus.process(&PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 240, TimeVal::from(1_000_100)));
+        bus.process(&PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 1400, TimeVal::from(1_000_200)));
+
+        let events: Vec<CaptureEvent> = first.try_iter().collect();
+        assert_eq!(events.len(), 6);
+        assert!(matches!(&events[0], CaptureEvent::Packet(info) if info.get_port() == 53));
+        assert!(matches!(&events[1], CaptureEvent::Alert(Alert::NewHost(event)) if event.get_address() == "192.168.1.1"));
+        assert!(matches!(&events[2], CaptureEvent::Packet(info) if info.get_byte_transmitted() == 240));
+        assert!(matches!(&events[3], CaptureEvent::Packet(info) if info.get_port() == 443));
+        assert!(matches!(&events[4], CaptureEvent::Alert(Alert::NewHost(event)) if event.get_address() == "149.154.167.92"));
+        assert!(matches!(&events[5], CaptureEvent::Stats(totals) if totals.get_packets() == 3 && totals.get_bytes() == 1750));
+        assert_eq!(second.try_iter().count(), 6);
+    }
+
+    #[test]
+    fn test_dropped_subscribers_are_removed() {
+        let mut bus = EventBus::new();
+        let receiver = bus.subscribe();
+        drop(bus.subscribe());
+        assert_eq!(bus.get_subscriber_count(), 2);
+
+        bus.process(&PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(1_000_000)));
+        assert_eq!(bus.get_subscriber_count(), 1);
+        assert_eq!(receiver.try_iter().count(), 2);
+    }
+}
@@ -0,0 +1,401 @@
+//! export
+//! This module defines a common way to write the collected packets in different textual formats.
+//!
+//! Every format implements the PacketFormatter trait, so that a new one can be plugged in the export function. The
+//! built-in formats are:
+//! - CSV
+//! - JSON (one object per line)
+//! - tcpdump-like lines
+//!
+//! A TrafficReport can be written as CSV too, a row per connection (see write_report_csv).
+//!
+//! With the `serde` feature the packets can also be written as a single JSON array, keeping all their fields (see
+//! export_json_array).
+//!
+//! The packets can also be written as a capture file, rebuilding their frames from the summarized information (see
+//! export_reconstructed_pcap), while PcapWriter writes the raw frames as they have been captured.
+
+use std::io;
+use std::io::Write;
+use chrono::{TimeZone, Utc};
+use pcap::Linktype;
+use crate::pkt_parser::{PacketInfo, TimeVal};
+use crate::report::TrafficReport;
+
+/// The comment written in the capture files made of synthetic frames.
+pub const RECONSTRUCTED_COMMENT: &str = "Reconstructed by packet_sniffer from summarized packet information: addresses of the local host, MAC addresses and payloads are synthetic";
+
+/// The PacketFormatter trait define how a single packet is rendered. A formatter can optionally provide an header line,
+/// written once before all the packets.
+pub trait PacketFormatter {
+    fn format(&self, info: &PacketInfo) -> String;
+
+    fn header(&self) -> Option<String> { None }
+}
+
+/// Writes all the packets in the writer, one per line, using the given formatter.
+pub fn export<F, W>(packets: &[PacketInfo], formatter: &F, writer: &mut W) -> io::Result<()>
+    where
+        F: PacketFormatter + ?Sized,
+        W: Write,
+{
+    if let Some(header) = formatter.header() {
+        writeln!(writer, "{}", header)?;
+    }
+    for info in packets {
+        writeln!(writer, "{}", formatter.format(info))?;
+    }
+    writer.flush()
+}
+
+fn timestamp_to_string(ts: &TimeVal) -> String {
+    format!("{}.{:06}", ts.sec, ts.u_sec)
+}
+
+/// Renders the TCP flags as tcpdump does, e.g. "S." for a SYN-ACK segment.
+fn tcp_flags_to_string(info: &PacketInfo) -> Option<String> {
+    let flags = info.get_tcp_header()?.get_flags();
+    let mut result = String::new();
+    if flags.is_syn() { result.push('S') }
+    if flags.is_fin() { result.push('F') }
+    if flags.is_rst() { result.push('R') }
+    if flags.is_ack() { result.push('.') }
+    if result.is_empty() { result.push_str("none") }
+    Some(result)
+}
+
+/// Formats the packets as comma separated values: address, port, protocol, bytes and timestamp.
+#[derive(Debug, Clone, Default)]
+pub struct CsvFormatter;
+
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else { value.to_string() }
+}
+
+impl PacketFormatter for CsvFormatter {
+    fn format(&self, info: &PacketInfo) -> String {
+        format!("{},{},{},{},{}", csv_field(&info.get_address()), info.get_port(), csv_field(&info.get_protocol().to_string()),
+                info.get_byte_transmitted(), timestamp_to_string(&info.get_time_stamp()))
+    }
+
+    fn header(&self) -> Option<String> {
+        Some("address,port,protocol,bytes,timestamp".to_string())
+    }
+}
+
+/// Writes the report as comma separated values, a row per connection ordered by the first packet seen: address, port,
+/// protocol, bytes, packets and the UTC dates of the first and last packet.
+pub fn write_report_csv<W: Write>(report: &TrafficReport, mut out: W) -> io::Result<()> {
+    writeln!(out, "address,port,protocol,bytes,packets,first_ts,last_ts")?;
+    let mut entries: Vec<_> = report.entries().collect();
+    entries.sort_by_key(|(key, stats)| (stats.get_first_seen(), key.get_address(), key.get_port()));
+    for (key, stats) in entries {
+        writeln!(out, "{},{},{},{},{},{},{}", csv_field(&key.get_address()), key.get_port(), csv_field(&key.get_protocol().to_string()),
+                 stats.get_bytes(), stats.get_packets(), stats.get_first_seen().display_as_date(), stats.get_last_seen().display_as_date())?;
+    }
+    out.flush()
+}
+
+/// Formats every packet as a single line JSON object.
+#[derive(Debug, Clone, Default)]
+pub struct JsonFormatter;
+
+fn json_string(value: &str) -> String {
+    let mut result = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c)
+        }
+    }
+    result.push('"');
+    result
+}
+
+impl PacketFormatter for JsonFormatter {
+    fn format(&self, info: &PacketInfo) -> String {
+        format!("{{\"address\":{},\"port\":{},\"protocol\":{},\"bytes\":{},\"timestamp\":{}}}",
+                json_string(&info.get_address()), info.get_port(), json_string(&info.get_protocol().to_string()),
+                info.get_byte_transmitted(), timestamp_to_string(&info.get_time_stamp()))
+    }
+}
+
+/// Formats the packets similarly to the tcpdump output: UTC time, protocol, endpoint, TCP flags and length.
+#[derive(Debug, Clone, Default)]
+pub struct TcpdumpFormatter;
+
+impl PacketFormatter for TcpdumpFormatter {
+    fn format(&self, info: &PacketInfo) -> String {
+        let ts = info.get_time_stamp();
+        let time = match Utc.timestamp_opt(ts.sec as i64, ts.u_sec * 1000).single() {
+            Some(date) => date.format("%H:%M:%S%.6f").to_string(),
+            None => timestamp_to_string(&ts)
+        };
+        let flags = match tcp_flags_to_string(info) {
+            Some(flags) => format!(" Flags [{}],", flags),
+            None => String::new()
+        };
+        format!("{} {} {}.{}:{} length {}", time, info.get_protocol().to_string(), info.get_address(), info.get_port(),
+                flags, info.get_byte_transmitted())
+    }
+}
+
+/// Writes the packets as a JSON array of objects holding all the fields of PacketInfo. Protocol and Direction are
+/// written as their names, e.g. "TCP" and "Transmitted".
+#[cfg(feature = "serde")]
+pub fn export_json_array<W: Write>(packets: &[PacketInfo], writer: &mut W) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, packets)?;
+    writer.flush()
+}
+
+/// The magic number of the pcap files with microseconds timestamps.
+pub const PCAP_MAGIC: u32 = 0xa1b2c3d4;
+
+/// The largest frame written whole in a pcap file, longer frames are truncated.
+pub const PCAP_SNAPLEN: u32 = 262144;
+
+/// Writes raw frames as a pcap file, that can be opened in Wireshark or read back by the sniffer. The file is written in
+/// the byte order of the host, as libpcap does, with a record per frame holding its timestamp and its length.
+pub struct PcapWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Writes the global header of the file, whose frames have the given link layer.
+    pub fn new(mut writer: W, linktype: Linktype) -> io::Result<Self> {
+        writer.write_all(&PCAP_MAGIC.to_ne_bytes())?;
+        // version 2.4, timestamps in UTC and no accuracy
+        writer.write_all(&2u16.to_ne_bytes())?;
+        writer.write_all(&4u16.to_ne_bytes())?;
+        writer.write_all(&0i32.to_ne_bytes())?;
+        writer.write_all(&0u32.to_ne_bytes())?;
+        writer.write_all(&PCAP_SNAPLEN.to_ne_bytes())?;
+        writer.write_all(&(linktype.0 as u32).to_ne_bytes())?;
+        Ok(PcapWriter { writer })
+    }
+
+    /// Writes a frame captured at the given time.
+    pub fn write_packet(&mut self, data: &[u8], ts: &TimeVal) -> io::Result<()> {
+        let captured_len = usize::min(data.len(), PCAP_SNAPLEN as usize);
+        self.writer.write_all(&ts.sec.to_ne_bytes())?;
+        self.writer.write_all(&ts.u_sec.to_ne_bytes())?;
+        self.writer.write_all(&(captured_len as u32).to_ne_bytes())?;
+        self.writer.write_all(&(data.len() as u32).to_ne_bytes())?;
+        self.writer.write_all(&data[..captured_len])
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> { self.writer.flush() }
+
+    /// Flushes the frames written and returns the underlying writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Writes a pcapng block: its type, its body padded to 32 bits and the total length before and after it.
+fn write_pcapng_block<W: Write>(writer: &mut W, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let padding = (4 - body.len() % 4) % 4;
+    let total_len = (12 + body.len() + padding) as u32;
+    writer.write_all(&block_type.to_le_bytes())?;
+    writer.write_all(&total_len.to_le_bytes())?;
+    writer.write_all(body)?;
+    writer.write_all(&vec![0; padding])?;
+    writer.write_all(&total_len.to_le_bytes())
+}
+
+/// Writes the packets of a flow as a pcapng capture that can be opened in Wireshark. As the packets carry no raw data,
+/// every frame is rebuilt by FrameBuilder, keeping the remote endpoint, the protocol, the payload length, the TCP flags
+/// and the timestamp. The file is marked as reconstructed by a comment in its section header.
+pub fn export_reconstructed_pcap<W: Write>(packets: &[PacketInfo], writer: &mut W) -> io::Result<()> {
+    // section header: byte order magic, version 1.0, unknown section length and the comment option
+    let comment = RECONSTRUCTED_COMMENT.as_bytes();
+    let mut section = Vec::new();
+    section.extend_from_slice(&0x1a2b3c4du32.to_le_bytes());
+    section.extend_from_slice(&[1, 0, 0, 0]);
+    section.extend_from_slice(&(-1i64).to_le_bytes());
+    section.extend_from_slice(&1u16.to_le_bytes());
+    section.extend_from_slice(&(comment.len() as u16).to_le_bytes());
+    section.extend_from_slice(comment);
+    section.extend_from_slice(&vec![0; (4 - comment.len() % 4) % 4]);
+    section.extend_from_slice(&[0, 0, 0, 0]);
+    write_pcapng_block(writer, 0x0a0d0d0a, &section)?;
+
+    // a single ethernet interface, with the default microseconds resolution
+    let mut interface = Vec::new();
+    interface.extend_from_slice(&1u16.to_le_bytes());
+    interface.extend_from_slice(&[0, 0]);
+    interface.extend_from_slice(&0u32.to_le_bytes());
+    write_pcapng_block(writer, 0x00000001, &interface)?;
+
+    for info in packets {
+        let frame = info.to_synthetic_frame().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+        let ts: u64 = info.get_time_stamp().into();
+        let mut packet = Vec::with_capacity(20 + frame.len());
+        packet.extend_from_slice(&0u32.to_le_bytes());
+        packet.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+        packet.extend_from_slice(&(ts as u32).to_le_bytes());
+        packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        packet.extend_from_slice(&frame);
+        write_pcapng_block(writer, 0x00000006, &packet)?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::export::{*};
+    use crate::pkt_parser::{try_parse, Direction, Header, NetworkHeader, Protocol, TCPHeader, TransportHeader};
+
+    fn sample_packet() -> PacketInfo {
+        let data = vec![220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 0x12, 250, 240, 0, 0, 0, 0, 1, 2, 3, 4];
+        let (tcp_header_result, tcp_payload) = TCPHeader::decode(data);
+        let mut info = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, tcp_payload.len(), TimeVal::from(1657968204419346));
+        info.set_tcp_header(tcp_header_result.unwrap());
+        info
+    }
+
+    fn export_to_string<F: PacketFormatter>(formatter: &F) -> String {
+        let mut buffer: Vec<u8> = Vec::new();
+        export(&[sample_packet()], formatter, &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_csv_formatter() {
+        assert_eq!(export_to_string(&CsvFormatter),
+                   "address,port,protocol,bytes,timestamp\n149.154.167.92,443,TCP,4,1657968204.419346\n");
+    }
+
+    #[test]
+    fn test_json_formatter() {
+        assert_eq!(export_to_string(&JsonFormatter),
+                   "{\"address\":\"149.154.167.92\",\"port\":443,\"protocol\":\"TCP\",\"bytes\":4,\"timestamp\":1657968204.419346}\n");
+    }
+
+    #[test]
+    fn test_report_csv() {
+        let mut report = TrafficReport::new();
+        report.add(sample_packet());
+        report.add(PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 1400, TimeVal::from(1657968204519346)));
+        let mut buffer: Vec<u8> = Vec::new();
+        write_report_csv(&report, &mut buffer).unwrap();
+        let csv = String::from_utf8(buffer).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows, vec![
+            "address,port,protocol,bytes,packets,first_ts,last_ts",
+            "149.154.167.92,443,TCP,1404,2,2022-07-16 10:43:24.419346,2022-07-16 10:43:24.519346"
+        ]);
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_array() {
+        let mut info = sample_packet();
+        info.set_direction(Direction::Received);
+        let mut buffer: Vec<u8> = Vec::new();
+        export_json_array(&[info.clone(), info], &mut buffer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        let packets = json.as_array().unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0]["address"], "149.154.167.92");
+        assert_eq!(packets[0]["protocol"], "TCP");
+        assert_eq!(packets[0]["direction"], "Received");
+        assert_eq!(packets[0]["ts"]["sec"], 1657968204);
+    }
+
+    #[test]
+    fn test_tcpdump_formatter() {
+        assert_eq!(export_to_string(&TcpdumpFormatter), "10:43:24.419346 TCP 149.154.167.92.443: Flags [S.], length 4\n");
+    }
+
+    #[test]
+    fn test_custom_formatter() {
+        struct PortFormatter;
+        impl PacketFormatter for PortFormatter {
+            fn format(&self, info: &PacketInfo) -> String { info.get_port().to_string() }
+        }
+        let formatter: Box<dyn PacketFormatter> = Box::new(PortFormatter);
+        let mut buffer: Vec<u8> = Vec::new();
+        export(&[sample_packet()], formatter.as_ref(), &mut buffer).unwrap();
+        assert_eq!(buffer, b"443\n");
+    }
+
+    #[test]
+    fn test_pcap_writer() {
+        let frames = vec![
+            (vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0], TimeVal::from(1657968204419346)),
+            (vec![0xff; 60], TimeVal::from(1657968205000001)),
+        ];
+        let mut writer = PcapWriter::new(Vec::new(), Linktype::ETHERNET).unwrap();
+        for (data, ts) in &frames {
+            writer.write_packet(data, ts).unwrap();
+        }
+        let buffer = writer.into_inner().unwrap();
+
+        let read_u32 = |offset: usize| u32::from_ne_bytes([buffer[offset], buffer[offset + 1], buffer[offset + 2], buffer[offset + 3]]);
+        assert_eq!(read_u32(0), PCAP_MAGIC);
+        assert_eq!(read_u32(20), 1);
+        let mut offset = 24;
+        for (data, ts) in &frames {
+            assert_eq!(TimeVal { sec: read_u32(offset), u_sec: read_u32(offset + 4) }, *ts);
+            assert_eq!(read_u32(offset + 8) as usize, data.len());
+            assert_eq!(read_u32(offset + 12) as usize, data.len());
+            assert_eq!(&buffer[offset + 16..offset + 16 + data.len()], &data[..]);
+            offset += 16 + data.len();
+        }
+        assert_eq!(offset, buffer.len());
+    }
+
+    #[test]
+    fn test_reconstructed_pcap() {
+        let mut request = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 517, TimeVal::from(1657968204419346));
+        request.set_direction(Direction::Transmitted);
+        let mut answer = sample_packet();
+        answer.set_direction(Direction::Received);
+        let mut buffer: Vec<u8> = Vec::new();
+        export_reconstructed_pcap(&[request.clone(), answer.clone()], &mut buffer).unwrap();
+
+        let mut blocks = Vec::new();
+        let mut rest = &buffer[..];
+        while !rest.is_empty() {
+            let block_type = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+            let len = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+            assert_eq!(&rest[len - 4..len], &rest[4..8]);
+            blocks.push((block_type, &rest[8..len - 4]));
+            rest = &rest[len..];
+        }
+        assert_eq!(blocks.iter().map(|(block_type, _)| *block_type).collect::<Vec<u32>>(), vec![0x0a0d0d0a, 1, 6, 6]);
+        assert!(String::from_utf8_lossy(blocks[0].1).contains("Reconstructed"));
+
+        // back to the summaries, keeping the remote endpoint of every packet
+        let summaries: Vec<(String, u16, Protocol, usize, TimeVal)> = blocks[2..].iter().map(|(_, body)| {
+            let ts = ((u32::from_le_bytes([body[4], body[5], body[6], body[7]]) as u64) << 32) | u32::from_le_bytes([body[8], body[9], body[10], body[11]]) as u64;
+            let captured_len = u32::from_le_bytes([body[12], body[13], body[14], body[15]]) as usize;
+            let packet = try_parse(&body[20..20 + captured_len]).unwrap();
+            let (src, dest) = match packet.get_network_header() {
+                NetworkHeader::Ipv4(header) => (header.get_src_address(), header.get_dest_address()),
+                _ => panic!("not an ipv4 packet")
+            };
+            let (src_port, dest_port) = match packet.get_transport_header() {
+                Some(TransportHeader::Tcp(header)) => (header.get_src_port(), header.get_dest_port()),
+                _ => panic!("not a tcp segment")
+            };
+            let (address, port) = if dest == "149.154.167.92" { (dest, dest_port) } else { (src, src_port) };
+            (address, port, Protocol::TCP, packet.get_payload().len(), TimeVal::from(ts))
+        }).collect();
+        let expected: Vec<(String, u16, Protocol, usize, TimeVal)> = [request, answer].iter()
+            .map(|info| (info.get_address(), info.get_port(), info.get_protocol(), info.get_byte_transmitted(), info.get_time_stamp()))
+            .collect();
+        assert_eq!(summaries, expected);
+    }
+}
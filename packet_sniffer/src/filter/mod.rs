@@ -0,0 +1,554 @@
+//! filter
+//! This module defines a common way to select which packets are accounted in the sniffing report.
+//!
+//! A filter implements the PacketFilter trait, and several filters can be chained in a Pipeline: a packet is kept only if
+//! every filter accepts it. The filters available are:
+//! - FlowSampler: keeps only the first N packets of each flow
+//! - AddressFilter: keeps or drops the packets by the address of the remote host, it can also be translated to BPF so
+//!   that the unwanted packets are dropped by the kernel before being copied to the sniffer, unless its subnets contain
+//!   the addresses of the capturing host
+//! - PortFilter: keeps the packets whose remote port is in a list of ports and ranges, e.g. "80,443,8000-8100"
+//! - ProtocolFilter: keeps the packets of some level 4 protocols, it can be checked on the result of parse_headers_only
+//!   to skip the decoding of the unwanted frames
+//! - SubnetFilter: keeps the packets whose source or destination is in a subnet, e.g. "192.168.1.0/24"
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use std::str::FromStr;
+use crate::flow::FlowKey;
+use crate::pkt_parser::{PacketInfo, Protocol};
+
+/// A custom error returned when a filter cannot be built from the given values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterError {
+    pub msg: String
+}
+
+impl Display for FilterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Filter error: {}", self.msg)
+    }
+}
+
+/// The PacketFilter trait define a common behaviour. It requires an accept function that decides if a packet is kept,
+/// filters can update their own state while doing it.
+pub trait PacketFilter: Send {
+    fn accept(&mut self, info: &PacketInfo) -> bool;
+}
+
+/// A chain of filters, applied in the order they have been added.
+#[derive(Default)]
+pub struct Pipeline {
+    filters: Vec<Box<dyn PacketFilter>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline { filters: Vec::new() }
+    }
+
+    pub fn add_filter<F: PacketFilter + 'static>(&mut self, filter: F) {
+        self.filters.push(Box::new(filter));
+    }
+
+    pub fn len(&self) -> usize { self.filters.len() }
+    pub fn is_empty(&self) -> bool { self.filters.is_empty() }
+}
+
+impl PacketFilter for Pipeline {
+    /// A packet is accepted if all the filters accept it, the following filters are not run once a filter drops it.
+    fn accept(&mut self, info: &PacketInfo) -> bool {
+        self.filters.iter_mut().all(|f| f.accept(info))
+    }
+}
+
+/// Passes only the first N packets of every flow, so that the beginning of the conversations (handshakes,
+/// application headers) is kept while the bulk transfers are dropped.
+#[derive(Debug, Clone)]
+pub struct FlowSampler {
+    max_packets: usize,
+    counts: HashMap<FlowKey, usize>,
+}
+
+impl FlowSampler {
+    pub fn new(max_packets: usize) -> Self {
+        FlowSampler { max_packets, counts: HashMap::new() }
+    }
+
+    pub fn get_max_packets(&self) -> usize { self.max_packets }
+
+    /// Returns how many packets of the flow have been seen so far, including the dropped ones.
+    pub fn get_count(&self, key: &FlowKey) -> usize { self.counts.get(key).copied().unwrap_or(0) }
+}
+
+impl PacketFilter for FlowSampler {
+    fn accept(&mut self, info: &PacketInfo) -> bool {
+        let count = self.counts.entry(FlowKey::from(info)).or_insert(0);
+        *count += 1;
+        *count <= self.max_packets
+    }
+}
+
+/// Parses the address of a PacketInfo.
+pub(crate) fn parse_address(address: &str) -> Option<IpAddr> {
+    address.parse::<IpAddr>().ok()
+}
+
+/// A block of addresses, written as "192.168.1.0/24" or "2001:db8::/32". A single address is a block with the full prefix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Subnet {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl Subnet {
+    /// Creates the subnet, the host bits of the address are cleared.
+    pub fn new(address: IpAddr, prefix_len: u8) -> Result<Self, FilterError> {
+        let address = match address {
+            IpAddr::V4(v4) if prefix_len <= 32 => {
+                let mask = if prefix_len == 0 { 0 } else { u32::MAX << (32 - prefix_len) };
+                IpAddr::V4((u32::from(v4) & mask).into())
+            },
+            IpAddr::V6(v6) if prefix_len <= 128 => {
+                let mask = if prefix_len == 0 { 0 } else { u128::MAX << (128 - prefix_len) };
+                IpAddr::V6((u128::from(v6) & mask).into())
+            },
+            _ => return Err(FilterError{msg: format!("Invalid prefix length {} for the address {}", prefix_len, address)})
+        };
+        Ok(Subnet { address, prefix_len })
+    }
+
+    pub fn get_address(&self) -> IpAddr { self.address }
+    pub fn get_prefix_len(&self) -> u8 { self.prefix_len }
+
+    /// Returns true if the subnet is made of a single address.
+    pub fn is_host(&self) -> bool {
+        match self.address {
+            IpAddr::V4(_) => self.prefix_len == 32,
+            IpAddr::V6(_) => self.prefix_len == 128
+        }
+    }
+
+    pub fn contains(&self, address: &IpAddr) -> bool {
+        match Subnet::new(*address, self.prefix_len) {
+            Ok(subnet) => subnet.address == self.address,
+            // the address is of the other ip version
+            Err(_) => false
+        }
+    }
+
+    /// Renders the subnet as a BPF primitive, that matches both the source and the destination address.
+    fn to_bpf(self) -> String {
+        if self.is_host() { format!("host {}", self.address) } else { format!("net {}/{}", self.address, self.prefix_len) }
+    }
+}
+
+impl Display for Subnet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (s, None)
+        };
+        let address = match address.trim().parse::<IpAddr>() {
+            Ok(address) => address,
+            Err(_) => return Err(FilterError{msg: format!("Cannot parse the address {}", address)})
+        };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => match prefix_len.trim().parse::<u8>() {
+                Ok(prefix_len) => prefix_len,
+                Err(_) => return Err(FilterError{msg: format!("Cannot parse the prefix length {}", prefix_len)})
+            },
+            None => if address.is_ipv4() { 32 } else { 128 }
+        };
+        Subnet::new(address, prefix_len)
+    }
+}
+
+/// Selects the packets by the address of their remote host, with a list of allowed and a list of denied subnets.
+/// A packet is dropped if its address is in a denied subnet, even when it is also in an allowed one. Otherwise it is kept
+/// if the allow list is empty or contains its address.
+#[derive(Debug, Clone, Default)]
+pub struct AddressFilter {
+    allow: Vec<Subnet>,
+    deny: Vec<Subnet>,
+}
+
+impl AddressFilter {
+    /// Creates a filter that keeps every packet.
+    pub fn new() -> Self {
+        AddressFilter::default()
+    }
+
+    pub fn allow(&mut self, subnet: Subnet) -> &mut Self { self.allow.push(subnet); self }
+    pub fn deny(&mut self, subnet: Subnet) -> &mut Self { self.deny.push(subnet); self }
+
+    pub fn get_allowed(&self) -> &[Subnet] { &self.allow }
+    pub fn get_denied(&self) -> &[Subnet] { &self.deny }
+
+    /// Returns true if the filter has no rules, so it keeps every packet.
+    pub fn is_empty(&self) -> bool { self.allow.is_empty() && self.deny.is_empty() }
+
+    /// Decides if the packets of a host are kept.
+    pub fn is_allowed(&self, address: &IpAddr) -> bool {
+        if self.deny.iter().any(|subnet| subnet.contains(address)) {
+            return false
+        }
+        self.allow.is_empty() || self.allow.iter().any(|subnet| subnet.contains(address))
+    }
+
+    /// Translates the filter into a BPF program, to be set on the capture. It returns None if the filter has no rules.
+    /// The BPF primitives match both the source and the destination address, while accept only looks at the remote host:
+    /// the two agree only if no subnet contains the local addresses, e.g. denying the local network would drop every
+    /// packet in the kernel. In that case None is returned too, and the packets must be checked with accept.
+    pub fn to_bpf(&self, local_addresses: &[IpAddr]) -> Option<String> {
+        if self.allow.iter().chain(self.deny.iter()).any(|subnet| local_addresses.iter().any(|address| subnet.contains(address))) {
+            return None
+        }
+        let join = |subnets: &[Subnet]| subnets.iter().map(|s| s.to_bpf()).collect::<Vec<String>>().join(" or ");
+        match (self.allow.is_empty(), self.deny.is_empty()) {
+            (true, true) => None,
+            (false, true) => Some(format!("({})", join(&self.allow))),
+            (true, false) => Some(format!("not ({})", join(&self.deny))),
+            (false, false) => Some(format!("({}) and not ({})", join(&self.allow), join(&self.deny)))
+        }
+    }
+}
+
+impl PacketFilter for AddressFilter {
+    /// Packets whose address cannot be parsed are kept only if there is no allow list.
+    fn accept(&mut self, info: &PacketInfo) -> bool {
+        match parse_address(&info.get_address()) {
+            Some(address) => self.is_allowed(&address),
+            None => self.allow.is_empty()
+        }
+    }
+}
+
+/// Selects the packets by the port of their remote host, with a list of ranges of ports. A single port is a range with
+/// the same first and last port. A filter without ranges keeps every packet.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PortFilter {
+    ranges: Vec<(u16, u16)>,
+}
+
+impl PortFilter {
+    /// Creates a filter that keeps every packet.
+    pub fn new() -> Self {
+        PortFilter::default()
+    }
+
+    pub fn add_port(&mut self, port: u16) -> &mut Self { self.ranges.push((port, port)); self }
+
+    /// Adds the ports from first to last, both included.
+    pub fn add_range(&mut self, first: u16, last: u16) -> Result<&mut Self, FilterError> {
+        if first > last {
+            return Err(FilterError{msg: format!("Invalid port range {}-{}", first, last)})
+        }
+        self.ranges.push((first, last));
+        Ok(self)
+    }
+
+    pub fn get_ranges(&self) -> &[(u16, u16)] { &self.ranges }
+
+    pub fn is_empty(&self) -> bool { self.ranges.is_empty() }
+
+    pub fn matches(&self, port: u16) -> bool {
+        self.ranges.is_empty() || self.ranges.iter().any(|(first, last)| (*first..=*last).contains(&port))
+    }
+}
+
+impl Display for PortFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ranges = self.ranges.iter()
+            .map(|(first, last)| if first == last { first.to_string() } else { format!("{}-{}", first, last) })
+            .collect::<Vec<String>>();
+        write!(f, "{}", ranges.join(","))
+    }
+}
+
+impl FromStr for PortFilter {
+    type Err = FilterError;
+
+    /// Parses a comma separated list of ports and ranges, e.g. "80,443,8000-8100".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_port = |port: &str| port.trim().parse::<u16>()
+            .map_err(|_| FilterError{msg: format!("Cannot parse the port {}", port)});
+        let mut filter = PortFilter::new();
+        for item in s.split(',') {
+            match item.split_once('-') {
+                Some((first, last)) => { filter.add_range(parse_port(first)?, parse_port(last)?)?; },
+                None => { filter.add_port(parse_port(item)?); }
+            }
+        }
+        Ok(filter)
+    }
+}
+
+impl PacketFilter for PortFilter {
+    fn accept(&mut self, info: &PacketInfo) -> bool {
+        self.matches(info.get_port())
+    }
+}
+
+/// Selects the packets by their level 4 protocol. Protocol::Unknown is matched only when it is in the list, as any
+/// other protocol. A filter without protocols keeps every packet.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProtocolFilter {
+    protocols: Vec<Protocol>,
+}
+
+impl ProtocolFilter {
+    /// Creates a filter that keeps every packet.
+    pub fn new() -> Self {
+        ProtocolFilter::default()
+    }
+
+    pub fn add_protocol(&mut self, protocol: Protocol) -> &mut Self { self.protocols.push(protocol); self }
+
+    pub fn get_protocols(&self) -> &[Protocol] { &self.protocols }
+
+    pub fn is_empty(&self) -> bool { self.protocols.is_empty() }
+
+    pub fn matches(&self, protocol: &Protocol) -> bool {
+        self.protocols.is_empty() || self.protocols.contains(protocol)
+    }
+}
+
+impl From<Vec<Protocol>> for ProtocolFilter {
+    fn from(protocols: Vec<Protocol>) -> Self { ProtocolFilter { protocols } }
+}
+
+impl PacketFilter for ProtocolFilter {
+    fn accept(&mut self, info: &PacketInfo) -> bool {
+        self.matches(&info.get_protocol())
+    }
+}
+
+/// Selects the packets exchanged with a subnet, to monitor it: unlike AddressFilter, that looks only at the remote host,
+/// a packet is kept if either its source or its destination is in the subnet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubnetFilter {
+    subnet: Subnet,
+}
+
+impl SubnetFilter {
+    pub fn new(subnet: Subnet) -> Self {
+        SubnetFilter { subnet }
+    }
+
+    pub fn get_subnet(&self) -> Subnet { self.subnet }
+
+    pub fn contains(&self, ip: IpAddr) -> bool { self.subnet.contains(&ip) }
+}
+
+impl FromStr for SubnetFilter {
+    type Err = FilterError;
+
+    /// Parses a subnet in the CIDR notation, e.g. "192.168.1.0/24" or "2001:db8::/32".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(SubnetFilter::new(s.parse::<Subnet>()?))
+    }
+}
+
+impl PacketFilter for SubnetFilter {
+    /// When the endpoints of the packet are not known, only the address of the remote host is checked.
+    fn accept(&mut self, info: &PacketInfo) -> bool {
+        let addresses = match (info.get_src(), info.get_dest()) {
+            (Some(src), Some(dest)) => vec![src.get_address(), dest.get_address()],
+            _ => vec![info.get_address()]
+        };
+        addresses.iter().filter_map(|address| parse_address(address)).any(|address| self.contains(address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{*};
+    use crate::pkt_parser::{parse_headers_only, Endpoint, TimeVal};
+
+    #[test]
+    fn test_flow_sampler() {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_filter(FlowSampler::new(3));
+
+        let passed = (0..10)
+            .map(|i| PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 1400, TimeVal::from(1_000_000 + i)))
+            .filter(|info| pipeline.accept(info))
+            .map(|info| info.get_time_stamp())
+            .collect::<Vec<TimeVal>>();
+        assert_eq!(passed, vec![TimeVal::from(1_000_000), TimeVal::from(1_000_001), TimeVal::from(1_000_002)]);
+
+        // other flows have their own budget
+        assert!(pipeline.accept(&PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(1_000_010))));
+    }
+
+    #[test]
+    fn test_flow_sampler_counts() {
+        let mut sampler = FlowSampler::new(3);
+        let info = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 1400, TimeVal::from(1_000_000));
+        for _ in 0..10 { sampler.accept(&info); }
+        assert_eq!(sampler.get_count(&FlowKey::from(&info)), 10);
+        assert_eq!(sampler.get_count(&FlowKey::new("192.168.1.1".to_string(), 53, Protocol::UDP)), 0);
+    }
+
+    #[test]
+    fn test_address_filter() {
+        let mut filter = AddressFilter::new();
+        filter.allow("192.168.1.0/24".parse().unwrap()).deny("192.168.1.1".parse().unwrap());
+        assert_eq!(filter.to_bpf(&[]), Some("(net 192.168.1.0/24) and not (host 192.168.1.1)".to_string()));
+
+        let packet = |address: &str| PacketInfo::new(address.to_string(), 443, Protocol::TCP, 100, TimeVal::from(1_000_000));
+        assert!(filter.accept(&packet("192.168.1.21")));
+        assert!(filter.accept(&packet("192.168.1.254")));
+        // the deny entry wins over the allow list
+        assert!(!filter.accept(&packet("192.168.1.1")));
+        assert!(!filter.accept(&packet("149.154.167.92")));
+        assert!(!filter.accept(&packet("fe80::1")));
+
+        let mut filter = AddressFilter::new();
+        filter.deny("fe80::/10".parse().unwrap());
+        assert_eq!(filter.to_bpf(&[]), Some("not (net fe80::/10)".to_string()));
+        assert!(!filter.accept(&packet("fe80::1")));
+        assert!(filter.accept(&packet("2001:db8::1")));
+        assert!(filter.accept(&packet("149.154.167.92")));
+        assert!(AddressFilter::new().to_bpf(&[]).is_none());
+    }
+
+    /// Evaluates a program made by AddressFilter::to_bpf as libpcap does: host and net match either address.
+    fn bpf_matches(program: &str, src: IpAddr, dest: IpAddr) -> bool {
+        let any = |primitives: &str| primitives.trim_start_matches('(').trim_end_matches(')').split(" or ").any(|primitive| {
+            let subnet = primitive.trim_start_matches("host ").trim_start_matches("net ").parse::<Subnet>().unwrap();
+            subnet.contains(&src) || subnet.contains(&dest)
+        });
+        match program.split_once(" and not ") {
+            Some((allow, deny)) => any(allow) && !any(deny),
+            None => match program.strip_prefix("not ") {
+                Some(deny) => !any(deny),
+                None => any(program)
+            }
+        }
+    }
+
+    #[test]
+    fn test_address_filter_bpf_agrees_with_accept() {
+        let local: IpAddr = "192.168.1.21".parse().unwrap();
+        let remotes = ["192.168.1.1", "149.154.167.92", "149.154.1.1", "8.8.8.8"];
+        let mut filters = [AddressFilter::new(), AddressFilter::new(), AddressFilter::new()];
+        filters[0].deny("149.154.0.0/16".parse().unwrap());
+        filters[1].allow("149.154.0.0/16".parse().unwrap()).allow("8.8.8.8".parse().unwrap()).deny("149.154.167.92".parse().unwrap());
+        filters[2].allow("192.168.1.1".parse().unwrap());
+        for filter in filters.iter_mut() {
+            let program = filter.to_bpf(&[local]).unwrap();
+            for remote in remotes {
+                let remote: IpAddr = remote.parse().unwrap();
+                let accepted = filter.accept(&PacketInfo::new(remote.to_string(), 443, Protocol::TCP, 100, TimeVal::from(0)));
+                assert_eq!(bpf_matches(&program, local, remote), accepted, "{} sent to {}", program, remote);
+                assert_eq!(bpf_matches(&program, remote, local), accepted, "{} received from {}", program, remote);
+            }
+        }
+
+        // denying the local network would drop every packet in the kernel, while accept keeps those of the other hosts
+        let mut filter = AddressFilter::new();
+        filter.deny("192.168.1.0/24".parse().unwrap());
+        assert!(!bpf_matches(&filter.to_bpf(&[]).unwrap(), local, "8.8.8.8".parse().unwrap()));
+        assert!(filter.accept(&PacketInfo::new("8.8.8.8".to_string(), 53, Protocol::UDP, 100, TimeVal::from(0))));
+        assert_eq!(filter.to_bpf(&[local]), None);
+        // the same for an allow entry, it would pass every packet
+        let mut filter = AddressFilter::new();
+        filter.allow("fe80::/10".parse().unwrap());
+        assert_eq!(filter.to_bpf(&[local, "fe80::1".parse().unwrap()]), None);
+        assert!(filter.to_bpf(&[local]).is_some());
+    }
+
+    #[test]
+    fn test_subnet_parsing() {
+        let subnet: Subnet = "10.1.2.3/8".parse().unwrap();
+        assert_eq!(subnet.get_address(), "10.0.0.0".parse::<IpAddr>().unwrap());
+        assert!(subnet.contains(&"10.255.0.1".parse().unwrap()));
+        assert!(!subnet.contains(&"::1".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<Subnet>().is_err());
+        assert!("10.0.0.0/x".parse::<Subnet>().is_err());
+        assert!("localhost".parse::<Subnet>().is_err());
+        assert!("0.0.0.0/0".parse::<Subnet>().unwrap().contains(&"8.8.8.8".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_port_filter() {
+        let filter: PortFilter = "80, 443,8000-8100".parse().unwrap();
+        assert_eq!(filter.get_ranges(), &[(80, 80), (443, 443), (8000, 8100)]);
+        assert_eq!(filter.to_string(), "80,443,8000-8100");
+        assert!(filter.matches(80));
+        assert!(filter.matches(443));
+        assert!(!filter.matches(81));
+        // the bounds of the range are included
+        assert!(filter.matches(8000));
+        assert!(filter.matches(8100));
+        assert!(!filter.matches(7999));
+        assert!(!filter.matches(8101));
+
+        let mut filter = filter;
+        assert!(filter.accept(&PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 100, TimeVal::from(0))));
+        assert!(!filter.accept(&PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 100, TimeVal::from(0))));
+        assert!(PortFilter::new().matches(53));
+        assert!("0-65535".parse::<PortFilter>().unwrap().matches(65535));
+
+        assert!("8100-8000".parse::<PortFilter>().is_err());
+        assert!("80,,443".parse::<PortFilter>().is_err());
+        assert!("65536".parse::<PortFilter>().is_err());
+        assert!("http".parse::<PortFilter>().is_err());
+    }
+
+    #[test]
+    fn test_protocol_filter() {
+        let mut filter = ProtocolFilter::from(vec![Protocol::TCP]);
+        assert!(filter.matches(&Protocol::TCP));
+        assert!(!filter.matches(&Protocol::Unknown));
+        assert!(filter.accept(&PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 100, TimeVal::from(0))));
+        assert!(!filter.accept(&PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 100, TimeVal::from(0))));
+
+        // the DNS response of test_whole_packet_1 is dropped before building its PacketInfo
+        let data = [80, 235, 113, 35, 142, 103, 152, 0, 106, 4, 85, 32, 8, 0, 69, 0, 0, 36, 170, 10, 64, 0, 64, 17, 12, 250, 192, 168, 1, 1, 192, 168, 1, 21, 0, 53, 234, 64, 0, 16, 71, 245, 212, 212, 129, 131, 0, 1];
+        assert!(!filter.matches(&parse_headers_only(&data).unwrap().get_protocol()));
+
+        filter.add_protocol(Protocol::Unknown);
+        assert!(filter.matches(&Protocol::Unknown));
+        assert!(!filter.matches(&Protocol::ICMP));
+        assert!(ProtocolFilter::new().matches(&Protocol::ICMPv6));
+    }
+
+    #[test]
+    fn test_subnet_filter() {
+        let mut filter = "192.168.1.0/24".parse::<SubnetFilter>().unwrap();
+        assert!(filter.contains("192.168.1.21".parse().unwrap()));
+        assert!(!filter.contains("149.154.167.92".parse().unwrap()));
+        // the boundaries of the prefix
+        assert!(filter.contains("192.168.1.0".parse().unwrap()));
+        assert!(filter.contains("192.168.1.255".parse().unwrap()));
+        assert!(!filter.contains("192.168.0.255".parse().unwrap()));
+        assert!(!filter.contains("192.168.2.0".parse().unwrap()));
+        assert!(!filter.contains("fe80::1".parse().unwrap()));
+
+        let filter_v6 = "2001:db8::/32".parse::<SubnetFilter>().unwrap();
+        assert!(filter_v6.contains("2001:db8:ffff::1".parse().unwrap()));
+        assert!(!filter_v6.contains("2001:db9::".parse().unwrap()));
+        assert!("192.168.1.0/33".parse::<SubnetFilter>().is_err());
+
+        // a packet sent from the subnet to a remote host is kept by its source
+        let mut info = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 100, TimeVal::from(0));
+        assert!(!filter.accept(&info));
+        info.set_endpoints(Endpoint::new("192.168.1.21".to_string(), 50000), Endpoint::new("149.154.167.92".to_string(), 443));
+        assert!(filter.accept(&info));
+        info.set_endpoints(Endpoint::new("10.0.0.1".to_string(), 50000), Endpoint::new("149.154.167.92".to_string(), 443));
+        assert!(!filter.accept(&info));
+    }
+}
@@ -0,0 +1,986 @@
+//! flow
+//! This module keeps track of the flows observed during a sniffing. A flow is identified, as in the sniffer report, by the
+//! remote address and port of a packet together with its level 4 protocol.
+//!
+//! For every flow the table collects the number of packets and bytes, the first and last timestamp and, for TCP flows,
+//! how many segments carried each of the main control flags and how many bytes are in flight, that is sent but not yet
+//! acknowledged, in both the directions. The zero window stalls, when an endpoint stops the transfer because it cannot
+//! receive more data, are timed too, and the ones lasting more than a threshold are reported as events.
+//!
+//! The two halves of every conversation are accounted separately: the bytes sent by the local and the remote host, and
+//! so the bytes sent by the client (the endpoint that sent the SYN or, without it, the first packet) and by the server.
+//!
+//! When the direction of the packets is known, the table can also point out the unidirectional flows, whose packets all go
+//! the same way: they usually reveal asymmetric routing or a span port that mirrors only one direction, and make any
+//! analysis of those flows unreliable.
+//!
+//! The DSCP values marked on the packets of every flow are collected too: a flow should keep the same marking from the
+//! beginning to the end, so a flow with more values points to a device remarking it along the path.
+//!
+//! The Explicit Congestion Notification is followed end to end: whether the endpoints negotiated it in the handshake, how
+//! many packets the routers marked as congested and how many times the receiver echoed the marks (ECE) and the sender
+//! reduced its window in response (CWR).
+//!
+//! A flow is labelled with the application it belongs to, taken from the first application message recognized in the
+//! payloads passed to update_with_payload, so that the report does not show bare ports:
+//! - the server name of a TLS ClientHello
+//! - the domain of a DNS query, on port 53
+//! - the host of an HTTP request
+//!
+//! The label is part of the FlowSummary of the flow, a compact view of its statistics.
+//!
+//! For TCP flows the goodput, the payload bytes acknowledged per second, is measured over a sliding window: unlike the
+//! bytes on the wire it counts every byte once, however many times it was retransmitted, and leaves out the headers.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use crate::pkt_parser::{Direction, Ecn, PacketInfo, Protocol, SliceHeader, TCPHeader, TimeVal};
+use crate::pkt_parser::dns::DnsMessage;
+use crate::pkt_parser::tls::client_hello_server_name;
+
+/// The key that identifies a flow inside the FlowTable.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlowKey {
+    address: String,
+    port: u16,
+    protocol: Protocol,
+}
+
+impl FlowKey {
+    pub fn new(address: String, port: u16, protocol: Protocol) -> Self {
+        FlowKey { address, port, protocol }
+    }
+
+    pub fn get_address(&self) -> String { self.address.clone() }
+    pub fn get_port(&self) -> u16 { self.port }
+    pub fn get_protocol(&self) -> Protocol { self.protocol.clone() }
+}
+
+impl From<&PacketInfo> for FlowKey {
+    fn from(info: &PacketInfo) -> Self {
+        FlowKey::new(info.get_address(), info.get_port(), info.get_protocol())
+    }
+}
+
+/// The key aggregating the packets of a connection, e.g. to sum their bytes in a HashMap: the remote address and port
+/// with the protocol, as the FlowTable does.
+pub type ConnectionKey = FlowKey;
+
+/// Tracks the data sent by one of the two endpoints of a TCP connection: the sequence number following the last byte
+/// sent and the last acknowledgment received from the peer. It also keeps when the endpoint started advertising a zero window.
+#[derive(Debug, Clone, Default)]
+struct TcpSender {
+    first_seq: Option<u32>,
+    next_seq: Option<u32>,
+    acked: Option<u32>,
+    max_in_flight: u32,
+    fin_seq: Option<u32>,
+    zero_window_since: Option<TimeVal>,
+}
+
+impl TcpSender {
+    /// Sequence numbers wrap around, so a is after b if the distance from b to a is less than half of the space.
+    fn is_after(a: u32, b: u32) -> bool {
+        (a.wrapping_sub(b) as i32) > 0
+    }
+
+    fn send(&mut self, tcp_header: &TCPHeader, len: usize) {
+        let flags = tcp_header.get_flags();
+        // SYN and FIN take up one sequence number each
+        let seq_len = len as u32 + flags.is_syn() as u32 + flags.is_fin() as u32;
+        let seq_end = tcp_header.get_seq_number().wrapping_add(seq_len);
+        if self.first_seq.is_none() { self.first_seq = Some(tcp_header.get_seq_number()) }
+        if flags.is_fin() { self.fin_seq = Some(seq_end.wrapping_sub(1)) }
+        match self.next_seq {
+            Some(next_seq) if !TcpSender::is_after(seq_end, next_seq) => {}
+            _ => self.next_seq = Some(seq_end)
+        }
+        self.update_max();
+    }
+
+    /// Takes the acknowledgment of the peer, returning how many payload bytes it acknowledges for the first time.
+    fn ack(&mut self, ack_number: u32) -> u32 {
+        match self.acked {
+            Some(acked) if !TcpSender::is_after(ack_number, acked) => 0,
+            Some(acked) => {
+                self.acked = Some(ack_number);
+                // the FIN takes up a sequence number but carries no data
+                let fin_acked = self.fin_seq.map(|fin_seq| !TcpSender::is_after(acked, fin_seq) && TcpSender::is_after(ack_number, fin_seq));
+                ack_number.wrapping_sub(acked) - fin_acked.unwrap_or(false) as u32
+            },
+            // the first acknowledgment seen covers the SYN, or data sent before the capture started
+            None => { self.acked = Some(ack_number); 0 }
+        }
+    }
+
+    fn in_flight(&self) -> u32 {
+        // until the first acknowledgment everything sent since the first segment seen is in flight
+        match (self.next_seq, self.acked.or(self.first_seq)) {
+            (Some(next_seq), Some(acked)) if TcpSender::is_after(next_seq, acked) => next_seq.wrapping_sub(acked),
+            _ => 0
+        }
+    }
+
+    fn update_max(&mut self) {
+        let in_flight = self.in_flight();
+        if in_flight > self.max_in_flight { self.max_in_flight = in_flight }
+    }
+
+    /// Follows the window advertised by the endpoint, returning how long the window stayed closed when it is opened again.
+    fn advertise_window(&mut self, window_size: u16, ts: &TimeVal) -> Option<(TimeVal, Duration)> {
+        match (&self.zero_window_since, window_size) {
+            (None, 0) => { self.zero_window_since = Some(ts.clone()); None },
+            (Some(_), 0) | (None, _) => None,
+            (Some(start), _) => {
+                let (end, start): (u64, u64) = (ts.clone().into(), start.clone().into());
+                let duration = Duration::from_micros(end.saturating_sub(start));
+                self.zero_window_since.take().map(|start| (start, duration))
+            }
+        }
+    }
+}
+
+/// describes a zero window stall: the endpoint sending the segments in the given direction advertised a zero window
+/// (so Transmitted means that the local host stopped receiving) from start, for the given duration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowStall {
+    direction: Direction,
+    start: TimeVal,
+    duration: Duration,
+}
+
+impl WindowStall {
+    pub fn get_direction(&self) -> Direction { self.direction.clone() }
+    pub fn get_start(&self) -> TimeVal { self.start.clone() }
+    pub fn get_duration(&self) -> Duration { self.duration }
+}
+
+/// The event raised by the FlowTable when a flow stays stalled longer than the threshold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowStallEvent {
+    key: FlowKey,
+    stall: WindowStall,
+}
+
+impl WindowStallEvent {
+    pub fn get_key(&self) -> FlowKey { self.key.clone() }
+    pub fn get_stall(&self) -> WindowStall { self.stall.clone() }
+}
+
+/// The application a flow belongs to: the application protocol and the name found in its first message, as the host of
+/// an HTTP request, the server name of a TLS ClientHello or the domain of a DNS query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppLabel {
+    protocol: String,
+    name: String,
+}
+
+impl AppLabel {
+    pub fn new(protocol: String, name: String) -> Self {
+        AppLabel { protocol, name }
+    }
+
+    pub fn get_protocol(&self) -> String { self.protocol.clone() }
+    pub fn get_name(&self) -> String { self.name.clone() }
+}
+
+impl Display for AppLabel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} → {}", self.protocol, self.name)
+    }
+}
+
+/// The port of the DNS servers.
+const DNS_PORT: u16 = 53;
+
+/// Returns the host of the HTTP request at the beginning of payload, from its Host header.
+fn http_request_host(payload: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(payload).ok()?;
+    let mut lines = text.split("\r\n");
+    // the request line is made of the method, the target and the version
+    let request_line = lines.next()?;
+    let mut parts = request_line.split(' ');
+    let (method, _target, version) = (parts.next()?, parts.next()?, parts.next()?);
+    if method.is_empty() || !method.bytes().all(|b| b.is_ascii_uppercase()) || !version.starts_with("HTTP/") {
+        return None
+    }
+    lines.take_while(|line| !line.is_empty()).find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if name.trim().eq_ignore_ascii_case("host") { Some(value.trim().to_string()) } else { None }
+    })
+}
+
+/// Returns the label of the application whose message is at the beginning of payload, if it is a recognized one.
+fn recognize_app(info: &PacketInfo, payload: &[u8]) -> Option<AppLabel> {
+    let dns_port = info.get_port() == DNS_PORT || [info.get_src(), info.get_dest()].iter().flatten().any(|endpoint| endpoint.get_port() == DNS_PORT);
+    match info.get_protocol() {
+        Protocol::TCP => {
+            if let Some(server_name) = client_hello_server_name(payload) {
+                return Some(AppLabel::new("TLS".to_string(), server_name))
+            }
+            if let Some(host) = http_request_host(payload) {
+                return Some(AppLabel::new("HTTP".to_string(), host))
+            }
+            // over TCP the DNS messages are prefixed by their length
+            if dns_port && payload.len() > 2 {
+                return DnsMessage::decode_slice(&payload[2..]).0.ok()?.get_query_name().map(|name| AppLabel::new("DNS".to_string(), name))
+            }
+            None
+        },
+        Protocol::UDP if dns_port => {
+            DnsMessage::decode_slice(payload).0.ok()?.get_query_name().map(|name| AppLabel::new("DNS".to_string(), name))
+        },
+        _ => None
+    }
+}
+
+/// describes a flow in short: its key, its traffic and the application it belongs to, if recognized.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowSummary {
+    key: FlowKey,
+    packets: usize,
+    bytes: usize,
+    first_ts: TimeVal,
+    last_ts: TimeVal,
+    app_label: Option<AppLabel>,
+}
+
+impl FlowSummary {
+    pub fn get_key(&self) -> FlowKey { self.key.clone() }
+    pub fn get_packets(&self) -> usize { self.packets }
+    pub fn get_bytes(&self) -> usize { self.bytes }
+    pub fn get_first_time_stamp(&self) -> TimeVal { self.first_ts.clone() }
+    pub fn get_last_time_stamp(&self) -> TimeVal { self.last_ts.clone() }
+    pub fn get_app_label(&self) -> Option<AppLabel> { self.app_label.clone() }
+}
+
+/// Formats the summary on a single line, e.g. "93.184.216.34:443/TCP (TLS → example.com), 12 packets, 5400 bytes".
+impl Display for FlowSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}/{}", self.key.address, self.key.port, self.key.protocol.to_string())?;
+        if let Some(label) = &self.app_label {
+            write!(f, " ({})", label)?;
+        }
+        write!(f, ", {} packets, {} bytes", self.packets, self.bytes)
+    }
+}
+
+/// The statistics collected for a single flow.
+#[derive(Debug, Clone)]
+pub struct FlowStats {
+    packets: usize,
+    bytes: usize,
+    first_ts: TimeVal,
+    last_ts: TimeVal,
+    syn_count: usize,
+    ack_count: usize,
+    fin_count: usize,
+    rst_count: usize,
+    transmitted_packets: usize,
+    received_packets: usize,
+    transmitted_bytes: usize,
+    received_bytes: usize,
+    client: Option<Direction>,
+    client_from_syn: bool,
+    dscp_values: BTreeSet<u8>,
+    window_stalls: Vec<WindowStall>,
+    app_label: Option<AppLabel>,
+    ce_count: usize,
+    ece_count: usize,
+    cwr_count: usize,
+    ecn_offered: bool,
+    ecn_accepted: bool,
+    acked_samples: VecDeque<(u64, u32)>,
+    goodput: f64,
+    peak_goodput: f64,
+    local_sender: TcpSender,
+    remote_sender: TcpSender,
+}
+
+impl FlowStats {
+    fn new(ts: TimeVal) -> Self {
+        FlowStats {
+            packets: 0, bytes: 0, first_ts: ts.clone(), last_ts: ts, syn_count: 0, ack_count: 0, fin_count: 0, rst_count: 0,
+            transmitted_packets: 0, received_packets: 0, transmitted_bytes: 0, received_bytes: 0, client: None, client_from_syn: false,
+            dscp_values: BTreeSet::new(), window_stalls: Vec::new(), app_label: None,
+            ce_count: 0, ece_count: 0, cwr_count: 0, ecn_offered: false, ecn_accepted: false,
+            acked_samples: VecDeque::new(), goodput: 0.0, peak_goodput: 0.0, local_sender: TcpSender::default(), remote_sender: TcpSender::default()
+        }
+    }
+
+    /// Returns the direction of the packet: the one set by the sniffer or, for TCP, the one implied by the ports.
+    pub(crate) fn get_side(info: &PacketInfo) -> Option<Direction> {
+        info.get_direction().or_else(|| info.get_tcp_header().map(|tcp_header| {
+            // the port of the flow is the remote one, so the segment comes from the remote host if it is its source port
+            if tcp_header.get_src_port() == info.get_port() { Direction::Received } else { Direction::Transmitted }
+        }))
+    }
+
+    fn sender(&self, direction: Direction) -> &TcpSender {
+        match direction {
+            Direction::Transmitted => &self.local_sender,
+            Direction::Received => &self.remote_sender
+        }
+    }
+
+    /// Measures the goodput over the window ending at the given time, in microseconds. The flows younger than the window
+    /// are measured from their first packet.
+    fn update_goodput(&mut self, now: u64, window: Duration) {
+        let window = window.as_micros() as u64;
+        while let Some((ts, _)) = self.acked_samples.front() {
+            if now.saturating_sub(*ts) < window { break }
+            self.acked_samples.pop_front();
+        }
+        let first_ts: u64 = self.first_ts.clone().into();
+        let span = window.min(now.saturating_sub(first_ts));
+        let acked: u64 = self.acked_samples.iter().map(|(_, bytes)| *bytes as u64).sum();
+        self.goodput = if span == 0 { 0.0 } else { acked as f64 * 1_000_000.0 / span as f64 };
+        if self.goodput > self.peak_goodput { self.peak_goodput = self.goodput }
+    }
+
+    /// Accounts the packet, returning the zero window stall it ends, if any. The goodput is measured over goodput_window.
+    fn update(&mut self, info: &PacketInfo, goodput_window: Duration) -> Option<WindowStall> {
+        self.packets += 1;
+        self.bytes += info.get_byte_transmitted();
+        self.last_ts = info.get_time_stamp();
+        let side = FlowStats::get_side(info);
+        match side {
+            Some(Direction::Transmitted) => {
+                self.transmitted_packets += 1;
+                self.transmitted_bytes += info.get_byte_transmitted();
+            },
+            Some(Direction::Received) => {
+                self.received_packets += 1;
+                self.received_bytes += info.get_byte_transmitted();
+            },
+            None => {}
+        }
+        if let Some(direction) = &side {
+            let is_connection_request = info.get_tcp_header().map(|h| h.get_flags().is_syn() && !h.get_flags().is_ack()).unwrap_or(false);
+            if is_connection_request && !self.client_from_syn {
+                self.client = Some(direction.clone());
+                self.client_from_syn = true;
+            } else if self.client.is_none() {
+                self.client = Some(direction.clone());
+            }
+        }
+        if let Some(dscp) = info.get_dscp() {
+            self.dscp_values.insert(dscp);
+        }
+        if info.get_ecn() == Some(Ecn::Ce) { self.ce_count += 1 }
+
+        if let (Some(tcp_header), Some(direction)) = (info.get_tcp_header(), side) {
+            let flags = tcp_header.get_flags();
+            if flags.is_syn() { self.syn_count += 1 }
+            if flags.is_ack() { self.ack_count += 1 }
+            if flags.is_fin() { self.fin_count += 1 }
+            if flags.is_rst() { self.rst_count += 1 }
+            // on the handshake ECE and CWR negotiate ECN (RFC 3168), afterwards they report the congestion
+            match (flags.is_syn(), flags.is_ack()) {
+                (true, false) => self.ecn_offered = flags.is_ece() && flags.is_cwr(),
+                (true, true) => self.ecn_accepted = flags.is_ece() && !flags.is_cwr(),
+                _ => {
+                    if flags.is_ece() { self.ece_count += 1 }
+                    if flags.is_cwr() { self.cwr_count += 1 }
+                }
+            }
+
+            let (sender, receiver) = match direction {
+                Direction::Received => (&mut self.remote_sender, &mut self.local_sender),
+                Direction::Transmitted => (&mut self.local_sender, &mut self.remote_sender)
+            };
+            sender.send(&tcp_header, info.get_byte_transmitted());
+            // a reset carries no meaningful window
+            let window_opened = if flags.is_rst() { None } else { sender.advertise_window(tcp_header.get_window_size(), &info.get_time_stamp()) };
+            if flags.is_ack() {
+                let acked = receiver.ack(tcp_header.get_ack_number());
+                receiver.update_max();
+                let now: u64 = info.get_time_stamp().into();
+                if acked > 0 { self.acked_samples.push_back((now, acked)) }
+                self.update_goodput(now, goodput_window);
+            }
+            if let Some((start, duration)) = window_opened {
+                let stall = WindowStall { direction, start, duration };
+                self.window_stalls.push(stall.clone());
+                return Some(stall);
+            }
+        }
+        None
+    }
+
+    pub fn get_packets(&self) -> usize { self.packets }
+    pub fn get_bytes(&self) -> usize { self.bytes }
+    pub fn get_first_time_stamp(&self) -> TimeVal { self.first_ts.clone() }
+    pub fn get_last_time_stamp(&self) -> TimeVal { self.last_ts.clone() }
+    pub fn get_syn_count(&self) -> usize { self.syn_count }
+    pub fn get_ack_count(&self) -> usize { self.ack_count }
+    pub fn get_fin_count(&self) -> usize { self.fin_count }
+    pub fn get_rst_count(&self) -> usize { self.rst_count }
+    /// Returns the packets sent by the local host, only the packets whose direction is known are counted.
+    pub fn get_transmitted_packets(&self) -> usize { self.transmitted_packets }
+    /// Returns the packets received by the local host, only the packets whose direction is known are counted.
+    pub fn get_received_packets(&self) -> usize { self.received_packets }
+    /// Returns the bytes sent by the local host, only the packets whose direction is known are counted.
+    pub fn get_transmitted_bytes(&self) -> usize { self.transmitted_bytes }
+    /// Returns the bytes received by the local host, only the packets whose direction is known are counted.
+    pub fn get_received_bytes(&self) -> usize { self.received_bytes }
+
+    /// Returns the direction of the packets sent by the client: Transmitted if the local host opened the conversation.
+    pub fn get_client_direction(&self) -> Option<Direction> { self.client.clone() }
+    /// Returns the bytes sent by the client of the conversation.
+    pub fn get_client_bytes(&self) -> usize {
+        match self.client {
+            Some(Direction::Transmitted) => self.transmitted_bytes,
+            Some(Direction::Received) => self.received_bytes,
+            None => 0
+        }
+    }
+    /// Returns the bytes sent by the server of the conversation.
+    pub fn get_server_bytes(&self) -> usize {
+        match self.client {
+            Some(Direction::Transmitted) => self.received_bytes,
+            Some(Direction::Received) => self.transmitted_bytes,
+            None => 0
+        }
+    }
+
+    /// Returns true if packets have been seen in one direction only.
+    pub fn is_unidirectional(&self) -> bool {
+        (self.transmitted_packets == 0) != (self.received_packets == 0)
+    }
+
+    /// Returns the DSCP values seen on the packets of the flow, sorted.
+    pub fn get_dscp_values(&self) -> Vec<u8> { self.dscp_values.iter().copied().collect() }
+
+    /// Returns true if the marking of the flow has changed, that is its packets carry more than one DSCP value.
+    pub fn is_dscp_remarked(&self) -> bool { self.dscp_values.len() > 1 }
+
+    /// Returns the bytes sent in the given direction and not acknowledged yet. Transmitted refers to the data sent by
+    /// the local host, Received to the data sent by the remote one.
+    pub fn get_bytes_in_flight(&self, direction: Direction) -> u32 { self.sender(direction).in_flight() }
+    /// Returns the highest number of bytes in flight observed so far in the given direction.
+    pub fn get_max_bytes_in_flight(&self, direction: Direction) -> u32 { self.sender(direction).max_in_flight }
+
+    /// Returns true if the handshake has negotiated ECN: the SYN offered it and the SYN-ACK accepted it.
+    pub fn is_ecn_negotiated(&self) -> bool { self.ecn_offered && self.ecn_accepted }
+    /// Returns the packets marked as Congestion Experienced by the routers along the path.
+    pub fn get_ce_count(&self) -> usize { self.ce_count }
+    /// Returns the segments, after the handshake, echoing a congestion mark to the peer (ECE).
+    pub fn get_ece_count(&self) -> usize { self.ece_count }
+    /// Returns the segments, after the handshake, telling the peer that the window has been reduced (CWR).
+    pub fn get_cwr_count(&self) -> usize { self.cwr_count }
+
+    /// Returns the application of the flow, if it has been recognized.
+    pub fn get_app_label(&self) -> Option<AppLabel> { self.app_label.clone() }
+    /// Returns the summary of the flow, that is identified by key.
+    pub fn summary(&self, key: &FlowKey) -> FlowSummary {
+        FlowSummary { key: key.clone(), packets: self.packets, bytes: self.bytes, first_ts: self.first_ts.clone(), last_ts: self.last_ts.clone(),
+            app_label: self.app_label.clone() }
+    }
+    /// Returns the payload bytes per second acknowledged over the goodput window ending at the last acknowledgment.
+    pub fn get_goodput(&self) -> f64 { self.goodput }
+    /// Returns the highest goodput measured since the beginning of the flow.
+    pub fn get_peak_goodput(&self) -> f64 { self.peak_goodput }
+
+    /// Returns the zero window stalls of the flow that have ended, whatever their duration.
+    pub fn get_window_stalls(&self) -> &[WindowStall] { &self.window_stalls }
+    /// Returns since when the window advertised in the given direction is zero, if it is currently closed.
+    pub fn get_zero_window_since(&self, direction: Direction) -> Option<TimeVal> { self.sender(direction).zero_window_since.clone() }
+}
+
+/// The statistics collected for a single remote host, summing all its flows.
+#[derive(Debug, Clone)]
+pub struct HostStats {
+    address: String,
+    hostname: Option<String>,
+    flows: usize,
+    packets: usize,
+    bytes: usize,
+}
+
+impl HostStats {
+    fn new(address: String) -> Self {
+        HostStats { address, hostname: None, flows: 0, packets: 0, bytes: 0 }
+    }
+
+    pub fn get_address(&self) -> String { self.address.clone() }
+    /// Returns the name of the host if it has been resolved, its address otherwise.
+    pub fn get_hostname(&self) -> String { self.hostname.clone().unwrap_or_else(|| self.address.clone()) }
+    pub fn set_hostname(&mut self, hostname: String) { self.hostname = Some(hostname) }
+    pub fn get_flows(&self) -> usize { self.flows }
+    pub fn get_packets(&self) -> usize { self.packets }
+    pub fn get_bytes(&self) -> usize { self.bytes }
+}
+
+/// A table of all the flows seen so far, updated packet by packet.
+#[derive(Debug, Clone)]
+pub struct FlowTable {
+    flows: HashMap<FlowKey, FlowStats>,
+    last_ts: Option<TimeVal>,
+    stall_threshold: Duration,
+    goodput_window: Duration,
+    stall_events: Vec<WindowStallEvent>,
+}
+
+impl Default for FlowTable {
+    fn default() -> Self {
+        FlowTable::new()
+    }
+}
+
+impl FlowTable {
+    /// Creates an empty table, that reports the zero window stalls lasting at least one second and measures the goodput
+    /// over 5 seconds.
+    pub fn new() -> Self {
+        FlowTable { flows: HashMap::new(), last_ts: None, stall_threshold: Duration::from_secs(1), goodput_window: Duration::from_secs(5),
+            stall_events: Vec::new() }
+    }
+
+    /// Accounts the packet to its flow, creating the flow if it is the first packet seen for it.
+    pub fn update(&mut self, info: &PacketInfo) {
+        self.update_with_payload(info, &[])
+    }
+
+    /// Accounts the packet as update does. Until the flow is labelled, its payload is checked for an application message,
+    /// whose application becomes the label of the flow.
+    pub fn update_with_payload(&mut self, info: &PacketInfo, payload: &[u8]) {
+        self.last_ts = Some(info.get_time_stamp());
+        let key = FlowKey::from(info);
+        let stats = self.flows.entry(key.clone()).or_insert_with(|| FlowStats::new(info.get_time_stamp()));
+        let stall = stats.update(info, self.goodput_window);
+        if stats.app_label.is_none() && !payload.is_empty() {
+            stats.app_label = recognize_app(info, payload);
+        }
+        if let Some(stall) = stall {
+            if stall.duration >= self.stall_threshold {
+                self.stall_events.push(WindowStallEvent { key, stall });
+            }
+        }
+    }
+
+    pub fn get_stall_threshold(&self) -> Duration { self.stall_threshold }
+    /// Sets the minimum duration of the zero window stalls reported as events.
+    pub fn set_stall_threshold(&mut self, threshold: Duration) { self.stall_threshold = threshold }
+
+    pub fn get_goodput_window(&self) -> Duration { self.goodput_window }
+    /// Sets the length of the sliding window over which the goodput of the flows is measured.
+    pub fn set_goodput_window(&mut self, window: Duration) { self.goodput_window = window }
+
+    /// Returns the window stall events raised so far, removing them from the table.
+    pub fn take_window_stalls(&mut self) -> Vec<WindowStallEvent> { std::mem::take(&mut self.stall_events) }
+
+    /// Labels the flow with its application. Only the first label is kept, the later ones are ignored: it returns true
+    /// if the label has been attached, false if the flow is unknown or already labelled.
+    pub fn set_app_label(&mut self, key: &FlowKey, label: AppLabel) -> bool {
+        match self.flows.get_mut(key) {
+            Some(stats) if stats.app_label.is_none() => {
+                stats.app_label = Some(label);
+                true
+            },
+            _ => false
+        }
+    }
+
+    pub fn get(&self, key: &FlowKey) -> Option<&FlowStats> { self.flows.get(key) }
+    pub fn len(&self) -> usize { self.flows.len() }
+    pub fn is_empty(&self) -> bool { self.flows.is_empty() }
+    pub fn iter(&self) -> impl Iterator<Item = (&FlowKey, &FlowStats)> { self.flows.iter() }
+
+    /// Returns the summaries of the flows, the largest ones first.
+    pub fn summaries(&self) -> Vec<FlowSummary> {
+        let mut summaries: Vec<FlowSummary> = self.flows.iter().map(|(key, stats)| stats.summary(key)).collect();
+        summaries.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.key.address.cmp(&b.key.address)).then_with(|| a.key.port.cmp(&b.key.port)));
+        summaries
+    }
+
+    /// Returns the flows whose DSCP marking has changed along the way.
+    pub fn dscp_remarked_flows(&self) -> Vec<FlowKey> {
+        let mut flows: Vec<FlowKey> = self.flows.iter()
+            .filter(|(_, stats)| stats.is_dscp_remarked())
+            .map(|(key, _)| key.clone())
+            .collect();
+        flows.sort_by(|a, b| (&a.address, a.port).cmp(&(&b.address, b.port)));
+        flows
+    }
+
+    /// Returns the flows that, after the grace period from their first packet, still have packets in one direction only.
+    /// The grace period is measured on the timestamps of the capture, up to the last packet seen by the table, so that
+    /// flows that just started and are still waiting for an answer are not reported.
+    pub fn unidirectional_flows(&self, grace_period: Duration) -> Vec<FlowKey> {
+        let now: u64 = match &self.last_ts {
+            Some(ts) => ts.clone().into(),
+            None => return Vec::new()
+        };
+        let grace_period = grace_period.as_micros() as u64;
+        let mut flows: Vec<FlowKey> = self.flows.iter()
+            .filter(|(_, stats)| stats.is_unidirectional())
+            .filter(|(_, stats)| now.saturating_sub(stats.get_first_time_stamp().into()) >= grace_period)
+            .map(|(key, _)| key.clone())
+            .collect();
+        flows.sort_by(|a, b| (&a.address, a.port).cmp(&(&b.address, b.port)));
+        flows
+    }
+
+    /// Aggregates the flows by remote host, the result is sorted by address.
+    pub fn hosts(&self) -> Vec<HostStats> {
+        let mut hosts: HashMap<String, HostStats> = HashMap::new();
+        for (key, stats) in self.flows.iter() {
+            let host = hosts.entry(key.get_address()).or_insert_with(|| HostStats::new(key.get_address()));
+            host.flows += 1;
+            host.packets += stats.get_packets();
+            host.bytes += stats.get_bytes();
+        }
+        let mut hosts: Vec<HostStats> = hosts.into_values().collect();
+        hosts.sort_by(|a, b| a.address.cmp(&b.address));
+        hosts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flow::{*};
+    use crate::pkt_parser::tls::client_hello_server_name;
+    use crate::pkt_parser::tls::tests::client_hello;
+    use crate::pkt_parser::{Ecn, Header, TCPHeader};
+
+    fn tcp_packet(flags: u8, ts: u64) -> PacketInfo {
+        let data = vec![220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, flags, 250, 240, 0, 0, 0, 0];
+        let (tcp_header_result, _tcp_payload) = TCPHeader::decode(data);
+        let mut info = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 0, TimeVal::from(ts));
+        info.set_tcp_header(tcp_header_result.unwrap());
+        info
+    }
+
+    #[test]
+    fn test_flag_counts_per_flow() {
+        let mut table = FlowTable::new();
+        // three way handshake, followed by a reset
+        table.update(&tcp_packet(0x02, 1_000_000));
+        table.update(&tcp_packet(0x12, 1_000_100));
+        table.update(&tcp_packet(0x10, 1_000_200));
+        table.update(&tcp_packet(0x04, 1_000_300));
+        // an unrelated UDP flow
+        table.update(&PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(1_000_400)));
+
+        assert_eq!(table.len(), 2);
+        let stats = table.get(&FlowKey::new("149.154.167.92".to_string(), 443, Protocol::TCP)).unwrap();
+        assert_eq!(stats.get_packets(), 4);
+        assert_eq!(stats.get_syn_count(), 2);
+        assert_eq!(stats.get_ack_count(), 2);
+        assert_eq!(stats.get_fin_count(), 0);
+        assert_eq!(stats.get_rst_count(), 1);
+        assert_eq!(stats.get_first_time_stamp(), TimeVal::from(1_000_000));
+        assert_eq!(stats.get_last_time_stamp(), TimeVal::from(1_000_300));
+
+        let udp_stats = table.get(&FlowKey::new("192.168.1.1".to_string(), 53, Protocol::UDP)).unwrap();
+        assert_eq!(udp_stats.get_bytes(), 110);
+        assert_eq!(udp_stats.get_syn_count(), 0);
+    }
+
+    /// Builds a segment of the connection between the local port 56369 and 149.154.167.92:443.
+    fn tcp_segment(from_remote: bool, seq: u32, ack: u32, flags: u8, len: usize) -> PacketInfo {
+        tcp_segment_with_window(from_remote, seq, ack, flags, len, 64240, 1_000_000)
+    }
+
+    fn tcp_segment_with_window(from_remote: bool, seq: u32, ack: u32, flags: u8, len: usize, window: u16, ts: u64) -> PacketInfo {
+        let (src, dest): (u16, u16) = if from_remote { (443, 56369) } else { (56369, 443) };
+        let mut data = Vec::new();
+        data.extend_from_slice(&src.to_be_bytes());
+        data.extend_from_slice(&dest.to_be_bytes());
+        data.extend_from_slice(&seq.to_be_bytes());
+        data.extend_from_slice(&ack.to_be_bytes());
+        data.extend_from_slice(&[80, flags]);
+        data.extend_from_slice(&window.to_be_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        let (tcp_header_result, _tcp_payload) = TCPHeader::decode(data);
+        let mut info = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, len, TimeVal::from(ts));
+        info.set_tcp_header(tcp_header_result.unwrap());
+        info
+    }
+
+    #[test]
+    fn test_bytes_in_flight() {
+        let key = FlowKey::new("149.154.167.92".to_string(), 443, Protocol::TCP);
+        let mut table = FlowTable::new();
+        table.update(&tcp_segment(false, 1000, 0, 0x02, 0));
+        table.update(&tcp_segment(true, 5000, 1001, 0x12, 0));
+        table.update(&tcp_segment(false, 1001, 5001, 0x10, 0));
+        assert_eq!(table.get(&key).unwrap().get_bytes_in_flight(Direction::Transmitted), 0);
+        assert_eq!(table.get(&key).unwrap().get_bytes_in_flight(Direction::Received), 0);
+
+        // two data segments sent, then acknowledged one at a time
+        table.update(&tcp_segment(false, 1001, 5001, 0x18, 1000));
+        table.update(&tcp_segment(false, 2001, 5001, 0x18, 500));
+        assert_eq!(table.get(&key).unwrap().get_bytes_in_flight(Direction::Transmitted), 1500);
+        table.update(&tcp_segment(true, 5001, 2001, 0x10, 0));
+        assert_eq!(table.get(&key).unwrap().get_bytes_in_flight(Direction::Transmitted), 500);
+        // a retransmission does not add bytes in flight
+        table.update(&tcp_segment(false, 2001, 5001, 0x18, 500));
+        assert_eq!(table.get(&key).unwrap().get_bytes_in_flight(Direction::Transmitted), 500);
+        // the answer of the remote host acknowledges the rest of the data
+        table.update(&tcp_segment(true, 5001, 2501, 0x18, 300));
+
+        let stats = table.get(&key).unwrap();
+        assert_eq!(stats.get_bytes_in_flight(Direction::Transmitted), 0);
+        assert_eq!(stats.get_max_bytes_in_flight(Direction::Transmitted), 1500);
+        assert_eq!(stats.get_bytes_in_flight(Direction::Received), 300);
+        assert_eq!(stats.get_max_bytes_in_flight(Direction::Received), 300);
+    }
+
+    #[test]
+    fn test_bytes_in_flight_follows_the_direction() {
+        // a BGP session, both the hosts use the port 179 so the direction cannot be told by the ports
+        let segment = |direction: Direction, seq: u32, ack: u32, flags: u8, len: usize| {
+            let mut data = vec![0, 179, 0, 179];
+            data.extend_from_slice(&seq.to_be_bytes());
+            data.extend_from_slice(&ack.to_be_bytes());
+            data.extend_from_slice(&[80, flags, 250, 240, 0, 0, 0, 0]);
+            let mut info = PacketInfo::new("192.168.1.1".to_string(), 179, Protocol::TCP, len, TimeVal::from(1_000_000));
+            info.set_tcp_header(TCPHeader::decode(data).0.unwrap());
+            info.set_direction(direction);
+            info
+        };
+        let key = FlowKey::new("192.168.1.1".to_string(), 179, Protocol::TCP);
+        let mut table = FlowTable::new();
+        table.update(&segment(Direction::Transmitted, 1000, 0, 0x02, 0));
+        table.update(&segment(Direction::Received, 5000, 1001, 0x12, 0));
+        table.update(&segment(Direction::Transmitted, 1001, 5001, 0x18, 700));
+        assert_eq!(table.get(&key).unwrap().get_bytes_in_flight(Direction::Transmitted), 700);
+        assert_eq!(table.get(&key).unwrap().get_bytes_in_flight(Direction::Received), 0);
+        table.update(&segment(Direction::Received, 5001, 1701, 0x10, 0));
+        let stats = table.get(&key).unwrap();
+        assert_eq!(stats.get_bytes_in_flight(Direction::Transmitted), 0);
+        assert_eq!(stats.get_max_bytes_in_flight(Direction::Transmitted), 700);
+        assert_eq!(stats.get_client_direction(), Some(Direction::Transmitted));
+    }
+
+    #[test]
+    fn test_zero_window_stall() {
+        let key = FlowKey::new("149.154.167.92".to_string(), 443, Protocol::TCP);
+        let mut table = FlowTable::new();
+        table.set_stall_threshold(Duration::from_millis(500));
+        table.update(&tcp_segment_with_window(true, 5001, 1001, 0x18, 1400, 64240, 1_000_000));
+        // the local host cannot keep up and closes its window for 750ms
+        table.update(&tcp_segment_with_window(false, 1001, 6401, 0x10, 0, 0, 1_000_100));
+        table.update(&tcp_segment_with_window(false, 1001, 6401, 0x10, 0, 0, 1_300_000));
+        assert_eq!(table.get(&key).unwrap().get_zero_window_since(Direction::Transmitted), Some(TimeVal::from(1_000_100)));
+        table.update(&tcp_segment_with_window(false, 1001, 6401, 0x10, 0, 8192, 1_750_100));
+
+        let stall = WindowStall { direction: Direction::Transmitted, start: TimeVal::from(1_000_100), duration: Duration::from_millis(750) };
+        assert_eq!(table.get(&key).unwrap().get_window_stalls(), std::slice::from_ref(&stall));
+        assert_eq!(table.get(&key).unwrap().get_zero_window_since(Direction::Transmitted), None);
+        assert_eq!(table.take_window_stalls(), vec![WindowStallEvent { key: key.clone(), stall }]);
+
+        // a shorter stall is recorded but not reported
+        table.update(&tcp_segment_with_window(false, 1001, 6401, 0x10, 0, 0, 2_000_000));
+        table.update(&tcp_segment_with_window(false, 1001, 6401, 0x10, 0, 8192, 2_100_000));
+        assert_eq!(table.get(&key).unwrap().get_window_stalls().len(), 2);
+        assert!(table.take_window_stalls().is_empty());
+    }
+
+    #[test]
+    fn test_client_and_server_bytes() {
+        let key = FlowKey::new("149.154.167.92".to_string(), 443, Protocol::TCP);
+        let mut table = FlowTable::new();
+        // the first segment seen is an answer of the server, but the SYN decides who the client is
+        table.update(&tcp_segment(true, 5000, 1001, 0x12, 0));
+        table.update(&tcp_segment(false, 1000, 0, 0x02, 0));
+        table.update(&tcp_segment(false, 1001, 5001, 0x18, 517));
+        table.update(&tcp_segment(true, 5001, 1518, 0x18, 1400));
+        table.update(&tcp_segment(true, 6401, 1518, 0x18, 1400));
+        table.update(&tcp_segment(false, 1518, 7801, 0x18, 80));
+
+        let stats = table.get(&key).unwrap();
+        assert_eq!(stats.get_client_direction(), Some(Direction::Transmitted));
+        assert_eq!(stats.get_client_bytes(), 597);
+        assert_eq!(stats.get_server_bytes(), 2800);
+        assert_eq!(stats.get_client_bytes() + stats.get_server_bytes(), stats.get_bytes());
+
+        // without a SYN the client is the sender of the first packet
+        let mut table = FlowTable::new();
+        table.update(&directed_packet("192.168.1.1", Direction::Received, 1_000_000));
+        table.update(&directed_packet("192.168.1.1", Direction::Transmitted, 1_000_100));
+        table.update(&directed_packet("192.168.1.1", Direction::Transmitted, 1_000_200));
+        let stats = table.get(&FlowKey::new("192.168.1.1".to_string(), 443, Protocol::TCP)).unwrap();
+        assert_eq!(stats.get_client_direction(), Some(Direction::Received));
+        assert_eq!(stats.get_client_bytes(), 100);
+        assert_eq!(stats.get_server_bytes(), 200);
+    }
+
+    fn directed_packet(address: &str, direction: Direction, ts: u64) -> PacketInfo {
+        let mut info = PacketInfo::new(address.to_string(), 443, Protocol::TCP, 100, TimeVal::from(ts));
+        info.set_direction(direction);
+        info
+    }
+
+    #[test]
+    fn test_unidirectional_flows() {
+        let mut table = FlowTable::new();
+        // only the packets sent to 149.154.167.92 are visible, while 142.250.180.3 answers
+        table.update(&directed_packet("149.154.167.92", Direction::Transmitted, 1_000_000));
+        table.update(&directed_packet("142.250.180.3", Direction::Transmitted, 1_000_000));
+        table.update(&directed_packet("142.250.180.3", Direction::Received, 1_050_000));
+        table.update(&directed_packet("149.154.167.92", Direction::Transmitted, 2_000_000));
+        table.update(&directed_packet("149.154.167.92", Direction::Transmitted, 3_500_000));
+
+        let key = FlowKey::new("149.154.167.92".to_string(), 443, Protocol::TCP);
+        assert!(table.get(&key).unwrap().is_unidirectional());
+        assert_eq!(table.get(&key).unwrap().get_transmitted_packets(), 3);
+        assert_eq!(table.unidirectional_flows(Duration::from_secs(2)), vec![key]);
+        // the flow is still within the grace period
+        assert!(table.unidirectional_flows(Duration::from_secs(5)).is_empty());
+    }
+
+    #[test]
+    fn test_dscp_remarking() {
+        let mut table = FlowTable::new();
+        for (i, dscp) in [46, 46, 0, 46].iter().enumerate() {
+            let mut info = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::UDP, 160, TimeVal::from(1_000_000 + i as u64));
+            info.set_dscp(*dscp);
+            table.update(&info);
+        }
+        let mut info = PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(1_000_010));
+        info.set_dscp(0);
+        table.update(&info);
+
+        let key = FlowKey::new("149.154.167.92".to_string(), 443, Protocol::UDP);
+        assert_eq!(table.get(&key).unwrap().get_dscp_values(), vec![0, 46]);
+        assert!(table.get(&key).unwrap().is_dscp_remarked());
+        assert!(!table.get(&FlowKey::new("192.168.1.1".to_string(), 53, Protocol::UDP)).unwrap().is_dscp_remarked());
+        assert_eq!(table.dscp_remarked_flows(), vec![key]);
+    }
+
+    #[test]
+    fn test_hosts_aggregation() {
+        let mut table = FlowTable::new();
+        table.update(&PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(1_000_000)));
+        table.update(&PacketInfo::new("192.168.1.1".to_string(), 67, Protocol::UDP, 300, TimeVal::from(1_000_100)));
+        table.update(&PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 40, TimeVal::from(1_000_200)));
+
+        let hosts = table.hosts();
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts[1].get_address(), "192.168.1.1".to_string());
+        assert_eq!(hosts[1].get_flows(), 2);
+        assert_eq!(hosts[1].get_bytes(), 410);
+        assert_eq!(hosts[1].get_hostname(), "192.168.1.1".to_string());
+    }
+
+    #[test]
+    fn test_app_label() {
+        let mut table = FlowTable::new();
+        let key = FlowKey::new("93.184.216.34".to_string(), 443, Protocol::TCP);
+        let hello = client_hello("example.com");
+        assert!(!table.set_app_label(&key, AppLabel::new("TLS".to_string(), "unknown.org".to_string())));
+        table.update(&PacketInfo::new(key.get_address(), 443, Protocol::TCP, hello.len(), TimeVal::from(1_000_000)));
+
+        let server_name = client_hello_server_name(&hello).unwrap();
+        assert!(table.set_app_label(&key, AppLabel::new("TLS".to_string(), server_name)));
+        // the first label wins
+        assert!(!table.set_app_label(&key, AppLabel::new("HTTP".to_string(), "example.org".to_string())));
+        table.update(&PacketInfo::new(key.get_address(), 443, Protocol::TCP, 1200, TimeVal::from(1_000_100)));
+
+        let label = table.get(&key).unwrap().get_app_label().unwrap();
+        assert_eq!(label.get_name(), "example.com");
+        assert_eq!(format!("{}/{}", key.get_port(), label), "443/TLS → example.com");
+    }
+
+    #[test]
+    fn test_automatic_app_label() {
+        let mut table = FlowTable::new();
+        let tls_key = FlowKey::new("93.184.216.34".to_string(), 443, Protocol::TCP);
+        // the handshake carries no application message, the ClientHello labels the flow and the later messages do not
+        table.update_with_payload(&PacketInfo::new(tls_key.get_address(), 443, Protocol::TCP, 0, TimeVal::from(1_000_000)), &[]);
+        assert!(table.get(&tls_key).unwrap().get_app_label().is_none());
+        let hello = client_hello("example.com");
+        table.update_with_payload(&PacketInfo::new(tls_key.get_address(), 443, Protocol::TCP, hello.len(), TimeVal::from(1_000_100)), &hello);
+        let other_hello = client_hello("example.org");
+        table.update_with_payload(&PacketInfo::new(tls_key.get_address(), 443, Protocol::TCP, other_hello.len(), TimeVal::from(1_000_200)), &other_hello);
+        assert_eq!(table.get(&tls_key).unwrap().get_app_label(), Some(AppLabel::new("TLS".to_string(), "example.com".to_string())));
+
+        let http_key = FlowKey::new("192.168.1.10".to_string(), 8080, Protocol::TCP);
+        let request = b"GET /index.html HTTP/1.1\r\nUser-Agent: curl\r\nHost: intranet.lan:8080\r\n\r\n";
+        table.update_with_payload(&PacketInfo::new(http_key.get_address(), 8080, Protocol::TCP, request.len(), TimeVal::from(1_000_300)), request);
+        assert_eq!(table.get(&http_key).unwrap().get_app_label(), Some(AppLabel::new("HTTP".to_string(), "intranet.lan:8080".to_string())));
+
+        // a query for example.com, type A
+        let dns_key = FlowKey::new("192.168.1.1".to_string(), 53, Protocol::UDP);
+        let mut query = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        query.extend_from_slice(&[7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0, 0, 1, 0, 1]);
+        table.update_with_payload(&PacketInfo::new(dns_key.get_address(), 53, Protocol::UDP, query.len(), TimeVal::from(1_000_400)), &query);
+        let dns_label = table.get(&dns_key).unwrap().get_app_label().unwrap();
+        assert_eq!(dns_label.get_protocol(), "DNS");
+        assert_eq!(dns_label.get_name(), "example.com");
+        // the same bytes on another port are not taken for DNS
+        table.update_with_payload(&PacketInfo::new(dns_key.get_address(), 5000, Protocol::UDP, query.len(), TimeVal::from(1_000_500)), &query);
+        assert!(table.get(&FlowKey::new("192.168.1.1".to_string(), 5000, Protocol::UDP)).unwrap().get_app_label().is_none());
+
+        // the summaries carry the labels, the largest flow first
+        let summaries = table.summaries();
+        assert_eq!(summaries.len(), 4);
+        assert_eq!(summaries[0].get_key(), tls_key);
+        assert_eq!(summaries[0].get_packets(), 3);
+        assert_eq!(summaries[0].get_app_label(), table.get(&tls_key).unwrap().get_app_label());
+        assert_eq!(summaries[0].to_string(), format!("93.184.216.34:443/TCP (TLS → example.com), 3 packets, {} bytes", hello.len() + other_hello.len()));
+        assert!(summaries.iter().any(|summary| summary.get_key() == dns_key && summary.get_app_label() == Some(dns_label.clone())));
+    }
+
+    #[test]
+    fn test_goodput() {
+        let key = FlowKey::new("149.154.167.92".to_string(), 443, Protocol::TCP);
+        let mut table = FlowTable::new();
+        table.set_goodput_window(Duration::from_secs(2));
+        table.update(&tcp_segment_with_window(false, 1000, 0, 0x02, 0, 64240, 1_000_000));
+        table.update(&tcp_segment_with_window(true, 5000, 1001, 0x12, 0, 64240, 1_000_000));
+        table.update(&tcp_segment_with_window(false, 1001, 5001, 0x10, 0, 64240, 1_000_000));
+        // the server sends 4000 bytes in one second, one segment is retransmitted
+        table.update(&tcp_segment_with_window(true, 5001, 1001, 0x18, 1000, 64240, 1_000_000));
+        table.update(&tcp_segment_with_window(false, 1001, 6001, 0x10, 0, 64240, 1_250_000));
+        table.update(&tcp_segment_with_window(true, 6001, 1001, 0x18, 1000, 64240, 1_250_000));
+        table.update(&tcp_segment_with_window(true, 6001, 1001, 0x18, 1000, 64240, 1_500_000));
+        table.update(&tcp_segment_with_window(false, 1001, 7001, 0x10, 0, 64240, 1_500_000));
+        table.update(&tcp_segment_with_window(true, 7001, 1001, 0x18, 2000, 64240, 1_750_000));
+        // the FIN is acknowledged with the last 2000 bytes, but carries none
+        table.update(&tcp_segment_with_window(true, 9001, 1001, 0x11, 0, 64240, 1_750_000));
+        table.update(&tcp_segment_with_window(false, 1001, 9002, 0x10, 0, 64240, 2_000_000));
+        let stats = table.get(&key).unwrap();
+        assert_eq!(stats.get_goodput(), 4000.0);
+        assert_eq!(stats.get_peak_goodput(), 4000.0);
+
+        // three seconds later only the last 2000 bytes are in the window
+        table.update(&tcp_segment_with_window(false, 1001, 9002, 0x10, 0, 64240, 3_750_000));
+        let stats = table.get(&key).unwrap();
+        assert_eq!(stats.get_goodput(), 1000.0);
+        assert_eq!(stats.get_peak_goodput(), 4000.0);
+    }
+
+    #[test]
+    fn test_ecn_response() {
+        let mut table = FlowTable::new();
+        let key = FlowKey::new("149.154.167.92".to_string(), 443, Protocol::TCP);
+        // the SYN offers ECN with ECE and CWR, the SYN-ACK accepts it with ECE
+        table.update(&tcp_segment(false, 100, 0, 0xc2, 0));
+        table.update(&tcp_segment(true, 500, 101, 0x52, 0));
+        table.update(&tcp_segment(false, 101, 501, 0x10, 0));
+        let stats = table.get(&key).unwrap();
+        assert!(stats.is_ecn_negotiated());
+        assert_eq!(stats.get_ece_count(), 0);
+
+        // a router marks the data of the server, the client echoes it and the server reduces its window
+        let mut info = tcp_segment(true, 501, 101, 0x18, 1000);
+        info.set_ecn(Ecn::Ce);
+        table.update(&info);
+        let info = tcp_segment(false, 101, 1501, 0x50, 0);
+        assert!(info.get_tcp_header().unwrap().get_flags().is_ece());
+        table.update(&info);
+        table.update(&tcp_segment(true, 1501, 101, 0x98, 1000));
+
+        let stats = table.get(&key).unwrap();
+        assert_eq!(stats.get_ce_count(), 1);
+        assert_eq!(stats.get_ece_count(), 1);
+        assert_eq!(stats.get_cwr_count(), 1);
+        assert_eq!(stats.get_syn_count(), 2);
+    }
+}
@@ -15,6 +15,20 @@
 extern crate core;
 #[macro_use] extern crate prettytable;
 pub mod pkt_parser;
+pub mod flow;
+pub mod export;
+pub mod stats;
+pub mod reassembly;
+pub mod filter;
+pub mod builder;
+pub mod checksum;
+pub mod detect;
+pub mod events;
+pub mod profile;
+pub mod report;
+pub mod offline;
+#[cfg(feature = "reverse-dns")]
+pub mod resolve;
 
 /// Sniffer module
 pub mod sniffer {
@@ -24,116 +38,67 @@ pub mod sniffer {
     use std::io::{Seek, Write};
     use std::path::Path;
     use std::sync::{Arc, Condvar, Mutex};
+    use std::sync::atomic::{AtomicU8, Ordering};
     use std::fmt::{Display, Formatter};
-    use std::sync::mpsc::channel;
+    use std::sync::mpsc::{channel, Sender};
     use std::thread;
+    use std::thread::JoinHandle;
     use std::time::Duration;
+    use std::net::IpAddr;
     use ansi_term::Color::{Blue, Green};
     use ansi_term::Colour;
-    use pcap::{Capture, Device};
+    use pcap::{Capture, Device, Inactive, Linktype};
     use libc;
     use prettytable::{Cell, Row, Table};
     use crate::pkt_parser;
     use crate::pkt_parser::{*};
+    use crate::filter::{AddressFilter, PacketFilter, Pipeline};
+    use crate::events::{CaptureEvent, EventBus};
+    use crate::stats::CaptureMetrics;
+    use crossbeam_channel::Receiver;
+    use crate::pkt_parser::trailer::TimestampTrailer;
     use std::fs::OpenOptions;
 
-    fn decode_info_from_packet(device: Device, packet: PacketExt) -> Result<PacketInfo, DecodeError> {
-        let (eth_header_result, eth_payload) = EthernetHeader::decode(packet.data);
-        let eth_header = eth_header_result?;
-
-        return match eth_header.get_ether_type() {
-            EtherType::Ipv4 => {
-                let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode(eth_payload);
-                let ipv4_header = ipv4_header_result?;
-                let direction = get_direction_from_ipv4(ipv4_header.clone(), device.clone());
-
-                match ipv4_header.get_protocol() {
-                    Protocol::UDP => {
-                        let (udp_header_result, udp_payload) = UDPHeader::decode(ipv4_payload);
-                        let udp_header = udp_header_result?;
-                        let byte_transmitted = udp_payload.len();
-                        match direction {
-                            Direction::Received => {
-                                let address = ipv4_header.get_src_address();
-                                let port = udp_header.get_src_port();
-                                Ok(PacketInfo::new(address, port, Protocol::UDP, byte_transmitted, packet.timestamp))
-                            },
-                            Direction::Transmitted => {
-                                let address = ipv4_header.get_dest_address();
-                                let port = udp_header.get_dest_port();
-                                Ok(PacketInfo::new(address, port, Protocol::UDP, byte_transmitted, packet.timestamp))
-                            }
-                        }
-                    }
-                    Protocol::TCP => {
-                        let (tcp_header_result, tcp_payload) = TCPHeader::decode(ipv4_payload);
-                        let tcp_header = tcp_header_result?;
-                        let byte_transmitted = tcp_payload.len();
-                        match direction {
-                            Direction::Received => {
-                                let address = ipv4_header.get_src_address();
-                                let port = tcp_header.get_src_port();
-                                Ok(PacketInfo::new(address, port, Protocol::TCP, byte_transmitted, packet.timestamp))
-                            },
-                            Direction::Transmitted => {
-                                let address = ipv4_header.get_dest_address();
-                                let port = tcp_header.get_dest_port();
-                                Ok(PacketInfo::new(address, port, Protocol::TCP, byte_transmitted, packet.timestamp))
-                            }
-                        }
-                    }
-                    Protocol::Unknown => {
-                        Err(DecodeError { msg: format!("Unknown lev 4 protocol") })
-                    }
-                }
-            },
-            EtherType::Ipv6 => {
-                let (ipv6_header_result, ipv6_payload) = Ipv6Header::decode(eth_payload);
-                let ipv6_header = ipv6_header_result?;
-                let direction = get_direction_from_ipv6(ipv6_header.clone(), device.clone());
-
-                match ipv6_header.get_protocol() {
-                    Protocol::UDP => {
-                        let (udp_header_result, udp_payload) = UDPHeader::decode(ipv6_payload);
-                        let udp_header = udp_header_result?;
-                        let byte_transmitted = udp_payload.len();
-                        match direction {
-                            Direction::Received => {
-                                let address = ipv6_header.get_src_address();
-                                let port = udp_header.get_src_port();
-                                Ok(PacketInfo::new(address, port, Protocol::UDP, byte_transmitted, packet.timestamp))
-                            },
-                            Direction::Transmitted => {
-                                let address = ipv6_header.get_dest_address();
-                                let port = udp_header.get_dest_port();
-                                Ok(PacketInfo::new(address, port, Protocol::UDP, byte_transmitted, packet.timestamp))
-                            }
-                        }
-                    },
-                    Protocol::TCP => {
-                        let (tcp_header_result, tcp_payload) = TCPHeader::decode(ipv6_payload);
-                        let tcp_header = tcp_header_result?;
-                        let byte_transmitted = tcp_payload.len();
-                        match direction {
-                            Direction::Received => {
-                                let address = ipv6_header.get_src_address();
-                                let port = tcp_header.get_src_port();
-                                Ok(PacketInfo::new(address, port, Protocol::TCP, byte_transmitted, packet.timestamp))
-                            },
-                            Direction::Transmitted => {
-                                let address = ipv6_header.get_dest_address();
-                                let port = tcp_header.get_dest_port();
-                                Ok(PacketInfo::new(address, port, Protocol::TCP, byte_transmitted, packet.timestamp))
-                            }
-                        }
-                    },
-                    Protocol::Unknown => {
-                        Err(DecodeError { msg: format!("Unknown lev 4 protocol") })
-                    }
-                }
+    fn decode_info_from_packet(device: &Device, packet: PacketExt) -> Result<PacketInfo, DecodeError> {
+        parse_packet_with_linktype(packet.linktype, packet.data, device, packet.timestamp)
+    }
+
+    /// Decodes the frames and calls the callback with every packet decoded, the frames that cannot be decoded are skipped.
+    /// The frames read while the status is RunStatus::Wait are dropped, so that a paused capture does not pile them up.
+    /// The loop ends with the frames, or as soon as the status is set to RunStatus::Stop.
+    fn capture_loop<I, F>(frames: I, linktype: Linktype, device: &Device, status: &SharedStatus, mut callback: F)
+        where
+            I: Iterator<Item = (Vec<u8>, TimeVal)>,
+            F: FnMut(PacketInfo),
+    {
+        for (data, ts) in frames {
+            match status.get() {
+                RunStatus::Stop => break,
+                RunStatus::Wait => continue,
+                _ => {}
             }
-            _ => Err(DecodeError { msg: "Cannot decode other level 3 header".parse().unwrap() }),
-        };
+            if let Ok(info) = parse_packet_with_linktype(linktype, data, device, ts) {
+                callback(info)
+            }
+        }
+    }
+
+    /// Sends the packets read by run to its decoder thread, as capture_loop does: the packets read while the status is
+    /// RunStatus::Wait are dropped, so that the ones buffered by the kernel during the pause are not delivered on resume.
+    /// The loop ends with the packets, as soon as the status is set to RunStatus::Stop or when the decoder is gone.
+    fn forward_loop<I>(packets: I, status: &SharedStatus, tx: Sender<PacketExt>)
+        where I: Iterator<Item = PacketExt>
+    {
+        for packet in packets {
+            match status.get() {
+                RunStatus::Stop => break,
+                RunStatus::Wait => continue,
+                _ => {}
+            }
+            if tx.send(packet).is_err() {
+                break
+            }
+        }
     }
 
     /// it describes a packet, like it arrives from pcap, but it has the Send trait.
@@ -141,11 +106,19 @@ pub mod sniffer {
     struct PacketExt {
         data: Vec<u8>,
         timestamp: TimeVal,
+        linktype: Linktype,
     }
 
     impl PacketExt {
-        pub fn new(data: &[u8], ts: libc::timeval) -> Self {
-            PacketExt{data: Vec::from(data), timestamp: TimeVal{sec: ts.tv_sec as u32, u_sec: ts.tv_usec as u32}}
+        pub fn new(data: &[u8], ts: libc::timeval, linktype: Linktype) -> Self {
+            PacketExt{data: Vec::from(data), timestamp: TimeVal{sec: ts.tv_sec as u32, u_sec: ts.tv_usec as u32}, linktype}
+        }
+
+        /// Removes the timestamp trailer from the frame, returning its timestamp. The frame is unchanged if it has no trailer.
+        fn strip_trailer(&mut self, trailer: &TimestampTrailer) -> Option<TimeValNanos> {
+            let (ts_result, data) = trailer.strip(std::mem::take(&mut self.data));
+            self.data = data;
+            ts_result.ok()
         }
     }
 
@@ -162,6 +135,58 @@ pub mod sniffer {
         Error(String)
     }
 
+    /// describes the status shared by the sniffer and its threads. The status is an atomic, so that the capture loops read
+    /// it on every packet without locking; the condition variable wakes up the threads waiting for the end of a pause.
+    struct SharedStatus {
+        status: AtomicU8,
+        error: Mutex<String>,
+        lock: Mutex<()>,
+        changed: Condvar,
+    }
+
+    impl SharedStatus {
+        const STOP: u8 = 0;
+        const WAIT: u8 = 1;
+        const RUNNING: u8 = 2;
+        const ERROR: u8 = 3;
+
+        fn new() -> Self {
+            SharedStatus { status: AtomicU8::new(Self::STOP), error: Mutex::new(String::new()), lock: Mutex::new(()), changed: Condvar::new() }
+        }
+
+        fn get(&self) -> RunStatus {
+            match self.status.load(Ordering::Acquire) {
+                Self::STOP => RunStatus::Stop,
+                Self::WAIT => RunStatus::Wait,
+                Self::RUNNING => RunStatus::Running,
+                _ => RunStatus::Error(self.error.lock().unwrap().clone())
+            }
+        }
+
+        /// Sets the status and wakes up the threads waiting for it to change.
+        fn set(&self, status: RunStatus) {
+            let value = match status {
+                RunStatus::Stop => Self::STOP,
+                RunStatus::Wait => Self::WAIT,
+                RunStatus::Running => Self::RUNNING,
+                RunStatus::Error(error) => {
+                    *self.error.lock().unwrap() = error;
+                    Self::ERROR
+                }
+            };
+            // the lock is taken so that a thread checking the status before waiting does not miss the notification
+            let _guard = self.lock.lock().unwrap();
+            self.status.store(value, Ordering::Release);
+            self.changed.notify_all();
+        }
+
+        /// Blocks the current thread while the status is RunStatus::Wait.
+        fn wait_while_paused(&self) {
+            let guard = self.lock.lock().unwrap();
+            let _guard = self.changed.wait_while(guard, |_| self.status.load(Ordering::Acquire) == Self::WAIT).unwrap();
+        }
+    }
+
     /// Custom Error that wraps all possible errors that can exit during the library activities
     #[derive(Debug, PartialEq)]
     pub enum SnifferError {
@@ -197,20 +222,58 @@ pub mod sniffer {
         result
     }
 
+    /// The default size of the kernel buffer holding the captured packets until they are read, in bytes: the default of
+    /// libpcap on Linux.
+    pub const DEFAULT_BUFFER_SIZE: i32 = 2 * 1024 * 1024;
+
+    /// describes the options set on the pcap handles opened by the sniffer, before they are activated.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct CaptureOptions {
+        promisc: bool,
+        buffer_size: i32,
+        timeout: Option<i32>,
+    }
+
+    impl CaptureOptions {
+        pub fn get_promisc(&self) -> bool { self.promisc }
+        pub fn get_buffer_size(&self) -> i32 { self.buffer_size }
+        /// The read timeout, in milliseconds.
+        pub fn get_timeout(&self) -> Option<i32> { self.timeout }
+
+        /// Sets the options on a capture that has not been activated yet.
+        pub fn apply(&self, capture: Capture<Inactive>) -> Capture<Inactive> {
+            let capture = capture.promisc(self.promisc).buffer_size(self.buffer_size);
+            match self.timeout {
+                Some(timeout) => capture.timeout(timeout),
+                None => capture
+            }
+        }
+    }
+
     /// The sniffer struct allow to start the sniffing, define the file, the interface to be sniffed and allow interacting with the pcap interface.
     /// Example of use...
     pub struct Sniffer {
         device: Option<pcap::Device>,
-        status: Arc<(Mutex<RunStatus>, Condvar)>,
+        status: Arc<SharedStatus>,
         filename: Option<String>,
         time_interval: u64,
         hashmap: Arc<Mutex<HashMap<(String, u16), (Protocol, usize, u64, u64)>>>,
+        pipeline: Arc<Mutex<Pipeline>>,
+        event_bus: Arc<Mutex<EventBus>>,
+        timestamp_trailer: Option<TimestampTrailer>,
+        address_filter: AddressFilter,
+        bpf_filter: Option<String>,
+        metrics: Arc<Mutex<CaptureMetrics>>,
+        buffer_size: i32,
     }
 
     impl Sniffer {
         pub fn new() -> Self {
-            return Sniffer { device: None, status: Arc::new((Mutex::new(RunStatus::Stop), Condvar::new())),
-                filename: None, time_interval: 0, hashmap: Arc::new(Mutex::new(HashMap::new()))
+            return Sniffer { device: None, status: Arc::new(SharedStatus::new()),
+                filename: None, time_interval: 0, hashmap: Arc::new(Mutex::new(HashMap::new())),
+                pipeline: Arc::new(Mutex::new(Pipeline::new())), event_bus: Arc::new(Mutex::new(EventBus::new())),
+                timestamp_trailer: None, address_filter: AddressFilter::new(), bpf_filter: None,
+                metrics: Arc::new(Mutex::new(CaptureMetrics::new())), buffer_size: DEFAULT_BUFFER_SIZE
             }
         }
 
@@ -243,6 +306,7 @@ pub mod sniffer {
         ///Starts the sniffing process.
         ///This function requires that a file and a device have been set .
         ///It works only if the status is RunStatus::Stop.
+        ///The capture is opened before the sniffing starts, so a BPF filter refused by pcap is returned as an error.
         pub fn run(&mut self) -> Result<(), SnifferError> {
             let status = self.get_status();
             return match &status {
@@ -254,52 +318,75 @@ pub mod sniffer {
                         return Err(SnifferError::UserError("You have to specify a device ...".to_string()));
                     }
 
+                    let device = self.get_device().clone().unwrap();
+                    let mut cap = self.get_capture_options().apply(Capture::from_device(device.clone()).map_err(SnifferError::PcapError)?)
+                        .open().map_err(SnifferError::PcapError)?;
+                    let linktype = cap.get_datalink();
+                    let local_addresses = device.addresses.iter().map(|address| address.addr).collect::<Vec<IpAddr>>();
+                    // pcap can refuse the program, e.g. for link types without addresses, then the decoder filters the packets
+                    let mut filtered_by_kernel = false;
+                    if let Some(program) = self.address_filter.to_bpf(&local_addresses) {
+                        let program = match &self.bpf_filter {
+                            Some(user_filter) => format!("({}) and {}", user_filter, program),
+                            None => program
+                        };
+                        filtered_by_kernel = cap.filter(&program, true).is_ok();
+                    }
+                    // the user filter cannot be left to the decoder, so the sniffing does not start without it
+                    if !filtered_by_kernel {
+                        if let Some(user_filter) = &self.bpf_filter {
+                            cap.filter(user_filter, true).map_err(SnifferError::PcapError)?;
+                        }
+                    }
+
                     self.set_status(RunStatus::Running);
 
-                    let device = self.get_device().clone().unwrap();
                     print!("Running on {}", display_device(device.clone()));
                     let (tx, rx) = channel();
                     let tuple = self.status.clone();
+                    let capture_metrics = self.metrics.clone();
 
                     let _sniffer_thread = thread::spawn(move || {
-                        let mut cap = Capture::from_device(device).unwrap().promisc(true).open().unwrap();
-                        loop {
-                            let mut _s = tuple.0.lock().unwrap();
-                            let status = (*_s).clone();
-
-                            match &status {
-                                RunStatus::Running => {
-                                    drop(_s);
-                                    match cap.next_packet() {
-                                        Ok(packet) => {
-                                            let res = tx.send(PacketExt::new(packet.data, packet.header.ts));
-                                            match res {
-                                                Ok(()) => continue,
-                                                Err(error) => SnifferError::UserError(error.to_string())
-                                            };
-                                        },
-                                        Err(error) => {
-                                            SnifferError::PcapError(error);
+                        let mut captured: u64 = 0;
+                        let packets = std::iter::from_fn(|| loop {
+                            match cap.next_packet() {
+                                Ok(packet) => {
+                                    let packet = PacketExt::new(packet.data, packet.header.ts, linktype);
+                                    // the drop counters of pcap are read once in a while, they need a system call
+                                    captured += 1;
+                                    if captured.is_multiple_of(1000) {
+                                        if let Ok(stats) = cap.stats() {
+                                            capture_metrics.lock().unwrap().set_capture_drops(stats.dropped, stats.if_dropped);
                                         }
                                     }
+                                    return Some(packet)
                                 },
-                                RunStatus::Wait => {
-                                    _s = tuple.1.wait_while(_s, |status| { *status == RunStatus::Wait }).unwrap();
-                                },
-                                RunStatus::Stop => { break; }
-                                RunStatus::Error(e) => { println!("{}", e) }
+                                Err(_) if tuple.get() != RunStatus::Stop => thread::sleep(Duration::from_micros(100)),
+                                Err(_) => return None
                             }
-                            thread::sleep(Duration::from_micros(100));
-                        };
+                        });
+                        forward_loop(packets, &tuple, tx);
                     });
 
-                    let device = self.get_device().clone().unwrap();
                     let hashmap = self.get_hashmap().clone();
+                    let pipeline = self.pipeline.clone();
+                    let event_bus = self.event_bus.clone();
+                    let timestamp_trailer = self.timestamp_trailer;
+                    let mut address_filter = self.address_filter.clone();
+                    let metrics = self.metrics.clone();
 
                     let _decoder_thread = thread::spawn(move || {
-                        while let Ok(packet) = rx.recv() {
-                            match decode_info_from_packet(device.clone(), packet) {
-                                Ok(info) => {
+                        while let Ok(mut packet) = rx.recv() {
+                            let hardware_ts = timestamp_trailer.and_then(|trailer| packet.strip_trailer(&trailer));
+                            match decode_info_from_packet(&device, packet) {
+                                Ok(mut info) => {
+                                    if let Some(ts) = hardware_ts { info.set_hardware_time_stamp(ts) }
+                                    if (!filtered_by_kernel && !address_filter.accept(&info)) || !pipeline.lock().unwrap().accept(&info) {
+                                        metrics.lock().unwrap().add_filtered();
+                                        continue
+                                    }
+                                    metrics.lock().unwrap().update(&info);
+                                    event_bus.lock().unwrap().process(&info);
                                     let mut hm = hashmap.lock().unwrap();
                                     let existing_pkt = hm.get(&(info.get_address(), info.get_port()));
                                     match existing_pkt {
@@ -315,7 +402,7 @@ pub mod sniffer {
                                         }
                                     }
                                 },
-                                Err(_) => {}
+                                Err(error) => metrics.lock().unwrap().add_decode_error(&error)
                             }
                         }
                     });
@@ -328,6 +415,57 @@ pub mod sniffer {
             }
         }
 
+        ///Starts capturing on the device in a background thread, calling the callback with every packet decoded.
+        ///Unlike run, it needs no file and keeps no report: the callback receives the packets as they arrive.
+        ///It works only if the status is RunStatus::Stop, and returns the handle of the capture thread.
+        pub fn start<F>(&mut self, device: Device, callback: F) -> Result<JoinHandle<()>, SnifferError>
+            where F: FnMut(PacketInfo) + Send + 'static
+        {
+            if self.get_status() != RunStatus::Stop {
+                return Err(SnifferError::UserWarning("Another scanning is already running ...".to_string()));
+            }
+            // the read timeout lets the loop notice the stop when no packet arrives
+            let capture_options = CaptureOptions { timeout: Some(100), ..self.get_capture_options() };
+            let mut cap = capture_options.apply(Capture::from_device(device.clone()).map_err(SnifferError::PcapError)?)
+                .open().map_err(SnifferError::PcapError)?;
+            if let Some(bpf_filter) = &self.bpf_filter {
+                cap.filter(bpf_filter, true).map_err(SnifferError::PcapError)?;
+            }
+            let linktype = cap.get_datalink();
+            let status = self.status.clone();
+            let frames = std::iter::from_fn(move || loop {
+                match cap.next_packet() {
+                    Ok(packet) => {
+                        let ts = TimeVal { sec: packet.header.ts.tv_sec as u32, u_sec: packet.header.ts.tv_usec as u32 };
+                        return Some((Vec::from(packet.data), ts))
+                    },
+                    Err(pcap::Error::TimeoutExpired) if status.get() != RunStatus::Stop => continue,
+                    Err(_) => return None
+                }
+            });
+            self.start_offline(frames, linktype, device, callback)
+        }
+
+        ///Runs the capture loop of start on frames that have already been captured, e.g. read from a file by
+        ///PcapFileSource, with the link layer of the capture. The device is the one the frames have been captured on, it
+        ///tells the direction of the packets.
+        ///It works only if the status is RunStatus::Stop, as start.
+        pub fn start_offline<I, F>(&mut self, frames: I, linktype: Linktype, device: Device, callback: F) -> Result<JoinHandle<()>, SnifferError>
+            where
+                I: Iterator<Item = (Vec<u8>, TimeVal)> + Send + 'static,
+                F: FnMut(PacketInfo) + Send + 'static,
+        {
+            if self.get_status() != RunStatus::Stop {
+                return Err(SnifferError::UserWarning("Another scanning is already running ...".to_string()));
+            }
+            self.set_status(RunStatus::Running);
+            let status = self.status.clone();
+            Ok(thread::spawn(move || {
+                capture_loop(frames, linktype, &device, &status, callback);
+                status.set(RunStatus::Stop);
+            }))
+        }
+
         ///Starts the sniffing process.
         ///This function requires that a time interval has been set.
         ///Since it uses the run() method, its requirements still hold also.
@@ -356,12 +494,8 @@ pub mod sniffer {
             let _sleep_thread = thread::spawn(move || {
                 let mut count = 0;
                 loop {
-                    let mut _s = tuple.0.lock().unwrap();
-                    let status = (*_s).clone();
-
-                    match &status {
+                    match tuple.get() {
                         RunStatus::Running => {
-                            drop(_s);
                             let mut heading = String::new();
                             thread::sleep(Duration::from_secs(interval.clone()));
                             if count == 0 {
@@ -376,9 +510,7 @@ pub mod sniffer {
                             }
                             count += 1;
                         },
-                        RunStatus::Wait => {
-                            _s = tuple.1.wait_while(_s, |status| { *status == RunStatus::Wait }).unwrap();
-                        },
+                        RunStatus::Wait => tuple.wait_while_paused(),
                         RunStatus::Stop => { break; }
                         RunStatus::Error(e) => { println!("{}", e) }
                     }
@@ -390,7 +522,8 @@ pub mod sniffer {
 
         ///Changes the application status in RunStatus::Wait.
         ///This function works only if the status is RunStatus::Running.
-        pub fn pause(&mut self) -> Result<(), SnifferError> {
+        ///The capture loops started by run and start drop the packets captured while paused.
+        pub fn pause(&self) -> Result<(), SnifferError> {
             let status = self.get_status();
             match &status {
                 RunStatus::Error(error) => Err(SnifferError::UserError(error.to_string())),
@@ -406,13 +539,12 @@ pub mod sniffer {
         ///Changes the application status in RunStatus::Running.
         ///This function works only if the status is RunStatus::Wait, and if the time interval has been set.
         ///It automatically writes the collected data every n seconds, with respect to the specified time interval.
-        pub fn resume(&mut self) -> Result<(), SnifferError> {
+        pub fn resume(&self) -> Result<(), SnifferError> {
             let status = self.get_status();
             match &status {
                 RunStatus::Error(error) => Err(SnifferError::UserError(error.to_string())),
                 RunStatus::Wait => {
                     self.set_status(RunStatus::Running);
-                    self.status.1.notify_all();
                     Ok(())
                 },
                 RunStatus::Stop => { return Err(SnifferError::UserWarning("There is no scanning in execution ...".to_string())); },
@@ -420,6 +552,20 @@ pub mod sniffer {
             }
         }
 
+        ///Changes the application status in RunStatus::Stop, ending the capture threads without saving the report.
+        ///This function works only if the status is either RunStatus::Running or RunStatus::Wait.
+        pub fn stop(&self) -> Result<(), SnifferError> {
+            match self.get_status() {
+                RunStatus::Error(error) => Err(SnifferError::UserError(error)),
+                RunStatus::Stop => Err(SnifferError::UserWarning("There is no scanning in execution ...".to_string())),
+                RunStatus::Running | RunStatus::Wait => {
+                    // the paused threads wake up to see the new status
+                    self.set_status(RunStatus::Stop);
+                    Ok(())
+                }
+            }
+        }
+
         fn heading(device: &Device) -> String {
             let mut string = "Scanning on: \n\t- Interface ".to_string();
             string.push_str(device.name.as_str());
@@ -516,6 +662,80 @@ pub mod sniffer {
             self.time_interval = time_interval;
         }
 
+        ///Sets the filters applied to the decoded packets before they are added to the report.
+        ///The pipeline is used by the next sniffing, and keeps its state until it is replaced.
+        pub fn set_pipeline(&mut self, pipeline: Pipeline) {
+            self.pipeline = Arc::new(Mutex::new(pipeline));
+        }
+
+        ///Sets the hosts whose packets are captured, the filter is used by the next sniffing.
+        ///It is translated to a BPF program so that the kernel drops the other packets, if pcap cannot use it the
+        ///packets are filtered after being decoded.
+        pub fn set_address_filter(&mut self, address_filter: AddressFilter) {
+            self.address_filter = address_filter;
+        }
+
+        ///Returns the size of the kernel capture buffer, in bytes.
+        pub fn get_buffer_size(&self) -> i32 {
+            self.buffer_size
+        }
+
+        ///Sets the size of the kernel buffer holding the captured packets until they are read, used by the next sniffing.
+        ///Packets arriving while the buffer is full are dropped, so it should be increased on high-throughput links.
+        ///Every packet takes up to the snapshot length (65535 bytes) in the buffer, so the size should be many times larger.
+        pub fn set_buffer_size(&mut self, buffer_size: i32) -> Result<(), SnifferError> {
+            if buffer_size <= 0 {
+                return Err(SnifferError::UserError("The buffer size must be positive ...".to_string()))
+            }
+            self.buffer_size = buffer_size;
+            Ok(())
+        }
+
+        ///Returns the options set on the pcap handle opened by the next sniffing, start adds a read timeout to them.
+        pub fn get_capture_options(&self) -> CaptureOptions {
+            CaptureOptions { promisc: true, buffer_size: self.buffer_size, timeout: None }
+        }
+
+        ///Returns the BPF expression selecting the captured packets, if any.
+        pub fn get_bpf_filter(&self) -> Option<String> {
+            self.bpf_filter.clone()
+        }
+
+        ///Sets a BPF expression (e.g. "tcp port 443") selecting the packets captured by the next sniffing, together with the
+        ///address filter. The expression is compiled right away, so that a wrong one is reported here.
+        pub fn set_bpf_filter(&mut self, bpf_filter: Option<String>) -> Result<(), SnifferError> {
+            if let Some(expression) = &bpf_filter {
+                let capture = Capture::dead(Linktype::ETHERNET).map_err(SnifferError::PcapError)?;
+                capture.compile(expression, true).map_err(SnifferError::PcapError)?;
+            }
+            self.bpf_filter = bpf_filter;
+            Ok(())
+        }
+
+        ///Sets the timestamp trailer appended to the frames by the switch mirroring the traffic, if any.
+        ///The trailer is stripped before decoding the frames and its timestamp is attached to the packet information.
+        pub fn set_timestamp_trailer(&mut self, timestamp_trailer: Option<TimestampTrailer>) {
+            self.timestamp_trailer = timestamp_trailer;
+        }
+
+        ///Returns the timestamp trailer stripped from the frames, if any.
+        pub fn get_timestamp_trailer(&self) -> Option<TimestampTrailer> {
+            self.timestamp_trailer
+        }
+
+        ///Returns the counters of the sniffing in the Prometheus text exposition format, to be served to a scraper.
+        pub fn prometheus_metrics(&self) -> String {
+            let connections = self.get_hashmap().lock().unwrap().len();
+            let mut metrics = self.metrics.lock().unwrap();
+            metrics.set_connections(connections);
+            metrics.prometheus_metrics()
+        }
+
+        ///Returns a receiver of the events published while sniffing: the packets accepted by the pipeline, the alerts and the statistics.
+        pub fn subscribe(&self) -> Receiver<CaptureEvent> {
+            self.event_bus.lock().unwrap().subscribe()
+        }
+
         ///Returns the filename that has been set.
         pub fn get_filename(&self) -> Option<String> {
             self.filename.clone()
@@ -535,17 +755,13 @@ pub mod sniffer {
         }
 
         ///Returns the current status of the sniffer.
-        ///This function waits until the status is available.
         pub fn get_status(&self) -> RunStatus {
-            let s = self.status.0.lock().unwrap();
-            return (*s).clone();
+            self.status.get()
         }
 
-        ///Sets the status of the sniffer.
-        ///This function waits until the status is available.
+        ///Sets the status of the sniffer, waking up the threads waiting for the resume.
         fn set_status(&self, status: RunStatus) -> () {
-            let mut s = self.status.0.lock().unwrap();
-            *s = status;
+            self.status.set(status);
         }
 
         ///Returns the device that has been set.
@@ -563,4 +779,64 @@ pub mod sniffer {
             &self.hashmap
         }
     }
-}
\ No newline at end of file
+
+    #[cfg(test)]
+    mod tests {
+        use std::sync::mpsc::channel;
+        use std::sync::Arc;
+        use std::thread;
+        use std::time::Duration;
+        use pcap::Linktype;
+        use crate::pkt_parser::TimeVal;
+        use crate::sniffer::{*};
+
+        fn packet(ts: u64) -> PacketExt {
+            PacketExt { data: vec![0; 60], timestamp: TimeVal::from(ts), linktype: Linktype::ETHERNET }
+        }
+
+        #[test]
+        fn paused_run_drops_packets() {
+            let status = Arc::new(SharedStatus::new());
+            status.set(RunStatus::Running);
+            let (packets_tx, packets_rx) = channel();
+            let (forwarded_tx, forwarded_rx) = channel();
+            let loop_status = status.clone();
+            let handle = thread::spawn(move || forward_loop(packets_rx.into_iter(), &loop_status, forwarded_tx));
+            let timeout = Duration::from_secs(5);
+
+            packets_tx.send(packet(1)).unwrap();
+            assert_eq!(forwarded_rx.recv_timeout(timeout).unwrap().timestamp, TimeVal::from(1));
+
+            // the packets read while paused are dropped, not delivered on resume
+            status.set(RunStatus::Wait);
+            packets_tx.send(packet(2)).unwrap();
+            packets_tx.send(packet(3)).unwrap();
+            assert!(forwarded_rx.recv_timeout(Duration::from_millis(200)).is_err());
+            status.set(RunStatus::Running);
+            packets_tx.send(packet(4)).unwrap();
+            assert_eq!(forwarded_rx.recv_timeout(timeout).unwrap().timestamp, TimeVal::from(4));
+
+            status.set(RunStatus::Stop);
+            packets_tx.send(packet(5)).unwrap();
+            handle.join().unwrap();
+            assert!(forwarded_rx.recv_timeout(Duration::from_millis(200)).is_err());
+        }
+
+        #[test]
+        fn resume_wakes_up_paused_threads() {
+            let status = Arc::new(SharedStatus::new());
+            status.set(RunStatus::Wait);
+            let waiting_status = status.clone();
+            let handle = thread::spawn(move || {
+                waiting_status.wait_while_paused();
+                waiting_status.get()
+            });
+            thread::sleep(Duration::from_millis(50));
+            status.set(RunStatus::Running);
+            assert_eq!(handle.join().unwrap(), RunStatus::Running);
+
+            status.set(RunStatus::Error("no device".to_string()));
+            assert_eq!(status.get(), RunStatus::Error("no device".to_string()));
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! offline
+//! This module reads the frames of a saved capture, so that the parser can run without a live device: on the captures
+//! shared by the users, in the tests and in the CI.
+//!
+//! PcapFileSource reads the pcap files, the ones written by PcapWriter (see the export module), by tcpdump and by
+//! Wireshark when saving in the pcap format:
+//! - in both the byte orders
+//! - with microseconds or nanoseconds timestamps: the iterator yields TimeVal, as the live captures, rounding the latter
+//!   down to microseconds, while next_record keeps the full precision of the file in a TimeValNanos
+
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, ErrorKind, Read};
+use std::path::Path;
+use pcap::Linktype;
+use crate::export::PCAP_SNAPLEN;
+use crate::pkt_parser::{TimeVal, TimeValNanos};
+
+/// Iterates over the frames of a pcap file, with the time they have been captured at.
+pub struct PcapFileSource<R: Read = BufReader<File>> {
+    reader: R,
+    big_endian: bool,
+    nanos: bool,
+    linktype: Linktype,
+    snaplen: u32,
+}
+
+impl PcapFileSource {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        PcapFileSource::from_reader(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> PcapFileSource<R> {
+    /// Reads the global header of the file, the frames are read by the iterator.
+    pub fn from_reader(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; 24];
+        reader.read_exact(&mut header)?;
+        let (big_endian, nanos) = match [header[0], header[1], header[2], header[3]] {
+            [0xd4, 0xc3, 0xb2, 0xa1] => (false, false),
+            [0xa1, 0xb2, 0xc3, 0xd4] => (true, false),
+            [0x4d, 0x3c, 0xb2, 0xa1] => (false, true),
+            [0xa1, 0xb2, 0x3c, 0x4d] => (true, true),
+            magic => return Err(io::Error::new(ErrorKind::InvalidData, format!("Not a pcap file, magic number {:x?}", magic)))
+        };
+        let mut source = PcapFileSource { reader, big_endian, nanos, linktype: Linktype::ETHERNET, snaplen: 0 };
+        source.snaplen = source.read_u32(&header[16..20]);
+        source.linktype = Linktype(source.read_u32(&header[20..24]) as i32);
+        Ok(source)
+    }
+
+    /// The link layer of the frames, to be passed to decode_link_layer.
+    pub fn get_linktype(&self) -> Linktype { self.linktype }
+    pub fn get_snaplen(&self) -> u32 { self.snaplen }
+    /// Whether the timestamps of the file have nanoseconds precision.
+    pub fn is_nanos(&self) -> bool { self.nanos }
+
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+    }
+
+    /// Reads the next frame as the iterator does, with its timestamp at the precision of the file.
+    pub fn next_record(&mut self) -> Option<io::Result<(Vec<u8>, TimeValNanos)>> {
+        self.read_record().transpose()
+    }
+
+    /// Reads the next record, None at the end of the file.
+    fn read_record(&mut self) -> io::Result<Option<(Vec<u8>, TimeValNanos)>> {
+        let mut header = [0; 16];
+        // a file ending between two records is complete, while one ending inside a record is truncated
+        match self.reader.read(&mut header[..1])? {
+            0 => return Ok(None),
+            _ => self.reader.read_exact(&mut header[1..])?
+        }
+        let sec = self.read_u32(&header[0..4]);
+        let fraction = self.read_u32(&header[4..8]);
+        let captured_len = self.read_u32(&header[8..12]);
+        // a record longer than the largest snaplen of libpcap means a corrupted file, that would make the reader allocate
+        // huge buffers: the snaplen of the header is not trusted, it can be corrupted as well
+        if captured_len > PCAP_SNAPLEN {
+            return Err(io::Error::new(ErrorKind::InvalidData, format!("Corrupted record, {} bytes captured", captured_len)))
+        }
+        let mut data = vec![0; captured_len as usize];
+        self.reader.read_exact(&mut data)?;
+        let ts = if self.nanos { TimeValNanos::new(sec, fraction) } else { TimeValNanos::from(TimeVal { sec, u_sec: fraction }) };
+        Ok(Some((data, ts)))
+    }
+}
+
+impl<R: Read> Iterator for PcapFileSource<R> {
+    type Item = io::Result<(Vec<u8>, TimeVal)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().map(|record| record.map(|(data, ts)| (data, TimeVal::from(ts)))).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::offline::{*};
+    use crate::export::PcapWriter;
+    use crate::pkt_parser::{EtherType, EthernetHeader, Header};
+
+    #[test]
+    fn test_read_sample_capture() {
+        let source = PcapFileSource::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/sample.pcap")).unwrap();
+        assert_eq!(source.get_linktype(), Linktype::ETHERNET);
+        let frames = source.collect::<io::Result<Vec<(Vec<u8>, TimeVal)>>>().unwrap();
+        assert_eq!(frames.len(), 2);
+        let (data, ts) = frames[0].clone();
+        assert_eq!(ts, TimeVal::from(1657968204419346));
+        let (ethernet_header_result, _ethernet_payload) = EthernetHeader::decode(data);
+        let ethernet_header = ethernet_header_result.unwrap();
+        assert_eq!(ethernet_header.get_src_address(), "50eb71238e67");
+        assert_eq!(ethernet_header.get_ether_type(), EtherType::Ipv4);
+        assert_eq!(frames[1].0.len(), 144);
+    }
+
+    #[test]
+    fn test_read_written_capture() {
+        let mut writer = PcapWriter::new(Vec::new(), Linktype::LINUX_SLL2).unwrap();
+        writer.write_packet(&[1, 2, 3], &TimeVal::from(1_000_001)).unwrap();
+        let buffer = writer.into_inner().unwrap();
+        let mut source = PcapFileSource::from_reader(&buffer[..]).unwrap();
+        assert_eq!(source.get_linktype(), Linktype::LINUX_SLL2);
+        assert_eq!(source.next().unwrap().unwrap(), (vec![1, 2, 3], TimeVal::from(1_000_001)));
+        assert!(source.next().is_none());
+
+        // a record cut in the middle
+        let mut source = PcapFileSource::from_reader(&buffer[..buffer.len() - 1]).unwrap();
+        assert!(source.next().unwrap().is_err());
+        assert!(PcapFileSource::from_reader(&[0u8; 24][..]).is_err());
+    }
+
+    #[test]
+    fn test_oversized_record() {
+        // the header announces the largest snaplen, a record of 16 MB follows
+        let mut buffer = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 1, 0, 0, 0];
+        buffer.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1]);
+        let mut source = PcapFileSource::from_reader(&buffer[..]).unwrap();
+        assert_eq!(source.get_snaplen(), u32::MAX);
+        assert_eq!(source.next().unwrap().unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_nanos_capture() {
+        // a little endian file with nanoseconds timestamps, then a big endian one
+        let mut buffer = vec![0x4d, 0x3c, 0xb2, 0xa1, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0, 1, 0, 0, 0];
+        buffer.extend_from_slice(&[10, 0, 0, 0, 0x15, 0xcd, 0x5b, 0x07, 2, 0, 0, 0, 2, 0, 0, 0, 7, 8]);
+        let mut source = PcapFileSource::from_reader(&buffer[..]).unwrap();
+        assert!(source.is_nanos());
+        let (data, ts) = source.next_record().unwrap().unwrap();
+        assert_eq!(data, vec![7, 8]);
+        assert_eq!((ts.get_sec(), ts.get_n_sec()), (10, 123_456_789));
+        assert!(source.next_record().is_none());
+        // the iterator rounds down to microseconds
+        let mut source = PcapFileSource::from_reader(&buffer[..]).unwrap();
+        assert_eq!(source.next().unwrap().unwrap().1, TimeVal::from(10_123_456));
+
+        let mut buffer = vec![0xa1, 0xb2, 0x3c, 0x4d, 0, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0, 0, 1];
+        buffer.extend_from_slice(&[0, 0, 0, 10, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 9]);
+        let (_, ts) = PcapFileSource::from_reader(&buffer[..]).unwrap().next_record().unwrap().unwrap();
+        assert_eq!((ts.get_sec(), ts.get_n_sec()), (10, 1));
+
+        // the microseconds files are converted exactly
+        let mut writer = PcapWriter::new(Vec::new(), Linktype::ETHERNET).unwrap();
+        writer.write_packet(&[1], &TimeVal::from(1_000_001)).unwrap();
+        let buffer = writer.into_inner().unwrap();
+        let mut source = PcapFileSource::from_reader(&buffer[..]).unwrap();
+        assert!(!source.is_nanos());
+        let (_, ts) = source.next_record().unwrap().unwrap();
+        assert_eq!((ts.get_sec(), ts.get_n_sec()), (1, 1000));
+    }
+}
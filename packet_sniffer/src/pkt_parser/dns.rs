@@ -0,0 +1,397 @@
+//! dns
+//! This module decodes the DNS messages (RFC 1035) carried by UDP and TCP on port 53: the transaction ID, that pairs a
+//! response with its query, and the flags telling queries from responses and the outcome. For every question it exposes the
+//! queried name, type and class, and for every resource record of the answer, authority and additional sections its
+//! owner name, type, class and TTL (the record data is not decoded).
+//!
+//! Over TCP, used for zone transfers and for the responses too large for UDP, every message is preceded by its length in
+//! 2 bytes: DnsTcpReader splits the reassembled stream of a connection (see reassembly::TcpStream) into the messages.
+
+use crate::pkt_parser::{decode_owned, DecodeError, DecodeErrorKind, Header, SliceHeader};
+
+/// The well known port of DNS.
+pub const DNS_PORT: u16 = 53;
+
+/// The maximum number of compression pointers followed while reading a name, to stop on looping pointers.
+const MAX_POINTERS: usize = 32;
+
+/// The types of the resource records and of the queries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DnsType {
+    A,
+    NS,
+    CNAME,
+    SOA,
+    PTR,
+    MX,
+    TXT,
+    AAAA,
+    SRV,
+    /// The pseudo record of EDNS (RFC 6891).
+    OPT,
+    HTTPS,
+    /// The zone transfer query.
+    AXFR,
+    /// The query for all the records of a name.
+    ANY,
+    Other(u16),
+}
+
+impl From<u16> for DnsType {
+    fn from(v: u16) -> Self {
+        match v {
+            1 => DnsType::A,
+            2 => DnsType::NS,
+            5 => DnsType::CNAME,
+            6 => DnsType::SOA,
+            12 => DnsType::PTR,
+            15 => DnsType::MX,
+            16 => DnsType::TXT,
+            28 => DnsType::AAAA,
+            33 => DnsType::SRV,
+            41 => DnsType::OPT,
+            65 => DnsType::HTTPS,
+            252 => DnsType::AXFR,
+            255 => DnsType::ANY,
+            v => DnsType::Other(v)
+        }
+    }
+}
+
+/// The classes of the resource records and of the queries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DnsClass {
+    /// The Internet, the class of almost all the traffic.
+    IN,
+    /// Chaos, still used to query the version of the servers.
+    CH,
+    /// Hesiod.
+    HS,
+    ANY,
+    Other(u16),
+}
+
+impl From<u16> for DnsClass {
+    fn from(v: u16) -> Self {
+        match v {
+            1 => DnsClass::IN,
+            3 => DnsClass::CH,
+            4 => DnsClass::HS,
+            255 => DnsClass::ANY,
+            v => DnsClass::Other(v)
+        }
+    }
+}
+
+/// The response codes of DNS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DnsRcode {
+    NoError,
+    /// The server could not interpret the query.
+    FormErr,
+    ServFail,
+    /// The queried name does not exist.
+    NXDomain,
+    NotImp,
+    Refused,
+    Other(u8),
+}
+
+impl From<u8> for DnsRcode {
+    fn from(v: u8) -> Self {
+        match v {
+            0 => DnsRcode::NoError,
+            1 => DnsRcode::FormErr,
+            2 => DnsRcode::ServFail,
+            3 => DnsRcode::NXDomain,
+            4 => DnsRcode::NotImp,
+            5 => DnsRcode::Refused,
+            v => DnsRcode::Other(v)
+        }
+    }
+}
+
+/// The flags of a DNS message (bytes 2-3 of the header).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DnsFlags(u16);
+
+impl DnsFlags {
+    /// QR: the message is a response, not a query.
+    pub fn is_response(&self) -> bool { self.0 & 0x8000 != 0 }
+    /// The kind of query: 0 for a standard query, 4 for a notify and 5 for an update.
+    pub fn get_opcode(&self) -> u8 { ((self.0 >> 11) & 0x0f) as u8 }
+    pub fn is_authoritative(&self) -> bool { self.0 & 0x0400 != 0 }
+    /// The message has been truncated to fit in a UDP datagram, the client should ask again over TCP.
+    pub fn is_truncated(&self) -> bool { self.0 & 0x0200 != 0 }
+    pub fn is_recursion_desired(&self) -> bool { self.0 & 0x0100 != 0 }
+    pub fn is_recursion_available(&self) -> bool { self.0 & 0x0080 != 0 }
+    pub fn get_rcode(&self) -> DnsRcode { DnsRcode::from((self.0 & 0x0f) as u8) }
+}
+
+impl From<u16> for DnsFlags {
+    fn from(v: u16) -> Self { DnsFlags(v) }
+}
+
+/// describes a question of a DNS message: the queried name, type and class.
+#[derive(Debug, Clone)]
+pub struct DnsQuestion {
+    name: String,
+    qtype: u16,
+    qclass: u16,
+}
+
+impl DnsQuestion {
+    pub fn get_name(&self) -> String { self.name.clone() }
+    pub fn get_type(&self) -> DnsType { DnsType::from(self.qtype) }
+    pub fn get_class(&self) -> DnsClass { DnsClass::from(self.qclass) }
+}
+
+/// describes a resource record of a DNS message: its owner name, type, class and TTL.
+#[derive(Debug, Clone)]
+pub struct DnsRecord {
+    name: String,
+    rtype: u16,
+    class: u16,
+    ttl: u32,
+}
+
+impl DnsRecord {
+    pub fn get_name(&self) -> String { self.name.clone() }
+    pub fn get_type(&self) -> DnsType { DnsType::from(self.rtype) }
+    /// The class of the record, for the OPT record it is instead the UDP payload size of the sender.
+    pub fn get_class(&self) -> DnsClass { DnsClass::from(self.class) }
+    pub fn get_ttl(&self) -> u32 { self.ttl }
+}
+
+/// describes a DNS message: its transaction ID and flags, its questions and the resource records of its answer, authority
+/// and additional sections.
+#[derive(Debug, Clone)]
+pub struct DnsMessage {
+    id: u16,
+    flags: DnsFlags,
+    questions: Vec<DnsQuestion>,
+    answers: Vec<DnsRecord>,
+    authorities: Vec<DnsRecord>,
+    additionals: Vec<DnsRecord>,
+}
+
+fn truncated() -> DecodeError {
+    DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode a dns message because is not long enough.".to_string()}
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, DecodeError> {
+    if data.len() < offset + 2 { return Err(truncated()) }
+    Ok(u16::from_be_bytes([data[offset], data[offset + 1]]))
+}
+
+/// Reads the name starting at offset, following the compression pointers, and returns it with the offset after it.
+/// The root name is returned as ".".
+fn read_name(data: &[u8], offset: usize) -> Result<(String, usize), DecodeError> {
+    let mut labels = Vec::new();
+    let mut position = offset;
+    let mut end = None;
+    let mut pointers = 0;
+    loop {
+        let len = *data.get(position).ok_or_else(truncated)? as usize;
+        match len & 0xc0 {
+            0x00 if len == 0 => {
+                position += 1;
+                break
+            }
+            0x00 => {
+                let label = data.get(position + 1..position + 1 + len).ok_or_else(truncated)?;
+                labels.push(String::from_utf8_lossy(label).to_string());
+                position += 1 + len;
+            }
+            0xc0 => {
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: "Too many compression pointers in a dns name.".to_string()})
+                }
+                let target = (read_u16(data, position)? & 0x3fff) as usize;
+                end.get_or_insert(position + 2);
+                position = target;
+            }
+            _ => return Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: format!("Unsupported dns label type {:#04x}", len & 0xc0)})
+        }
+    }
+    let name = if labels.is_empty() { ".".to_string() } else { labels.join(".") };
+    Ok((name, end.unwrap_or(position)))
+}
+
+fn read_records(data: &[u8], offset: &mut usize, count: u16) -> Result<Vec<DnsRecord>, DecodeError> {
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let (name, position) = read_name(data, *offset)?;
+        if data.len() < position + 10 { return Err(truncated()) }
+        let rtype = read_u16(data, position)?;
+        let class = read_u16(data, position + 2)?;
+        let ttl = u32::from_be_bytes([data[position + 4], data[position + 5], data[position + 6], data[position + 7]]);
+        let data_len = read_u16(data, position + 8)? as usize;
+        if data.len() < position + 10 + data_len { return Err(truncated()) }
+        *offset = position + 10 + data_len;
+        records.push(DnsRecord { name, rtype, class, ttl });
+    }
+    Ok(records)
+}
+
+impl SliceHeader for DnsMessage {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
+        let decode = || -> Result<(DnsMessage, usize), DecodeError> {
+            if data.len() < 12 { return Err(truncated()) }
+            let mut offset = 12;
+            let mut questions = Vec::new();
+            for _ in 0..read_u16(data, 4)? {
+                let (name, position) = read_name(data, offset)?;
+                questions.push(DnsQuestion { name, qtype: read_u16(data, position)?, qclass: read_u16(data, position + 2)? });
+                offset = position + 4;
+            }
+            let answers = read_records(data, &mut offset, read_u16(data, 6)?)?;
+            let authorities = read_records(data, &mut offset, read_u16(data, 8)?)?;
+            let additionals = read_records(data, &mut offset, read_u16(data, 10)?)?;
+            let (id, flags) = (read_u16(data, 0)?, DnsFlags::from(read_u16(data, 2)?));
+            Ok((DnsMessage { id, flags, questions, answers, authorities, additionals }, offset))
+        };
+        match decode() {
+            Ok((message, offset)) => (Ok(message), &data[offset..]),
+            Err(e) => (Err(e), data)
+        }
+    }
+}
+
+impl Header for DnsMessage {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
+impl DnsMessage {
+    /// The transaction ID, chosen by the client and copied in the response.
+    pub fn get_id(&self) -> u16 { self.id }
+    pub fn get_flags(&self) -> DnsFlags { self.flags }
+    /// Returns the name asked by the first question, that is the only one in practice.
+    pub fn get_query_name(&self) -> Option<String> { self.questions.first().map(|question| question.get_name()) }
+    pub fn get_questions(&self) -> Vec<DnsQuestion> { self.questions.clone() }
+    pub fn get_answers(&self) -> Vec<DnsRecord> { self.answers.clone() }
+    pub fn get_authorities(&self) -> Vec<DnsRecord> { self.authorities.clone() }
+    pub fn get_additionals(&self) -> Vec<DnsRecord> { self.additionals.clone() }
+}
+
+/// Reads the DNS messages from the stream of a DNS over TCP connection, that can carry many of them. The bytes are given as
+/// they are reassembled, and a message split across several segments is returned once it is complete.
+#[derive(Debug, Clone, Default)]
+pub struct DnsTcpReader {
+    buffer: Vec<u8>,
+}
+
+impl DnsTcpReader {
+    pub fn new() -> Self {
+        DnsTcpReader { buffer: Vec::new() }
+    }
+
+    /// Appends the next bytes of the stream, returning the messages they complete, decoded.
+    pub fn push(&mut self, data: &[u8]) -> Vec<Result<DnsMessage, DecodeError>> {
+        self.buffer.extend_from_slice(data);
+        let mut messages = Vec::new();
+        while self.buffer.len() >= 2 {
+            let len = u16::from_be_bytes([self.buffer[0], self.buffer[1]]) as usize;
+            if self.buffer.len() < 2 + len { break }
+            let message: Vec<u8> = self.buffer.drain(..2 + len).skip(2).collect();
+            messages.push(DnsMessage::decode(message).0);
+        }
+        messages
+    }
+
+    /// Returns the bytes of the message not completed yet.
+    pub fn get_buffered_bytes(&self) -> usize { self.buffer.len() }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pkt_parser::dns::{*};
+    use crate::pkt_parser::{try_parse, TransportHeader};
+    use crate::reassembly::TcpStream;
+
+    #[test]
+    fn test_question_type() {
+        // the udp payload of the response in test_whole_packet_1: no such name for the A record of wpad.home
+        let data = vec![212, 212, 129, 131, 0, 1, 0, 0, 0, 1, 0, 0, 4, 119, 112, 97, 100, 4, 104, 111, 109, 101, 0, 0, 1, 0, 1, 0, 0, 6, 0, 1, 0, 0, 0, 91, 0, 64, 1, 97, 12, 114, 111, 111, 116, 45, 115, 101, 114, 118, 101, 114, 115, 3, 110, 101, 116, 0, 5, 110, 115, 116, 108, 100, 12, 118, 101, 114, 105, 115, 105, 103, 110, 45, 103, 114, 115, 3, 99, 111, 109, 0, 120, 134, 93, 48, 0, 0, 7, 8, 0, 0, 3, 132, 0, 9, 58, 128, 0, 1, 81, 128];
+        let (message_result, payload) = DnsMessage::decode(data);
+        let message = message_result.unwrap();
+        assert!(payload.is_empty());
+
+        let questions = message.get_questions();
+        assert_eq!(questions.len(), 1);
+        assert_eq!(questions[0].get_name(), "wpad.home");
+        assert_eq!(questions[0].get_type(), DnsType::A);
+        assert_eq!(questions[0].get_class(), DnsClass::IN);
+
+        assert!(message.get_answers().is_empty());
+        let authorities = message.get_authorities();
+        assert_eq!(authorities.len(), 1);
+        assert_eq!(authorities[0].get_name(), ".");
+        assert_eq!(authorities[0].get_type(), DnsType::SOA);
+        assert_eq!(authorities[0].get_ttl(), 91);
+    }
+
+    #[test]
+    fn test_header() {
+        // the udp payload of test_whole_packet_1, decoded from the whole frame
+        let data = vec![80, 235, 113, 35, 142, 103, 152, 0, 106, 4, 85, 32, 8, 0, 69, 0, 0, 130, 170, 10, 64, 0, 64, 17, 12, 250, 192, 168, 1, 1, 192, 168, 1, 21, 0, 53, 234, 64, 0, 110, 71, 245, 212, 212, 129, 131, 0, 1, 0, 0, 0, 1, 0, 0, 4, 119, 112, 97, 100, 4, 104, 111, 109, 101, 0, 0, 1, 0, 1, 0, 0, 6, 0, 1, 0, 0, 0, 91, 0, 64, 1, 97, 12, 114, 111, 111, 116, 45, 115, 101, 114, 118, 101, 114, 115, 3, 110, 101, 116, 0, 5, 110, 115, 116, 108, 100, 12, 118, 101, 114, 105, 115, 105, 103, 110, 45, 103, 114, 115, 3, 99, 111, 109, 0, 120, 134, 93, 48, 0, 0, 7, 8, 0, 0, 3, 132, 0, 9, 58, 128, 0, 1, 81, 128];
+        let packet = try_parse(&data).unwrap();
+        assert!(matches!(packet.get_transport_header(), Some(TransportHeader::Udp(header)) if header.get_src_port() == DNS_PORT));
+        let (message_result, _payload) = DnsMessage::decode(packet.get_payload().to_vec());
+        let message = message_result.unwrap();
+        assert_eq!(message.get_query_name(), Some("wpad.home".to_string()));
+        assert_eq!(message.get_id(), 0xd4d4);
+
+        let flags = message.get_flags();
+        assert!(flags.is_response());
+        assert_eq!(flags.get_opcode(), 0);
+        assert!(!flags.is_authoritative() && !flags.is_truncated());
+        assert!(flags.is_recursion_desired() && flags.is_recursion_available());
+        assert_eq!(flags.get_rcode(), DnsRcode::NXDomain);
+    }
+
+    #[test]
+    fn test_compressed_answer() {
+        // an AAAA answer whose name points to the question, then a pointer to itself
+        let mut data = vec![0, 1, 129, 128, 0, 1, 0, 1, 0, 0, 0, 0, 3, 119, 119, 119, 0, 0, 28, 0, 1, 0xc0, 12, 0, 28, 0, 1, 0, 0, 1, 0, 0, 16];
+        data.extend_from_slice(&[0; 16]);
+        let (message_result, _payload) = DnsMessage::decode(data);
+        let answers = message_result.unwrap().get_answers();
+        assert_eq!(answers[0].get_name(), "www");
+        assert_eq!(answers[0].get_type(), DnsType::AAAA);
+
+        let (message_result, data) = DnsMessage::decode(vec![0, 1, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0xc0, 12, 0, 1, 0, 1]);
+        assert!(message_result.is_err());
+        assert_eq!(data.len(), 18);
+    }
+
+    #[test]
+    fn test_dns_over_tcp() {
+        // two queries for the A record of www, the first one split across three segments, the second one in the last
+        let query = vec![0, 2, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 3, 119, 119, 119, 0, 0, 1, 0, 1];
+        let mut data = (query.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(&query);
+        data.extend_from_slice(&data.clone());
+
+        let mut stream = TcpStream::new();
+        let mut reader = DnsTcpReader::new();
+        let mut messages = Vec::new();
+        stream.push(7000, true, &[]);
+        for (offset, segment) in [(0, &data[0..1]), (10, &data[10..30]), (1, &data[1..10]), (30, &data[30..])] {
+            messages.extend(reader.push(&stream.push(7001 + offset as u32, false, segment)));
+            if offset == 10 {
+                assert!(messages.is_empty());
+            }
+        }
+
+        assert_eq!(messages.len(), 2);
+        for message in messages {
+            let questions = message.unwrap().get_questions();
+            assert_eq!((questions[0].get_name(), questions[0].get_type()), ("www".to_string(), DnsType::A));
+        }
+        assert_eq!(reader.get_buffered_bytes(), 0);
+    }
+}
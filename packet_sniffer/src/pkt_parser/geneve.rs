@@ -0,0 +1,148 @@
+//! geneve
+//! This module decodes the GENEVE encapsulation (RFC 8926), carried by UDP on port 6081, that the cloud overlays use to
+//! tunnel the frames of a virtual network between the hosts. Its header identifies the virtual network (VNI) and carries
+//! a list of options, and it is followed by the inner frame, that can be decoded again from the link layer.
+
+use crate::pkt_parser::{decode_owned, DecodeError, DecodeErrorKind, Header, SliceHeader};
+
+/// The UDP port of GENEVE.
+pub const GENEVE_PORT: u16 = 6081;
+
+/// The protocol type announcing an inner Ethernet frame (Transparent Ethernet Bridging).
+pub const ETHERNET_PROTOCOL_TYPE: u16 = 0x6558;
+
+/// describes a GENEVE option: its class, type and data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneveOption {
+    class: u16,
+    option_type: u8,
+    data: Vec<u8>,
+}
+
+impl GeneveOption {
+    pub fn get_class(&self) -> u16 { self.class }
+    pub fn get_type(&self) -> u8 { self.option_type }
+    pub fn get_data(&self) -> &[u8] { &self.data }
+    /// Returns true if the tunnel endpoint must drop the packet when it does not understand the option.
+    pub fn is_critical(&self) -> bool { self.option_type & 0x80 != 0 }
+}
+
+/// describes a GENEVE Header: the version, the flags, the protocol of the inner frame, the Virtual Network Identifier and
+/// the options.
+#[derive(Debug, Clone)]
+pub struct GeneveHeader {
+    version: u8,
+    oam: bool,
+    critical: bool,
+    protocol_type: u16,
+    vni: u32,
+    options: Vec<GeneveOption>,
+}
+
+impl SliceHeader for GeneveHeader {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
+        let len = data.len();
+        if len < 8 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode a geneve header because is not long enough.".to_string()}), data) }
+        let version = data[0] >> 6;
+        if version != 0 {
+            return (Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: format!("Unsupported geneve version {}", version)}), data)
+        }
+        // the options length is given in 4 bytes units
+        let header_len = 8 + (data[0] & 0x3f) as usize * 4;
+        if len < header_len { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode a geneve header because is not long enough.".to_string()}), data) }
+
+        let mut options = Vec::new();
+        let mut offset = 8;
+        while offset + 4 <= header_len {
+            let option_len = 4 + (data[offset + 3] & 0x1f) as usize * 4;
+            if offset + option_len > header_len {
+                return (Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: "Malformed geneve option.".to_string()}), data)
+            }
+            options.push(GeneveOption {
+                class: u16::from_be_bytes([data[offset], data[offset + 1]]),
+                option_type: data[offset + 2],
+                data: data[offset + 4..offset + option_len].to_vec(),
+            });
+            offset += option_len;
+        }
+        (
+            Ok(GeneveHeader {
+                version,
+                oam: data[1] & 0x80 != 0,
+                critical: data[1] & 0x40 != 0,
+                protocol_type: u16::from_be_bytes([data[2], data[3]]),
+                vni: u32::from_be_bytes([0, data[4], data[5], data[6]]),
+                options,
+            }),
+            &data[header_len..len]
+        )
+    }
+}
+
+impl Header for GeneveHeader {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
+impl GeneveHeader {
+    pub fn get_version(&self) -> u8 { self.version }
+    /// Returns true for the control messages of the tunnel endpoints (Operations, Administration and Maintenance).
+    pub fn is_oam(&self) -> bool { self.oam }
+    /// Returns true if some options are critical.
+    pub fn has_critical_options(&self) -> bool { self.critical }
+    /// The ether type of the inner frame: ETHERNET_PROTOCOL_TYPE for an Ethernet frame, or the type of the packet.
+    pub fn get_protocol_type(&self) -> u16 { self.protocol_type }
+    /// The Virtual Network Identifier, the virtual network the inner frame belongs to.
+    pub fn get_vni(&self) -> u32 { self.vni }
+    pub fn get_options(&self) -> Vec<GeneveOption> { self.options.clone() }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pkt_parser::geneve::{*};
+    use crate::pkt_parser::{EthernetHeader, EtherType, Ipv4Header, UDPHeader};
+
+    #[test]
+    fn test_geneve_packet() {
+        // UDP to port 6081, GENEVE with VNI 0x12345 and a 4 bytes option of class 0x0103, an inner Ethernet/IPv4/UDP frame
+        let mut data = vec![0xc3, 0x50, 0x17, 0xc1, 0, 70, 0, 0];
+        data.extend_from_slice(&[0x02, 0x00, 0x65, 0x58, 0x01, 0x23, 0x45, 0x00, 0x01, 0x03, 0x80, 0x01, 0xde, 0xad, 0xbe, 0xef]);
+        data.extend_from_slice(&[2, 0, 0, 0, 0, 2, 2, 0, 0, 0, 0, 1, 8, 0]);
+        data.extend_from_slice(&[69, 0, 0, 28, 0, 0, 64, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        data.extend_from_slice(&[0, 53, 0, 53, 0, 8, 0, 0]);
+
+        let (udp_header_result, udp_payload) = UDPHeader::decode(data);
+        assert_eq!(udp_header_result.unwrap().get_dest_port(), GENEVE_PORT);
+        let (geneve_header_result, inner_frame) = GeneveHeader::decode(udp_payload);
+        let geneve_header = geneve_header_result.unwrap();
+        assert_eq!(geneve_header.get_vni(), 0x12345);
+        assert_eq!(geneve_header.get_protocol_type(), ETHERNET_PROTOCOL_TYPE);
+        assert!(!geneve_header.is_oam());
+        let options = geneve_header.get_options();
+        assert_eq!(options.len(), 1);
+        assert_eq!(options[0].get_class(), 0x0103);
+        assert!(options[0].is_critical());
+        assert_eq!(options[0].get_data(), &[0xde, 0xad, 0xbe, 0xef]);
+
+        let (ethernet_header_result, ethernet_payload) = EthernetHeader::decode(inner_frame);
+        let ethernet_header = ethernet_header_result.unwrap();
+        assert_eq!(ethernet_header.get_src_address(), "020000000001");
+        assert_eq!(ethernet_header.get_ether_type(), EtherType::Ipv4);
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode(ethernet_payload);
+        let ipv4_header = ipv4_header_result.unwrap();
+        assert_eq!(ipv4_header.get_src_address(), "10.0.0.1");
+        assert_eq!(ipv4_header.get_dest_address(), "10.0.0.2");
+    }
+
+    #[test]
+    fn test_malformed_geneve_header() {
+        // the header announces 8 bytes of options, but only 4 are there
+        let (geneve_header_result, data) = GeneveHeader::decode(vec![0x02, 0, 0x65, 0x58, 0, 0, 1, 0, 0, 0, 0, 0]);
+        assert!(geneve_header_result.is_err());
+        assert_eq!(data.len(), 12);
+        // an option longer than the options
+        let (geneve_header_result, _data) = GeneveHeader::decode(vec![0x01, 0, 0x65, 0x58, 0, 0, 1, 0, 0, 0, 0, 2]);
+        assert!(geneve_header_result.is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! gre
+//! This module decodes the Generic Routing Encapsulation (RFC 2784 and RFC 2890), carried by IP as protocol 47, that the
+//! VPNs and the routers use to tunnel a packet of any protocol between two hosts. Its header gives the ether type of the
+//! inner packet, so that an inner IP packet can be decoded again from the network layer (see decode_ip_layer).
+
+use crate::pkt_parser::{decode_owned, DecodeError, DecodeErrorKind, Header, SliceHeader};
+
+/// The IP protocol number of GRE.
+pub const GRE_PROTOCOL: u8 = 47;
+
+/// describes a GRE Header: the version, the ether type of the inner packet and the optional fields announced by the
+/// flags, the checksum, the key identifying the tunnel and the sequence number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreHeader {
+    version: u8,
+    protocol_type: u16,
+    checksum: Option<u16>,
+    key: Option<u32>,
+    sequence_number: Option<u32>,
+}
+
+impl SliceHeader for GreHeader {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
+        let len = data.len();
+        if len < 4 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode a gre header because is not long enough.".to_string()}), data) }
+        let version = data[1] & 0x07;
+        // the version 1 is the enhanced GRE of PPTP, that carries PPP frames
+        if version != 0 {
+            return (Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: format!("Unsupported gre version {}", version)}), data)
+        }
+        let has_checksum = data[0] & 0x80 != 0;
+        let has_key = data[0] & 0x20 != 0;
+        let has_sequence_number = data[0] & 0x10 != 0;
+        // every optional field takes 4 bytes, the checksum is followed by 2 reserved bytes
+        let header_len = 4 + 4 * (has_checksum as usize + has_key as usize + has_sequence_number as usize);
+        if len < header_len { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode a gre header because is not long enough.".to_string()}), data) }
+
+        let mut offset = 4;
+        let mut next_field = |present: bool| {
+            if !present { return None }
+            let field = u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+            offset += 4;
+            Some(field)
+        };
+        let checksum = next_field(has_checksum).map(|field| (field >> 16) as u16);
+        let key = next_field(has_key);
+        let sequence_number = next_field(has_sequence_number);
+        (
+            Ok(GreHeader {
+                version,
+                protocol_type: u16::from_be_bytes([data[2], data[3]]),
+                checksum,
+                key,
+                sequence_number,
+            }),
+            &data[header_len..len]
+        )
+    }
+}
+
+impl Header for GreHeader {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
+impl GreHeader {
+    pub fn get_version(&self) -> u8 { self.version }
+    /// The ether type of the inner packet, e.g. 0x0800 for IPv4.
+    pub fn get_protocol_type(&self) -> u16 { self.protocol_type }
+    pub fn get_checksum(&self) -> Option<u16> { self.checksum }
+    /// The key telling apart the tunnels between the same two hosts.
+    pub fn get_key(&self) -> Option<u32> { self.key }
+    pub fn get_sequence_number(&self) -> Option<u32> { self.sequence_number }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pkt_parser::gre::{*};
+    use pcap::Device;
+    use crate::pkt_parser::{decode_ip_layer, decode_ip_layer_with_limit, parse_packet, DecodeErrorKind, EtherType, Ipv4Header,
+        NetworkHeader, Protocol, TimeVal, UDPHeader, MAX_TUNNEL_DEPTH};
+
+    #[test]
+    fn test_gre_packet() {
+        // 203.0.113.1 -> 198.51.100.1 carrying GRE with the key 42, around 10.0.0.1 -> 10.0.0.2 carrying UDP
+        let mut data = vec![69, 0, 0, 64, 0, 0, 64, 0, 64, 47, 0, 0, 203, 0, 113, 1, 198, 51, 100, 1];
+        data.extend_from_slice(&[0x20, 0x00, 0x08, 0x00, 0, 0, 0, 42]);
+        data.extend_from_slice(&[69, 0, 0, 36, 0, 0, 64, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        data.extend_from_slice(&[0x13, 0xc4, 0, 53, 0, 16, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode(data.clone());
+        assert_eq!(ipv4_header_result.unwrap().get_protocol(), Protocol::GRE);
+        let (gre_header_result, gre_payload) = GreHeader::decode(ipv4_payload);
+        let gre_header = gre_header_result.unwrap();
+        assert_eq!(gre_header.get_protocol_type(), 0x0800);
+        assert_eq!(gre_header.get_key(), Some(42));
+        assert_eq!(gre_header.get_checksum(), None);
+        assert_eq!(gre_payload.len(), 36);
+
+        // the tunnel is unwrapped down to the inner packet
+        let (network_header, payload) = decode_ip_layer(EtherType::Ipv4, data).unwrap();
+        match network_header {
+            NetworkHeader::Ipv4(inner) => {
+                assert_eq!(inner.get_src_address(), "10.0.0.1");
+                assert_eq!(inner.get_protocol(), Protocol::UDP);
+            },
+            other => panic!("Unexpected network header {:?}", other)
+        }
+        assert_eq!(UDPHeader::decode(payload).0.unwrap().get_dest_port(), 53);
+    }
+
+    #[test]
+    fn test_malformed_gre_header() {
+        // the checksum and sequence number flags announce 8 more bytes
+        let (gre_header_result, data) = GreHeader::decode(vec![0x90, 0, 0x08, 0, 0, 0, 0, 0]);
+        assert!(gre_header_result.is_err());
+        assert_eq!(data.len(), 8);
+        // PPTP
+        let (gre_header_result, _data) = GreHeader::decode(vec![0x30, 0x81, 0x88, 0x0b, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert!(gre_header_result.is_err());
+    }
+
+    /// Wraps the packet in the given number of IPv4 and GRE headers.
+    fn tunnel(mut packet: Vec<u8>, depth: usize) -> Vec<u8> {
+        for _ in 0..depth {
+            let total_length = (packet.len() + 24) as u16;
+            let mut outer = vec![69, 0, (total_length >> 8) as u8, total_length as u8, 0, 0, 64, 0, 64, 47, 0, 0, 203, 0, 113, 1, 198, 51, 100, 1];
+            outer.extend_from_slice(&[0, 0, 0x08, 0x00]);
+            outer.extend_from_slice(&packet);
+            packet = outer;
+        }
+        packet
+    }
+
+    #[test]
+    fn test_tunnel_depth_limit() {
+        let inner = vec![69, 0, 0, 20, 0, 0, 64, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2];
+        let (network_header, _payload) = decode_ip_layer(EtherType::Ipv4, tunnel(inner.clone(), MAX_TUNNEL_DEPTH)).unwrap();
+        assert!(matches!(network_header, NetworkHeader::Ipv4(header) if header.get_src_address() == "10.0.0.1"));
+
+        let error = decode_ip_layer(EtherType::Ipv4, tunnel(inner.clone(), MAX_TUNNEL_DEPTH + 1)).unwrap_err();
+        assert_eq!(error.kind, DecodeErrorKind::Malformed);
+        // a deep chain is rejected without being walked to the end
+        assert!(decode_ip_layer(EtherType::Ipv4, tunnel(inner.clone(), 1000)).is_err());
+
+        // the limit is configurable
+        assert!(decode_ip_layer_with_limit(EtherType::Ipv4, tunnel(inner.clone(), 1), 0).is_err());
+        assert!(decode_ip_layer_with_limit(EtherType::Ipv4, tunnel(inner.clone(), 0), 0).is_ok());
+        assert!(decode_ip_layer_with_limit(EtherType::Ipv4, tunnel(inner.clone(), 20), 20).is_ok());
+
+        // parse_packet walks the tunnels under the same limit
+        let device = Device { name: "eth0".to_string(), desc: None, addresses: vec![], flags: pcap::DeviceFlags::empty() };
+        let frame = |depth| {
+            let mut frame = vec![2, 0, 0, 0, 0, 2, 2, 0, 0, 0, 0, 1, 8, 0];
+            frame.extend_from_slice(&tunnel(inner.clone(), depth));
+            frame
+        };
+        assert_eq!(parse_packet(frame(MAX_TUNNEL_DEPTH), &device, TimeVal::from(0)).unwrap().get_protocol(), Protocol::GRE);
+        assert!(parse_packet(frame(MAX_TUNNEL_DEPTH + 1), &device, TimeVal::from(0)).is_err());
+    }
+}
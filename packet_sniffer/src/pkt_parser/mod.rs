@@ -2,16 +2,51 @@
 //! This module defines a common way to decode the main protocol from the TCP/IP stack, including also Ethernet from layer 2.
 //!
 //! From now, the module can decode the following protocols:
-//! - Ethernet
-//! - IP(v4 and v6)
+//! - Ethernet (with 802.1Q VLAN tags)
+//! - Null/Loopback (BSD)
+//! - Linux cooked capture (SLL and SLL2)
+//! - ARP
+//! - IP(v4 and v6), walking the IPv6 extension headers, with the Routing one (see the routing submodule)
 //! - TCP
 //! - UDP
+//! - SCTP, its common header
+//! - ICMP
+//! - ICMPv6, with the Neighbor Discovery messages (see the ndp submodule)
+//! - DNS, the question and record types (see the dns submodule)
+//! - TLS, the server name requested by the ClientHello (see the tls submodule)
+//! - GENEVE, the tunnels of the cloud overlays, whose inner frames can be decoded again (see the geneve submodule)
+//! - GRE, whose inner IP packets are decoded by decode_ip_layer (see the gre submodule)
+//! - IP-in-IP, the IPv4 packets carried by IP (protocol 4), decoded by decode_ip_layer as well
+//!
+//! parse_headers_only is a faster alternative to try_parse, that reads only the addresses and the ports, borrowing the frame.
+//!
+//! Every header implements SliceHeader too, decoding a borrowed slice, and the functions decoding a whole packet have a
+//! slice counterpart (try_parse_slice, decode_link_layer_slice, decode_ip_layer_slice, parse_packet_slice) that copies
+//! no payload: the owned versions are built on them.
 //!
 //! In a first approximation, we decided to ot consider application layer protocols.
 
+use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
-use pcap::Device;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::Sub;
+use std::time::Duration;
+use chrono::{TimeZone, Utc};
+use pcap::{Device, Linktype};
+use crate::builder::{BuildError, FrameBuilder};
+use crate::checksum::internet_checksum;
+use crate::flow::ConnectionKey;
+use crate::pkt_parser::gre::GreHeader;
+use crate::pkt_parser::routing::{Ipv6RoutingHeader, ROUTING_NEXT_HEADER};
+
+pub mod dns;
+pub mod geneve;
+pub mod gre;
+pub mod ndp;
+pub mod routing;
+pub mod tls;
+pub mod trailer;
 
 /// This module contains some utility function to print u8 slices as address, as defined in the most common protocol.
 mod utils {
@@ -60,23 +95,80 @@ mod utils {
         address.iter().map(|b| b.to_string()).collect::<Vec<String>>().join(".")
     }
 
+    /// Formats the 16 bytes of an IPv6 address in the canonical form of RFC 5952, the one of the addresses listed by pcap:
+    /// eight colon-separated hextets without the leading zeros, the longest run of zero hextets compressed as "::".
     pub fn ipv6_address_to_string(address: &[u8]) -> String {
-        address.iter().hex_display().to_string().replace(" ", "")
+        let mut octets = [0; 16];
+        octets.copy_from_slice(address);
+        std::net::Ipv6Addr::from(octets).to_string()
+    }
+
+    pub fn payload_to_hex_string(payload: &[u8], max: usize) -> String {
+        if payload.len() > max {
+            format!("{}...", payload[..max].hex_display())
+        } else { payload.hex_display().to_string() }
     }
 }
 
+/// Formats the first max bytes of a payload as hex digits, for a compact preview in the logs. A longer payload is truncated
+/// and "..." is appended to mark it.
+pub fn payload_hex(payload: &[u8], max: usize) -> String {
+    utils::payload_to_hex_string(payload, max)
+}
+
 /// The Header trait define a common behaviour. It requires a decode function that extract from raw data a new header and the remaining bytes.
 pub trait Header: Debug + Clone {
     fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>);
+
+    /// Decodes the header as decode does, returning also the number of bytes it takes (0 if it cannot be decoded): the
+    /// length of the headers with options, as IPv4 and TCP, is known only after decoding them.
+    fn decode_with_len(data: Vec<u8>) -> (Result<Self, DecodeError>, usize, Vec<u8>) {
+        let len = data.len();
+        let (result, remaining) = Self::decode(data);
+        let consumed = if result.is_ok() { len - remaining.len() } else { 0 };
+        (result, consumed, remaining)
+    }
+}
+
+/// The zero-copy counterpart of Header: decode_slice borrows the data and returns the remaining bytes as a sub-slice of
+/// it, so that decoding a whole stack of headers copies no payload.
+pub trait SliceHeader: Sized {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]);
+}
+
+/// Decodes owned data with decode_slice, copying only the remaining bytes. On an error the data is given back untouched.
+fn decode_owned<T: SliceHeader>(data: Vec<u8>) -> (Result<T, DecodeError>, Vec<u8>) {
+    let (result, remaining) = T::decode_slice(&data);
+    match result {
+        Ok(header) => {
+            let remaining = remaining.to_vec();
+            (Ok(header), remaining)
+        },
+        Err(e) => (Err(e), data)
+    }
 }
 
 /// A custom error to be returned by a decode function. Some common error can be "next protocol not defined", or "cannot parse an header" because of
-/// damaged packet, so it can be good to discard the packet.
+/// damaged packet, so it can be good to discard the packet. The kind tells the errors apart, the msg is the one displayed.
 #[derive(Debug, Clone)]
 pub struct DecodeError{
+    pub kind: DecodeErrorKind,
     pub msg: String
 }
 
+/// An Enum that describe why a packet could not be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeErrorKind {
+    /// The data ends before the header does.
+    TooShort,
+    /// The ether type of a frame we cannot decode.
+    UnknownEtherType(u16),
+    /// The IP protocol number of a transport layer we cannot decode.
+    UnknownProtocol(u8),
+    /// A header whose fields are inconsistent or out of range.
+    Malformed,
+}
+
 impl Display for DecodeError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "Decode error: {}", self.msg)
@@ -86,78 +178,466 @@ impl Display for DecodeError {
 
 /// An Enum that describe the packet direction
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Direction {
     Received,
     Transmitted
 }
 
-pub fn get_direction_from_ipv4(header: Ipv4Header, device: Device) -> Direction {
-    if device.addresses.iter().any(|a| a.addr.to_string() == header.get_src_address()) {
+/// Returns Transmitted if the source of the packet is one of the addresses of the device, Received otherwise.
+pub fn get_direction_from_ipv4(header: &Ipv4Header, device: &Device) -> Direction {
+    if is_device_address(device, &header.get_src_address()) {
         Direction::Transmitted
     } else { Direction::Received }
 }
 
-pub fn get_direction_from_ipv6(header: Ipv6Header, device: Device) -> Direction {
-    if device.addresses.iter().any(|a| a.addr.to_string() ==  header.get_src_address()) {
+/// Returns Transmitted if the source of the packet is one of the addresses of the device, Received otherwise.
+pub fn get_direction_from_ipv6(header: &Ipv6Header, device: &Device) -> Direction {
+    if is_device_address(device, &header.get_src_address()) {
         Direction::Transmitted
     } else { Direction::Received }
 }
 
-/// Ether type that we can decode
-#[derive(Debug, Clone, PartialEq)]
+/// Returns true if the address is one of the device. The addresses are compared parsed, so that the ones written
+/// differently, as an IPv6 address with or without the zero compression, still match.
+fn is_device_address(device: &Device, address: &str) -> bool {
+    match address.parse::<IpAddr>() {
+        Ok(address) => device.addresses.iter().any(|a| a.addr == address),
+        Err(_) => false
+    }
+}
+
+/// Returns the hardware address of the device, formatted as the addresses returned by EthernetHeader, if it can be found.
+/// pcap does not report link layer addresses, so on Linux it is read from sysfs, while on the other platforms it is unknown.
+pub fn get_device_mac_address(device: &Device) -> Option<String> {
+    if cfg!(target_os = "linux") {
+        let address = std::fs::read_to_string(format!("/sys/class/net/{}/address", device.name)).ok()?;
+        let address = address.trim().replace(':', "").to_lowercase();
+        if address.len() == 12 { Some(address) } else { None }
+    } else { None }
+}
+
+/// Infers the direction of a frame comparing its MAC addresses with the given hardware address of the capturing interface.
+/// It returns None if neither the source nor the destination belong to the interface.
+pub fn get_direction_from_mac(header: &EthernetHeader, mac_address: &str) -> Option<Direction> {
+    let mac_address = mac_address.replace(':', "").to_lowercase();
+    if header.get_src_address() == mac_address {
+        Some(Direction::Transmitted)
+    } else if header.get_dest_address() == mac_address {
+        Some(Direction::Received)
+    } else { None }
+}
+
+/// Infers the direction of a frame from the link layer, useful when the device has no layer 3 addresses (e.g. a bridged capture).
+/// It returns None when the device MAC address is unknown or the frame does not involve the device.
+pub fn get_direction_from_ethernet(header: &EthernetHeader, device: &Device) -> Option<Direction> {
+    get_direction_from_ethernet_with(header, device, get_device_mac_address)
+}
+
+/// Same as get_direction_from_ethernet, with the function that looks up the hardware address of the device, e.g. to take
+/// it from the configuration on the platforms where get_device_mac_address cannot find it.
+pub fn get_direction_from_ethernet_with<F>(header: &EthernetHeader, device: &Device, mac_lookup: F) -> Option<Direction>
+    where F: Fn(&Device) -> Option<String>
+{
+    get_direction_from_mac(header, &mac_lookup(device)?)
+}
+
+/// How a packet is delivered according to its destination IP address: to a single host, to a group or to the whole network.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressCast {
+    Unicast,
+    Multicast,
+    Broadcast,
+}
+
+/// Classifies an IPv4 destination address: 224.0.0.0/4 is multicast, while 255.255.255.255 is broadcast.
+/// If the netmask of the network is given, also the subnet broadcast address (all the host bits set) is recognized.
+pub fn get_ipv4_address_cast(address: &Ipv4Addr, netmask: Option<&Ipv4Addr>) -> AddressCast {
+    if address.is_multicast() {
+        return AddressCast::Multicast
+    }
+    if address.is_broadcast() {
+        return AddressCast::Broadcast
+    }
+    match netmask.map(|mask| u32::from(*mask)) {
+        // point to point networks (/31 and /32) have no broadcast address
+        Some(mask) if !mask > 1 && u32::from(*address) & !mask == !mask => AddressCast::Broadcast,
+        _ => AddressCast::Unicast
+    }
+}
+
+/// Classifies an IPv6 destination address: ff00::/8 is multicast. IPv6 has no broadcast, its role is played by the
+/// all-nodes multicast group.
+pub fn get_ipv6_address_cast(address: &Ipv6Addr) -> AddressCast {
+    if address.is_multicast() { AddressCast::Multicast } else { AddressCast::Unicast }
+}
+
+/// Ether type that we can decode, the other ones are kept with their value
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum EtherType {
     Ipv4,
     Ipv6,
     ARP,
+    Other(u16),
+}
+
+/// Maps the value of an ether type field to the protocols we can decode.
+fn decode_ether_type(value: u16) -> EtherType {
+    match value {
+        0x0800 => EtherType::Ipv4,
+        0x0806 => EtherType::ARP,
+        0x86DD => EtherType::Ipv6,
+        val => EtherType::Other(val)
+    }
+}
+
+/// The error of the decoders going past the link layer, that cannot decode the network layer of the frame.
+fn unknown_ether_type(value: u16) -> DecodeError {
+    DecodeError{kind: DecodeErrorKind::UnknownEtherType(value), msg: format!("Cannot get the correct ether type, received 0x{:x}", value)}
+}
+
+/// describes an 802.1Q VLAN tag, inserted in the Ethernet header before the real ether type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VlanTag {
+    tci: u16,
+}
+
+impl VlanTag {
+    /// The Priority Code Point (3 bits), the QoS class of the frame.
+    pub fn priority(&self) -> u8 { (self.tci >> 13) as u8 }
+    /// The Drop Eligible Indicator.
+    pub fn is_drop_eligible(&self) -> bool { self.tci & 0x1000 != 0 }
+    /// The VLAN identifier (12 bits).
+    pub fn get_id(&self) -> u16 { self.tci & 0x0fff }
 }
 
 /// describes an Ethernet Header.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EthernetHeader {
     _dest: String,
     _src: String,
     ether_type: EtherType,
+    vlan_tag: Option<VlanTag>,
 }
 
-impl Header for EthernetHeader {
-    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+impl SliceHeader for EthernetHeader {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
         let len = data.len();
-        if len < 14 { return (Err(DecodeError{msg: "Cannot decode an ethernet packet because is not long enough.".to_string()}), data) }
-        // Extracting data
-        let eth_header = &data[0..14];
-        let ether_type_vec = &eth_header[12..14];
+        if len < 14 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode an ethernet packet because is not long enough.".to_string()}), data) }
+        // Extracting data, an 802.1Q tag moves the ether type 4 bytes forward
+        let tagged = data[12] == 0x81 && data[13] == 0x00;
+        if tagged && len < 18 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode a vlan tagged ethernet packet because is not long enough.".to_string()}), data) }
+        let header_len = if tagged { 18 } else { 14 };
+        let eth_header = &data[0..header_len];
+        let ether_type_vec = &eth_header[header_len - 2..header_len];
+        let vlan_tag = if tagged { Some(VlanTag{ tci: ((eth_header[14] as u16) << 8) | eth_header[15] as u16 }) } else { None };
         // println!("Entire header: {:x?} \n Destination MAC address: {:x?} Source MAC address: {:x?} Ether type: {:x?}", eth_header, &eth_header[0..6], &eth_header[6..12], ether_type);
-        let ether_payload = &data[14..len];
+        let ether_payload = &data[header_len..len];
 
-        let ether_type = match ((ether_type_vec[0] as u16) << 8) | ether_type_vec[1] as u16 {
-            0x0800 => EtherType::Ipv4,
-            0x0806 => EtherType::ARP,
-            0x86DD => EtherType::Ipv6,
-            val => return (
-                Err(DecodeError{msg: format!("Cannot get the correct ether type, received 0x{:x}", val).to_string()}),
-                data
-            )
-        };
+        let ether_type = decode_ether_type(((ether_type_vec[0] as u16) << 8) | ether_type_vec[1] as u16);
         (
-            Ok(EthernetHeader{_dest: utils::mac_address_to_string(&eth_header[0..6]), _src: utils::mac_address_to_string(&eth_header[6..12]) , ether_type }),
-            Vec::from(ether_payload)
+            Ok(EthernetHeader{_dest: utils::mac_address_to_string(&eth_header[0..6]), _src: utils::mac_address_to_string(&eth_header[6..12]) , ether_type, vlan_tag }),
+            ether_payload
         )
     }
 }
 
+impl Header for EthernetHeader {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
 impl EthernetHeader {
     pub fn get_ether_type(&self) -> EtherType {
         return self.ether_type.clone();
     }
     pub fn get_src_address(&self) -> String { return self._src.clone(); }
     pub fn get_dest_address(&self) -> String { return self._dest.clone(); }
+    pub fn get_vlan_tag(&self) -> Option<VlanTag> { self.vlan_tag }
+    /// The identifier of the VLAN of a tagged frame, None for the untagged ones.
+    pub fn get_vlan_id(&self) -> Option<u16> { self.vlan_tag.map(|tag| tag.get_id()) }
+}
+
+/// Formats the header on a single line, e.g. "50eb71238e67 -> 98006a045520, Ipv4", with the VLAN of the tagged frames.
+impl Display for EthernetHeader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}, {:?}", self.get_src_address(), self.get_dest_address(), self.get_ether_type())?;
+        match self.get_vlan_id() {
+            Some(vlan_id) => write!(f, " (vlan {})", vlan_id),
+            None => Ok(())
+        }
+    }
+}
+
+/// describes a Null/Loopback Header (DLT_NULL), used by loopback captures on macOS/BSD.
+/// It is made of a 4 bytes address family, written in the byte order of the capturing host.
+#[derive(Debug, Clone)]
+pub struct NullHeader {
+    family: u32,
+    ether_type: EtherType,
+}
+
+impl SliceHeader for NullHeader {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
+        let len = data.len();
+        if len < 4 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode a null/loopback packet because is not long enough.".to_string()}), data) }
+        // The address family always fits in the low 16 bits, so the byte order giving the smaller value is the right one
+        let bytes = [data[0], data[1], data[2], data[3]];
+        let family = u32::min(u32::from_le_bytes(bytes), u32::from_be_bytes(bytes));
+
+        let ether_type = match family {
+            // AF_INET has the same value on every platform
+            2 => EtherType::Ipv4,
+            // AF_INET6 differs between Linux (10), NetBSD/OpenBSD (24), FreeBSD (28) and macOS (30)
+            10 | 24 | 28 | 30 => EtherType::Ipv6,
+            val => return (
+                Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: format!("Cannot get the correct address family, received {}", val)}),
+                data
+            )
+        };
+        (
+            Ok(NullHeader{family, ether_type}),
+            &data[4..len]
+        )
+    }
+}
+
+impl Header for NullHeader {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
+impl NullHeader {
+    pub fn get_family(&self) -> u32 { self.family }
+    pub fn get_ether_type(&self) -> EtherType { self.ether_type.clone() }
+}
+
+/// describes a Linux cooked capture Header (LINKTYPE_LINUX_SLL), used by the captures on the "any" device of Linux, where
+/// the packets of different interfaces cannot share a real link layer header.
+#[derive(Debug, Clone)]
+pub struct SllHeader {
+    packet_type: u16,
+    arphrd_type: u16,
+    address: String,
+    ether_type: EtherType,
+}
+
+impl SliceHeader for SllHeader {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
+        let len = data.len();
+        if len < 16 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode a sll packet because is not long enough.".to_string()}), data) }
+        let ether_type = decode_ether_type(((data[14] as u16) << 8) | data[15] as u16);
+        // the link layer address field is 8 bytes long, whatever the length of the address
+        let address_len = usize::min((((data[4] as u16) << 8) | data[5] as u16) as usize, 8);
+        (
+            Ok(SllHeader{
+                packet_type: ((data[0] as u16) << 8) | data[1] as u16,
+                arphrd_type: ((data[2] as u16) << 8) | data[3] as u16,
+                address: utils::mac_address_to_string(&data[6..6 + address_len]),
+                ether_type,
+            }),
+            &data[16..len]
+        )
+    }
+}
+
+impl Header for SllHeader {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
+impl SllHeader {
+    pub fn get_ether_type(&self) -> EtherType { self.ether_type.clone() }
+    /// The ARPHRD_ type of the interface, 1 for Ethernet.
+    pub fn get_arphrd_type(&self) -> u16 { self.arphrd_type }
+    /// The packet type: 0 sent to us, 1 broadcast, 2 multicast, 3 sent to somebody else, 4 sent by us.
+    pub fn get_packet_type(&self) -> u16 { self.packet_type }
+    /// The link layer address of the sender of the packet.
+    pub fn get_src_address(&self) -> String { self.address.clone() }
+}
+
+/// describes a Linux cooked capture v2 Header (LINKTYPE_LINUX_SLL2), used by the captures on the "any" device of recent
+/// Linux versions. Unlike the first version, it starts with the protocol type and records the interface of the packet.
+#[derive(Debug, Clone)]
+pub struct Sll2Header {
+    ether_type: EtherType,
+    interface_index: u32,
+    arphrd_type: u16,
+    packet_type: u8,
+    address: String,
+}
+
+impl SliceHeader for Sll2Header {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
+        let len = data.len();
+        if len < 20 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode a sll2 packet because is not long enough.".to_string()}), data) }
+        let ether_type = decode_ether_type(((data[0] as u16) << 8) | data[1] as u16);
+        // the link layer address field is 8 bytes long, whatever the length of the address
+        let address_len = usize::min(data[11] as usize, 8);
+        (
+            Ok(Sll2Header{
+                ether_type,
+                interface_index: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+                arphrd_type: ((data[8] as u16) << 8) | data[9] as u16,
+                packet_type: data[10],
+                address: utils::mac_address_to_string(&data[12..12 + address_len]),
+            }),
+            &data[20..len]
+        )
+    }
+}
+
+impl Header for Sll2Header {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
+impl Sll2Header {
+    pub fn get_ether_type(&self) -> EtherType { self.ether_type.clone() }
+    /// The index of the interface the packet has been captured on.
+    pub fn get_interface_index(&self) -> u32 { self.interface_index }
+    /// The ARPHRD_ type of the interface, 1 for Ethernet.
+    pub fn get_arphrd_type(&self) -> u16 { self.arphrd_type }
+    /// The packet type: 0 sent to us, 1 broadcast, 2 multicast, 3 sent to somebody else, 4 sent by us.
+    pub fn get_packet_type(&self) -> u8 { self.packet_type }
+    /// The link layer address of the sender of the packet.
+    pub fn get_src_address(&self) -> String { self.address.clone() }
+}
+
+/// Decodes the link layer header selected by the capture linktype, returning the ether type of the carried packet and the link layer payload.
+pub fn decode_link_layer(linktype: Linktype, data: Vec<u8>) -> (Result<EtherType, DecodeError>, Vec<u8>) {
+    let (result, payload) = decode_link_layer_slice(linktype, &data);
+    match result {
+        Ok(ether_type) => {
+            let payload = payload.to_vec();
+            (Ok(ether_type), payload)
+        },
+        Err(e) => (Err(e), data)
+    }
+}
+
+/// The zero-copy counterpart of decode_link_layer, the payload is a sub-slice of the data.
+pub fn decode_link_layer_slice(linktype: Linktype, data: &[u8]) -> (Result<EtherType, DecodeError>, &[u8]) {
+    match linktype {
+        Linktype::ETHERNET => {
+            let (header, payload) = EthernetHeader::decode_slice(data);
+            (header.map(|h| h.get_ether_type()), payload)
+        },
+        Linktype::NULL => {
+            let (header, payload) = NullHeader::decode_slice(data);
+            (header.map(|h| h.get_ether_type()), payload)
+        },
+        Linktype::LINUX_SLL => {
+            let (header, payload) = SllHeader::decode_slice(data);
+            (header.map(|h| h.get_ether_type()), payload)
+        },
+        Linktype::LINUX_SLL2 => {
+            let (header, payload) = Sll2Header::decode_slice(data);
+            (header.map(|h| h.get_ether_type()), payload)
+        },
+        Linktype(val) => (Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: format!("Cannot decode the link layer, unsupported linktype {}", val)}), data)
+    }
+}
+
+/// The operation of an ARP packet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArpOperation {
+    Request,
+    Reply,
+    Other(u16)
+}
+
+impl From<u16> for ArpOperation {
+    fn from(v: u16) -> Self {
+        match v {
+            1 => ArpOperation::Request,
+            2 => ArpOperation::Reply,
+            _ => ArpOperation::Other(v)
+        }
+    }
+}
+
+/// describes an ARP Header. Hardware and protocol addresses are kept as strings, formatted as MAC and IPv4 addresses
+/// when their length allows it.
+#[derive(Debug, Clone)]
+pub struct ArpHeader {
+    hw_type: u16,
+    proto_type: u16,
+    operation: ArpOperation,
+    sender_hw: String,
+    sender_ip: String,
+    target_hw: String,
+    target_ip: String,
+}
+
+impl SliceHeader for ArpHeader {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
+        let len = data.len();
+        if len < 8 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode an arp packet because is not long enough.".to_string()}), data) }
+        let hw_len = data[4] as usize;
+        let proto_len = data[5] as usize;
+        let header_len = 8 + 2 * (hw_len + proto_len);
+        if len < header_len { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode an arp packet because is not long enough.".to_string()}), data) }
+
+        let proto_address_to_string = |address: &[u8]| {
+            if proto_len == 4 { utils::ipv4_address_to_string(address) } else { utils::mac_address_to_string(address) }
+        };
+        let sender_hw_start = 8;
+        let sender_ip_start = sender_hw_start + hw_len;
+        let target_hw_start = sender_ip_start + proto_len;
+        let target_ip_start = target_hw_start + hw_len;
+        (
+            Ok(ArpHeader{
+                hw_type: u16::from_be_bytes([data[0], data[1]]),
+                proto_type: u16::from_be_bytes([data[2], data[3]]),
+                operation: ArpOperation::from(u16::from_be_bytes([data[6], data[7]])),
+                sender_hw: utils::mac_address_to_string(&data[sender_hw_start..sender_ip_start]),
+                sender_ip: proto_address_to_string(&data[sender_ip_start..target_hw_start]),
+                target_hw: utils::mac_address_to_string(&data[target_hw_start..target_ip_start]),
+                target_ip: proto_address_to_string(&data[target_ip_start..header_len]),
+            }),
+            &data[header_len..len]
+        )
+    }
+}
+
+impl Header for ArpHeader {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
+impl ArpHeader {
+    /// The type of the link, 1 for Ethernet.
+    pub fn get_hardware_type(&self) -> u16 { self.hw_type }
+    /// The ether type of the protocol addresses, 0x0800 for IPv4.
+    pub fn get_protocol_type(&self) -> u16 { self.proto_type }
+    pub fn get_operation(&self) -> ArpOperation { self.operation }
+    pub fn get_sender_mac(&self) -> String { self.sender_hw.clone() }
+    pub fn get_sender_ip(&self) -> String { self.sender_ip.clone() }
+    pub fn get_target_mac(&self) -> String { self.target_hw.clone() }
+    pub fn get_target_ip(&self) -> String { self.target_ip.clone() }
+
+    /// A gratuitous ARP announces the sender own address (sender and target protocol addresses are the same), it is used
+    /// for IP takeover and failover but also by spoofing attacks.
+    pub fn is_gratuitous(&self) -> bool { self.sender_ip == self.target_ip }
 }
 
 /// level 4 protocol
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Protocol {
     TCP,
     UDP,
+    ICMP,
+    ICMPv6,
+    SCTP,
+    GRE,
+    IPIP,
     Unknown
 }
 
@@ -166,67 +646,255 @@ impl ToString for Protocol {
         return match &self {
             Protocol::TCP => "TCP".to_string(),
             Protocol::UDP => "UDP".to_string(),
+            Protocol::ICMP => "ICMP".to_string(),
+            Protocol::ICMPv6 => "ICMPv6".to_string(),
+            Protocol::SCTP => "SCTP".to_string(),
+            Protocol::GRE => "GRE".to_string(),
+            Protocol::IPIP => "IPIP".to_string(),
             Protocol::Unknown => "Unknown".to_string()
         }
     }
 }
 
+/// The fragmentation flags of an IPv4 header (the 3 high bits of bytes 6-7).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ipv4Flags(u8);
+
+impl Ipv4Flags {
+    /// The reserved bit, that must be zero (the "evil bit" of RFC 3514).
+    pub fn is_reserved(&self) -> bool { self.0 & 0x04 != 0 }
+    pub fn is_dont_fragment(&self) -> bool { self.0 & 0x02 != 0 }
+    pub fn is_more_fragments(&self) -> bool { self.0 & 0x01 != 0 }
+}
+
+impl From<u8> for Ipv4Flags {
+    fn from(v: u8) -> Self { Ipv4Flags(v & 0x07) }
+}
+
+impl From<Ipv4Flags> for u8 {
+    fn from(flags: Ipv4Flags) -> Self { flags.0 }
+}
+
+/// The Explicit Congestion Notification codepoint of an IP packet (RFC 3168): the last 2 bits of the type of service, or
+/// of the traffic class. The routers mark the packets of ECN capable transports as Ce instead of dropping them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Ecn {
+    /// The transport is not ECN capable.
+    NotEct,
+    Ect1,
+    Ect0,
+    /// Congestion Experienced.
+    Ce,
+}
+
+impl From<u8> for Ecn {
+    fn from(v: u8) -> Self {
+        match v & 0x03 {
+            0 => Ecn::NotEct,
+            1 => Ecn::Ect1,
+            2 => Ecn::Ect0,
+            _ => Ecn::Ce
+        }
+    }
+}
+
 /// describes an Ipv4 Header
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Ipv4Header {
     dest: String,
     src: String,
+    dest_ip: Ipv4Addr,
+    src_ip: Ipv4Addr,
     protocol: Protocol,
+    dscp: u8,
+    ecn: Ecn,
+    identification: u16,
+    flags: Ipv4Flags,
+    fragment_offset: u16,
+    header_length: usize,
+    total_length: u16,
+    ttl: u8,
+    checksum: u16,
+    checksum_valid: bool,
 }
 
-impl Header for Ipv4Header {
-    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+impl SliceHeader for Ipv4Header {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
         let len = data.len();
         if len < 20 {
-            return (Err(DecodeError{msg: "Cannot decode ipv4 packet because is not long enough.".to_string()}), data)
+            return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode ipv4 packet because is not long enough.".to_string()}), data)
         }
         let header_len = (data[0] & 0x0f ) as usize * 4;
+        if header_len < 20 || header_len > len {
+            return (Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: format!("Invalid ipv4 header length {}", header_len)}), data)
+        }
 
         let protocol = match &data[9] {
+            0x01 => Protocol::ICMP,
             0x06 => Protocol::TCP,
             0x11 => Protocol::UDP,
+            0x84 => Protocol::SCTP,
+            0x2f => Protocol::GRE,
+            0x04 => Protocol::IPIP,
             value => return (
-                Err(DecodeError{ msg: format!("Unable to identify level 4 protocol. Received 0x{:x}", value) }),
+                Err(DecodeError{kind: DecodeErrorKind::UnknownProtocol(*value), msg: format!("Unable to identify level 4 protocol. Received 0x{:x}", value) }),
                 data
             )
         };
 
+        // the first 6 bits of the type of service byte, the remaining ones are used by ECN
+        let dscp = data[1] >> 2;
+        let ecn = Ecn::from(data[1]);
+        let identification = ((data[4] as u16) << 8) | data[5] as u16;
+        let flags = Ipv4Flags::from(data[6] >> 5);
+        // the offset is expressed in 8 bytes units
+        let fragment_offset = ((((data[6] & 0x1f) as u16) << 8) | data[7] as u16) * 8;
         let src_address = utils::ipv4_address_to_string(&data[12..16]);
         let dest_address = utils::ipv4_address_to_string(&data[16..20]);
+        let src_ip = Ipv4Addr::new(data[12], data[13], data[14], data[15]);
+        let dest_ip = Ipv4Addr::new(data[16], data[17], data[18], data[19]);
         (
-            Ok(Ipv4Header{src: src_address, dest: dest_address, protocol}),
-            Vec::from(&data[header_len..len])
+            Ok(Ipv4Header{src: src_address, dest: dest_address, src_ip, dest_ip, protocol, dscp, ecn, identification, flags, fragment_offset,
+                header_length: header_len, total_length: u16::from_be_bytes([data[2], data[3]]), ttl: data[8],
+                checksum: u16::from_be_bytes([data[10], data[11]]), checksum_valid: Ipv4Header::verify_checksum(data)}),
+            &data[header_len..len]
         )
     }
 }
 
+impl Header for Ipv4Header {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
 impl Ipv4Header {
     pub fn get_protocol(&self) -> Protocol {
         self.protocol.clone()
     }
     pub fn get_src_address(&self) -> String { return self.src.clone(); }
     pub fn get_dest_address(&self) -> String { return self.dest.clone(); }
+    /// The source address, for the network math that the string of get_src_address would need to parse.
+    pub fn get_src_ip(&self) -> Ipv4Addr { self.src_ip }
+    pub fn get_dest_ip(&self) -> Ipv4Addr { self.dest_ip }
+    /// The Differentiated Services Code Point, the QoS class the packet has been marked with.
+    pub fn get_dscp(&self) -> u8 { self.dscp }
+    pub fn get_ecn(&self) -> Ecn { self.ecn }
+    /// The identification shared by all the fragments of a datagram.
+    pub fn get_identification(&self) -> u16 { self.identification }
+    pub fn get_flags(&self) -> Ipv4Flags { self.flags }
+    /// The offset of the fragment inside the original payload, in bytes.
+    pub fn get_fragment_offset(&self) -> u16 { self.fragment_offset }
+    /// Returns true if other fragments of the datagram follow this one.
+    pub fn is_more_fragments(&self) -> bool { self.flags.is_more_fragments() }
+    /// Returns true if the packet is a fragment of a bigger datagram.
+    pub fn is_fragment(&self) -> bool { self.flags.is_more_fragments() || self.fragment_offset != 0 }
+    /// The length of the header, options included.
+    pub fn get_header_length(&self) -> usize { self.header_length }
+    /// The length of the packet declared by the header, header included.
+    pub fn get_total_length(&self) -> u16 { self.total_length }
+    /// Returns true if the capture holds less than the declared length, e.g. when it has been clipped by the snaplen.
+    /// captured_len is the length captured from the beginning of the IPv4 header.
+    pub fn is_truncated(&self, captured_len: usize) -> bool { (self.total_length as usize) > captured_len }
+    /// The Time To Live, the number of hops the packet can still cross. Its initial value depends on the operating system
+    /// of the sender.
+    pub fn get_ttl(&self) -> u8 { self.ttl }
+    pub fn get_checksum(&self) -> u16 { self.checksum }
+    /// Returns true if the checksum matches the header. The packets sent by the capturing host are often captured before
+    /// the network card computes their checksum (checksum offloading), so a wrong checksum on them is not a corruption.
+    pub fn is_checksum_valid(&self) -> bool { self.checksum_valid }
+
+    /// Verifies the checksum of the IPv4 header at the beginning of data, options included.
+    pub fn verify_checksum(data: &[u8]) -> bool {
+        if data.len() < 20 { return false }
+        let header_len = (data[0] & 0x0f) as usize * 4;
+        header_len >= 20 && header_len <= data.len() && internet_checksum(&data[..header_len]) == 0
+    }
+
+    /// Decodes the header as decode does, but it rejects a header whose checksum is wrong.
+    pub fn decode_strict(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        // the malformed headers are left to decode, that reports why they are
+        let header_len = data.first().map(|b| (b & 0x0f) as usize * 4).unwrap_or(0);
+        if header_len >= 20 && header_len <= data.len() && !Ipv4Header::verify_checksum(&data) {
+            let checksum = u16::from_be_bytes([data[10], data[11]]);
+            return (Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: format!("Wrong ipv4 header checksum 0x{:04x}", checksum)}), data)
+        }
+        Ipv4Header::decode(data)
+    }
 }
 
-/// describes an Ipv6 Header
-#[derive(Debug, Clone)]
+/// The IPv6 extension headers skipped to reach the transport protocol: Hop-by-Hop Options, Routing, Fragment and
+/// Destination Options.
+const IPV6_EXTENSION_HEADERS: [u8; 4] = [0, ROUTING_NEXT_HEADER, 44, 60];
+
+/// The extension headers found after an IPv6 fixed header.
+struct Ipv6Extensions {
+    /// The protocol number following the extension headers.
+    next_header: u8,
+    /// The type of every extension header, with its offset from the end of the fixed header.
+    headers: Vec<(u8, usize)>,
+    /// The total length of the extension headers.
+    len: usize,
+}
+
+/// Follows the chain of the IPv6 extension headers at the beginning of data, announced by next_header. The walk stops at a
+/// fragment that is not the first one, since the transport header is only in the first, leaving the Fragment type as next header.
+fn walk_ipv6_extension_headers(mut next_header: u8, data: &[u8]) -> Result<Ipv6Extensions, DecodeError> {
+    let mut headers = Vec::new();
+    let mut offset = 0;
+    while IPV6_EXTENSION_HEADERS.contains(&next_header) {
+        ensure_len(&data[offset..], 8, "an ipv6 extension header")?;
+        // the Fragment header has a fixed length, the others give it in 8 bytes units, not including the first 8 bytes
+        let header_len = if next_header == 44 { 8 } else { (data[offset + 1] as usize + 1) * 8 };
+        ensure_len(&data[offset..], header_len, "an ipv6 extension header")?;
+        headers.push((next_header, offset));
+        if next_header == 44 && u16::from_be_bytes([data[offset + 2], data[offset + 3]]) >> 3 != 0 {
+            return Ok(Ipv6Extensions { next_header, headers, len: offset + header_len })
+        }
+        next_header = data[offset];
+        offset += header_len;
+    }
+    Ok(Ipv6Extensions { next_header, headers, len: offset })
+}
+
+/// describes an Ipv6 Header, with the extension headers following it. The protocol is the one after the extension headers.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Ipv6Header {
     dest: String,
     src: String,
+    dest_ip: Ipv6Addr,
+    src_ip: Ipv6Addr,
     protocol: Protocol,
+    dscp: u8,
+    ecn: Ecn,
+    payload_length: u16,
+    hop_limit: u8,
+    next_header: u8,
+    extension_headers: Vec<u8>,
+    routing_header: Option<Ipv6RoutingHeader>,
 }
 
-impl Header for Ipv6Header {
-    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+impl SliceHeader for Ipv6Header {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
         let len = data.len();
-        let protocol = match &data[9] {
+        if len < 40 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode an ipv6 packet because is not long enough.".to_string()}), data) }
+        // the Next Header field follows the version, the traffic class, the flow label and the payload length
+        let extensions = match walk_ipv6_extension_headers(data[6], &data[40..]) {
+            Ok(extensions) => extensions,
+            Err(e) => return (Err(e), data)
+        };
+        let routing_header = extensions.headers.iter()
+            .find(|(header, _)| *header == ROUTING_NEXT_HEADER)
+            .and_then(|(_, offset)| Ipv6RoutingHeader::decode(Vec::from(&data[40 + offset..])).0.ok());
+        let extension_headers = extensions.headers.iter().map(|(header, _)| *header).collect();
+        let protocol = match &extensions.next_header {
             0x06 => Protocol::TCP,
             0x11 => Protocol::UDP,
+            0x3a => Protocol::ICMPv6,
+            0x84 => Protocol::SCTP,
+            0x2f => Protocol::GRE,
+            0x04 => Protocol::IPIP,
             _ => Protocol::Unknown
             /*return (
                 Err(DecodeError{ msg: format!("Unable to identify level 4 protocol. Received 0x{:x}", value) }),
@@ -234,75 +902,310 @@ impl Header for Ipv6Header {
             )*/
         };
 
-        let src_address = utils::ipv6_address_to_string(&data[8..20]);
-        let dest_address = utils::ipv6_address_to_string(&data[20..36]);
+        // the traffic class spans the two first bytes, after the version, and its first 6 bits are the DSCP
+        let dscp = (((data[0] & 0x0f) << 4) | (data[1] >> 4)) >> 2;
+        let ecn = Ecn::from(data[1] >> 4);
+        let payload_length = ((data[4] as u16) << 8) | data[5] as u16;
+        let src_ip: [u8; 16] = data[8..24].try_into().unwrap();
+        let dest_ip: [u8; 16] = data[24..40].try_into().unwrap();
+        let src_address = utils::ipv6_address_to_string(&src_ip);
+        let dest_address = utils::ipv6_address_to_string(&dest_ip);
         (
-            Ok(Ipv6Header{src: src_address, dest: dest_address, protocol}),
-            Vec::from(&data[40..len])
+            Ok(Ipv6Header{src: src_address, dest: dest_address, src_ip: Ipv6Addr::from(src_ip), dest_ip: Ipv6Addr::from(dest_ip), protocol, dscp, ecn, payload_length, hop_limit: data[7],
+                next_header: extensions.next_header, extension_headers, routing_header}),
+            &data[40 + extensions.len..len]
         )
     }
 }
 
+impl Header for Ipv6Header {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
 impl Ipv6Header {
     pub fn get_protocol(&self) -> Protocol {
         self.protocol.clone()
     }
     pub fn get_src_address(&self) -> String { return self.src.clone(); }
     pub fn get_dest_address(&self) -> String { return self.dest.clone(); }
+    pub fn get_src_ip(&self) -> Ipv6Addr { self.src_ip }
+    pub fn get_dest_ip(&self) -> Ipv6Addr { self.dest_ip }
+    /// The Differentiated Services Code Point, taken from the traffic class.
+    pub fn get_dscp(&self) -> u8 { self.dscp }
+    pub fn get_ecn(&self) -> Ecn { self.ecn }
+    /// The length declared by the header of everything following the fixed header, extension headers included.
+    pub fn get_payload_length(&self) -> u16 { self.payload_length }
+    /// Returns true if the capture holds less than the declared length, e.g. when it has been clipped by the snaplen.
+    /// captured_len is the length captured from the beginning of the IPv6 header.
+    pub fn is_truncated(&self, captured_len: usize) -> bool { 40 + self.payload_length as usize > captured_len }
+    /// The Hop Limit, the IPv6 counterpart of the IPv4 TTL.
+    pub fn get_hop_limit(&self) -> u8 { self.hop_limit }
+    /// The protocol number following the extension headers, also when the protocol is Unknown.
+    pub fn get_next_header(&self) -> u8 { self.next_header }
+    /// The types of the extension headers between the fixed header and the payload, in order.
+    pub fn get_extension_headers(&self) -> Vec<u8> { self.extension_headers.clone() }
+    pub fn get_routing_header(&self) -> Option<Ipv6RoutingHeader> { self.routing_header.clone() }
 }
 
-/// describes an UDP Header
+/// Formats the header on a single line, e.g. "192.168.1.21 -> 149.154.167.92, TCP".
+impl Display for Ipv4Header {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}, {}", self.get_src_address(), self.get_dest_address(), self.get_protocol().to_string())
+    }
+}
+
+/// Formats the header on a single line, e.g. "fe80::52eb:71ff:fe23:8e67 -> ff02::16, UDP".
+impl Display for Ipv6Header {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}, {}", self.get_src_address(), self.get_dest_address(), self.get_protocol().to_string())
+    }
+}
+
+/// describes an ICMP Header: the type and code of the message, and its checksum. The rest of the message is returned as payload.
+#[derive(Debug, Clone)]
+pub struct IcmpHeader {
+    icmp_type: u8,
+    code: u8,
+    checksum: u16,
+}
+
+impl SliceHeader for IcmpHeader {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
+        if data.len() < 4 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode an icmp packet because is not long enough.".to_string()}), data) }
+        let checksum = ((data[2] as u16) << 8) | data[3] as u16;
+        (
+            Ok(IcmpHeader{icmp_type: data[0], code: data[1], checksum}),
+            &data[4..]
+        )
+    }
+}
+
+impl Header for IcmpHeader {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
+impl IcmpHeader {
+    /// The type of the message, e.g. 8 for an echo request, 0 for an echo reply and 3 for a destination unreachable.
+    pub fn get_type(&self) -> u8 { self.icmp_type }
+    pub fn get_code(&self) -> u8 { self.code }
+    pub fn get_checksum(&self) -> u16 { self.checksum }
+}
+
+/// describes an ICMPv6 Header: the type and code of the message, and its checksum. The rest of the message is returned as payload.
 #[derive(Debug, Clone)]
+pub struct Icmpv6Header {
+    icmp_type: u8,
+    code: u8,
+    checksum: u16,
+}
+
+impl SliceHeader for Icmpv6Header {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
+        if data.len() < 4 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode an icmpv6 packet because is not long enough.".to_string()}), data) }
+        let checksum = ((data[2] as u16) << 8) | data[3] as u16;
+        (
+            Ok(Icmpv6Header{icmp_type: data[0], code: data[1], checksum}),
+            &data[4..]
+        )
+    }
+}
+
+impl Header for Icmpv6Header {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
+impl Icmpv6Header {
+    pub fn get_type(&self) -> u8 { self.icmp_type }
+    pub fn get_code(&self) -> u8 { self.code }
+    pub fn get_checksum(&self) -> u16 { self.checksum }
+}
+
+/// describes an UDP Header
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct UDPHeader {
     dest: u16,
     src: u16,
+    length: u16,
 }
 
 impl UDPHeader {
     pub fn get_src_port(&self) -> u16 { return self.src }
     pub fn get_dest_port(&self) -> u16 { return self.dest }
+    /// The length of the datagram written in the header, the 8 bytes of the header included.
+    pub fn get_length(&self) -> u16 { self.length }
 }
 
-impl Header for UDPHeader {
-    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+impl SliceHeader for UDPHeader {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
+        if data.len() < 8 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode an udp datagram because is not long enough.".to_string()}), data) }
         let src = ((data[0] as u16) << 8) | data[1] as u16;
         let dest = ((data[2] as u16) << 8) | data[3] as u16;
+        let length = ((data[4] as u16) << 8) | data[5] as u16;
         (
-            Ok(UDPHeader{dest, src}),
-            Vec::from(&data[8..])
+            Ok(UDPHeader{dest, src, length}),
+            &data[8..]
         )
     }
 }
 
-/// describes a TCP Header
-#[derive(Debug, Clone)]
-pub struct TCPHeader {
+impl Header for UDPHeader {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
+/// describes the common header of an SCTP packet, the one before the chunks: the ports and the verification tag. The
+/// chunks are returned as payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SctpHeader {
     dest: u16,
     src: u16,
+    verification_tag: u32,
+    checksum: u32,
 }
 
-impl Header for TCPHeader {
-    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
-        let src = ((data[0] as u16) << 8) | data[1] as u16;
-        let dest = ((data[2] as u16) << 8) | data[3] as u16;
+impl SctpHeader {
+    pub fn get_src_port(&self) -> u16 { self.src }
+    pub fn get_dest_port(&self) -> u16 { self.dest }
+    /// The tag chosen by the receiver of the packet when the association has been set up, 0 in the INIT chunks.
+    pub fn get_verification_tag(&self) -> u32 { self.verification_tag }
+    /// The CRC32c checksum of the packet.
+    pub fn get_checksum(&self) -> u32 { self.checksum }
+}
+
+impl SliceHeader for SctpHeader {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
+        if data.len() < 12 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode an sctp packet because is not long enough.".to_string()}), data) }
         (
-            Ok(TCPHeader{dest, src}),
-            Vec::from(&data[20..])
+            Ok(SctpHeader{
+                src: u16::from_be_bytes([data[0], data[1]]),
+                dest: u16::from_be_bytes([data[2], data[3]]),
+                verification_tag: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+                checksum: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
+            }),
+            &data[12..]
         )
     }
 }
 
-impl TCPHeader {
-    pub fn get_src_port(&self) -> u16 { return self.src }
-    pub fn get_dest_port(&self) -> u16 { return self.dest }
+impl Header for SctpHeader {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct TimeVal {
-    pub(crate) sec: u32,
+/// The control flags carried by a TCP segment (byte 13 of the header).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TcpFlags(u8);
+
+impl TcpFlags {
+    pub fn is_fin(&self) -> bool { self.0 & 0x01 != 0 }
+    pub fn is_syn(&self) -> bool { self.0 & 0x02 != 0 }
+    pub fn is_rst(&self) -> bool { self.0 & 0x04 != 0 }
+    /// Push: the receiver should hand the data to the application without waiting for more.
+    pub fn is_psh(&self) -> bool { self.0 & 0x08 != 0 }
+    pub fn is_ack(&self) -> bool { self.0 & 0x10 != 0 }
+    /// Urgent: the urgent pointer of the segment is significant.
+    pub fn is_urg(&self) -> bool { self.0 & 0x20 != 0 }
+    /// ECN-Echo: on a SYN it offers ECN, otherwise it tells the peer that its packets have been marked Ce.
+    pub fn is_ece(&self) -> bool { self.0 & 0x40 != 0 }
+    /// Congestion Window Reduced: the sender has reacted to an ECN-Echo.
+    pub fn is_cwr(&self) -> bool { self.0 & 0x80 != 0 }
+}
+
+impl From<u8> for TcpFlags {
+    fn from(v: u8) -> Self { TcpFlags(v) }
+}
+
+impl From<TcpFlags> for u8 {
+    fn from(flags: TcpFlags) -> Self { flags.0 }
+}
+
+/// describes a TCP Header
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TCPHeader {
+    dest: u16,
+    src: u16,
+    seq_number: u32,
+    ack_number: u32,
+    flags: TcpFlags,
+    window_size: u16,
+}
+
+impl SliceHeader for TCPHeader {
+    fn decode_slice(data: &[u8]) -> (Result<Self, DecodeError>, &[u8]) {
+        if data.len() < 20 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode a tcp segment because is not long enough.".to_string()}), data) }
+        // the data offset gives the header length, options included, in 4 bytes units
+        let header_len = (data[12] >> 4) as usize * 4;
+        if header_len < 20 || header_len > data.len() {
+            return (Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: format!("Invalid tcp header length {}", header_len)}), data)
+        }
+        let src = ((data[0] as u16) << 8) | data[1] as u16;
+        let dest = ((data[2] as u16) << 8) | data[3] as u16;
+        let seq_number = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        let ack_number = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+        let flags = TcpFlags::from(data[13]);
+        let window_size = ((data[14] as u16) << 8) | data[15] as u16;
+        (
+            Ok(TCPHeader{dest, src, seq_number, ack_number, flags, window_size}),
+            &data[header_len..]
+        )
+    }
+}
+
+impl Header for TCPHeader {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        decode_owned(data)
+    }
+}
+
+impl TCPHeader {
+    pub fn get_src_port(&self) -> u16 { return self.src }
+    pub fn get_dest_port(&self) -> u16 { return self.dest }
+    pub fn get_seq_number(&self) -> u32 { self.seq_number }
+    pub fn get_ack_number(&self) -> u32 { self.ack_number }
+    pub fn get_flags(&self) -> TcpFlags { self.flags }
+    /// The receive window advertised by the sender of the segment, without the window scaling applied.
+    pub fn get_window_size(&self) -> u16 { self.window_size }
+}
+
+/// A timestamp with microsecond precision, as the ones given by pcap. The timestamps are compared by the microseconds
+/// since the epoch, as u_sec can exceed a second: { sec: 4, u_sec: 1_100_000 } is later than { sec: 5, u_sec: 0 }.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeVal {
+    pub(crate) sec: u32,
     pub(crate) u_sec: u32,
 }
 
+impl PartialEq for TimeVal {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_micros() == other.as_micros()
+    }
+}
+
+impl Eq for TimeVal {}
+
+impl PartialOrd for TimeVal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimeVal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_micros().cmp(&other.as_micros())
+    }
+}
+
 impl ToString for TimeVal {
     fn to_string(&self) -> String {
         format!("{} {}", self.sec, self.u_sec)
@@ -311,7 +1214,7 @@ impl ToString for TimeVal {
 
 impl Into<u64> for TimeVal {
     fn into(self) -> u64 {
-        (self.sec as u64) * 1000000 + (self.u_sec as u64)
+        self.as_micros()
     }
 }
 
@@ -321,38 +1224,677 @@ impl From<u64> for TimeVal {
     }
 }
 
-/*impl TimeVal {
-    pub fn display_as_date() -> String {
+/// A timestamp with nanosecond precision, as the ones taken by the hardware of some NICs and switches or stored by
+/// pcapng files. It converts to and from TimeVal, that keeps microseconds only.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeValNanos {
+    pub(crate) sec: u32,
+    pub(crate) n_sec: u32,
+}
+
+impl TimeValNanos {
+    pub fn new(sec: u32, n_sec: u32) -> Self {
+        TimeValNanos { sec, n_sec }
+    }
+
+    /// Creates the timestamp from the nanoseconds since the epoch.
+    pub fn from_nanos(nanos: u64) -> Self {
+        TimeValNanos { sec: (nanos / 1_000_000_000) as u32, n_sec: (nanos % 1_000_000_000) as u32 }
+    }
 
+    /// Returns the nanoseconds since the epoch.
+    pub fn as_nanos(&self) -> u64 {
+        (self.sec as u64) * 1_000_000_000 + (self.n_sec as u64)
     }
-}*/
+
+    pub fn get_sec(&self) -> u32 { self.sec }
+    pub fn get_n_sec(&self) -> u32 { self.n_sec }
+}
+
+/// The conversion truncates the nanoseconds to microseconds.
+impl From<TimeValNanos> for TimeVal {
+    fn from(ts: TimeValNanos) -> Self {
+        TimeVal { sec: ts.sec, u_sec: ts.n_sec / 1000 }
+    }
+}
+
+/// The microseconds exceeding a second, as the ones of a malformed timestamp, are carried into the seconds.
+impl From<TimeVal> for TimeValNanos {
+    fn from(ts: TimeVal) -> Self {
+        TimeValNanos::from_nanos(ts.as_micros() * 1000)
+    }
+}
+
+/// The time elapsed between two timestamps, zero if the second one is later (see duration_since).
+impl Sub for TimeVal {
+    type Output = Duration;
+
+    fn sub(self, earlier: TimeVal) -> Duration {
+        self.duration_since(&earlier)
+    }
+}
+
+impl TimeVal {
+    /// Returns the microseconds since the epoch.
+    fn as_micros(&self) -> u64 {
+        (self.sec as u64) * 1000000 + (self.u_sec as u64)
+    }
+
+    /// Returns the time elapsed from an earlier timestamp, e.g. the gap between two packets. It is zero when earlier is
+    /// actually later, as the capture timestamps can go back when the clock of the host is adjusted.
+    pub fn duration_since(&self, earlier: &TimeVal) -> Duration {
+        let micros: u64 = self.clone().into();
+        let earlier_micros: u64 = earlier.clone().into();
+        Duration::from_micros(micros.saturating_sub(earlier_micros))
+    }
+
+    /// Formats the timestamp as a UTC date, with the microseconds, e.g. "2024-01-15 13:45:02.123456".
+    pub fn display_as_date(&self) -> String {
+        let micros: u64 = self.clone().into();
+        Utc.timestamp_opt((micros / 1_000_000) as i64, (micros % 1_000_000) as u32 * 1000).unwrap()
+            .format("%Y-%m-%d %H:%M:%S%.6f").to_string()
+    }
+}
+
+/// describes an endpoint of a packet: its address and its port, 0 for the protocols without ports.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Endpoint {
+    address: String,
+    port: u16,
+}
+
+impl Endpoint {
+    pub fn new(address: String, port: u16) -> Self { Endpoint { address, port } }
+    pub fn get_address(&self) -> String { self.address.clone() }
+    pub fn get_port(&self) -> u16 { self.port }
+}
 
 /// A common way to describe useful information extracted by a packet, wrapped in a single struct
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PacketInfo {
     address: String,
     port: u16,
     protocol: Protocol,
     byte_transmitted: usize,
-    ts: TimeVal
+    ts: TimeVal,
+    tcp_header: Option<TCPHeader>,
+    direction: Option<Direction>,
+    dscp: Option<u8>,
+    ecn: Option<Ecn>,
+    ttl: Option<u8>,
+    hardware_ts: Option<TimeValNanos>,
+    src: Option<Endpoint>,
+    dest: Option<Endpoint>,
+}
+
+/// Formats the packet on a single line, e.g. "[2024-01-15 13:45:02.123456] TCP 149.154.167.92:443, 517 bytes". The IPv6
+/// addresses are enclosed in brackets, to separate them from the port.
+impl Display for PacketInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let address = if self.address.contains(':') { format!("[{}]", self.address) } else { self.address.clone() };
+        write!(f, "[{}] {} {}:{}, {} bytes", self.ts.display_as_date(), self.protocol.to_string(), address, self.port, self.byte_transmitted)
+    }
 }
 
 impl PacketInfo {
     pub fn new(address: String, port: u16, protocol: Protocol, byte_transmitted: usize, ts: TimeVal) -> Self {
-        PacketInfo { address, port, protocol, byte_transmitted, ts}
+        PacketInfo { address, port, protocol, byte_transmitted, ts, tcp_header: None, direction: None, dscp: None, ecn: None, ttl: None, hardware_ts: None,
+            src: None, dest: None }
     }
 
+    /// Sets whether the packet has been sent or received by the capturing host, when it is known.
+    pub fn set_direction(&mut self, direction: Direction) { self.direction = Some(direction) }
+    pub fn get_direction(&self) -> Option<Direction> { self.direction.clone() }
+
+    /// Sets both endpoints of the packet, while address and port describe only the remote one.
+    pub fn set_endpoints(&mut self, src: Endpoint, dest: Endpoint) {
+        self.src = Some(src);
+        self.dest = Some(dest);
+    }
+    pub fn get_src(&self) -> Option<Endpoint> { self.src.clone() }
+    pub fn get_dest(&self) -> Option<Endpoint> { self.dest.clone() }
+    /// Returns the endpoint on the capturing host, when both endpoints and the direction are known.
+    pub fn get_local(&self) -> Option<Endpoint> {
+        match self.direction.as_ref()? {
+            Direction::Received => self.dest.clone(),
+            Direction::Transmitted => self.src.clone()
+        }
+    }
+
+    /// Sets the DSCP marking of the IP header of the packet.
+    pub fn set_dscp(&mut self, dscp: u8) { self.dscp = Some(dscp) }
+    pub fn get_dscp(&self) -> Option<u8> { self.dscp }
+
+    /// Sets the ECN codepoint of the IP header of the packet.
+    pub fn set_ecn(&mut self, ecn: Ecn) { self.ecn = Some(ecn) }
+    pub fn get_ecn(&self) -> Option<Ecn> { self.ecn }
+
+    /// Sets the TTL of the IPv4 header, or the hop limit of the IPv6 one, of the packet.
+    pub fn set_ttl(&mut self, ttl: u8) { self.ttl = Some(ttl) }
+    pub fn get_ttl(&self) -> Option<u8> { self.ttl }
+
+    /// Sets the timestamp taken by the hardware, e.g. read from a switch timestamp trailer (see the trailer submodule).
+    pub fn set_hardware_time_stamp(&mut self, ts: TimeValNanos) { self.hardware_ts = Some(ts) }
+    /// Returns the timestamp taken by the hardware, if any: it is more accurate than the capture time returned by get_time_stamp.
+    pub fn get_hardware_time_stamp(&self) -> Option<TimeValNanos> { self.hardware_ts.clone() }
+    /// Returns the most precise timestamp of the packet: the hardware one if any, the capture time otherwise.
+    pub fn get_precise_time_stamp(&self) -> TimeValNanos {
+        self.hardware_ts.clone().unwrap_or_else(|| TimeValNanos::from(self.ts.clone()))
+    }
+
+    /// Attaches the TCP header the information has been extracted from, so that flags can be inspected later on.
+    pub fn set_tcp_header(&mut self, header: TCPHeader) { self.tcp_header = Some(header) }
+    pub fn get_tcp_header(&self) -> Option<TCPHeader> { self.tcp_header.clone() }
+
     pub fn get_address(&self) -> String { return self.address.clone() }
     pub fn get_port(&self) -> u16 { return self.port }
     pub fn get_protocol(&self) -> Protocol { return self.protocol.clone() }
     pub fn get_byte_transmitted(&self) -> usize { return self.byte_transmitted }
     pub fn get_time_stamp(&self) -> TimeVal { return self.ts.clone() }
+
+    /// Returns the key of the connection of the packet: its remote address and port, and its protocol.
+    pub fn get_connection_key(&self) -> ConnectionKey { ConnectionKey::from(self) }
+
+    /// Returns true if the two packets belong to the same conversation. The information always describes the remote
+    /// endpoint, whatever the direction of the packet, so the packets sent and received in a conversation share it; when
+    /// both endpoints are known they must match too, in either order.
+    pub fn same_flow(&self, other: &PacketInfo) -> bool {
+        let same_endpoints = match (&self.src, &self.dest, &other.src, &other.dest) {
+            (Some(src), Some(dest), Some(other_src), Some(other_dest)) =>
+                (src == other_src && dest == other_dest) || (src == other_dest && dest == other_src),
+            _ => true
+        };
+        self.protocol == other.protocol && self.address == other.address && self.port == other.port && same_endpoints
+    }
+
+    /// Builds a minimal valid frame carrying the information of the packet, see FrameBuilder::from_packet_info.
+    pub fn to_synthetic_frame(&self) -> Result<Vec<u8>, BuildError> {
+        FrameBuilder::from_packet_info(self)?.build()
+    }
+}
+
+/// The network layer header of a ParsedPacket.
+#[derive(Debug, Clone)]
+pub enum NetworkHeader {
+    Ipv4(Ipv4Header),
+    Ipv6(Ipv6Header),
+    Arp(ArpHeader),
+}
+
+/// The transport layer header of a ParsedPacket.
+#[derive(Debug, Clone)]
+pub enum TransportHeader {
+    Tcp(TCPHeader),
+    Udp(UDPHeader),
+    Icmp(IcmpHeader),
+    Icmpv6(Icmpv6Header),
+    Sctp(SctpHeader),
+}
+
+/// All the headers decoded from an Ethernet frame by try_parse, with the payload following the last one.
+#[derive(Debug, Clone)]
+pub struct ParsedPacket {
+    ethernet: EthernetHeader,
+    network: NetworkHeader,
+    transport: Option<TransportHeader>,
+    payload: Vec<u8>,
+    hash: Option<u64>,
+}
+
+/// The headers decoded from an Ethernet frame by try_parse_slice: as ParsedPacket, but the payload borrows the frame.
+#[derive(Debug, Clone)]
+pub struct ParsedSlice<'a> {
+    ethernet: EthernetHeader,
+    network: NetworkHeader,
+    transport: Option<TransportHeader>,
+    payload: &'a [u8],
+}
+
+impl<'a> ParsedSlice<'a> {
+    pub fn get_ethernet_header(&self) -> &EthernetHeader { &self.ethernet }
+    pub fn get_network_header(&self) -> &NetworkHeader { &self.network }
+    pub fn get_transport_header(&self) -> Option<&TransportHeader> { self.transport.as_ref() }
+    /// The bytes following the last header decoded, a slice of the frame.
+    pub fn get_payload(&self) -> &'a [u8] { self.payload }
+}
+
+/// Copies the payload, so that the packet does not borrow the frame anymore.
+impl<'a> From<ParsedSlice<'a>> for ParsedPacket {
+    fn from(packet: ParsedSlice<'a>) -> Self {
+        ParsedPacket { ethernet: packet.ethernet, network: packet.network, transport: packet.transport, payload: packet.payload.to_vec(), hash: None }
+    }
+}
+
+/// Feeds bytes to a 64 bits FNV-1a hash, that does not depend on the platform or on the compiler version.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+impl ParsedPacket {
+    pub fn get_ethernet_header(&self) -> &EthernetHeader { &self.ethernet }
+    pub fn get_network_header(&self) -> &NetworkHeader { &self.network }
+    /// Returns the transport header (the ICMP header for ICMP), None for ARP and for the IPv6 packets carrying other protocols.
+    pub fn get_transport_header(&self) -> Option<&TransportHeader> { self.transport.as_ref() }
+    pub fn get_payload(&self) -> &[u8] { &self.payload }
+
+    /// Returns the hash of the packet, if it has been computed by compute_hash.
+    pub fn get_hash(&self) -> Option<u64> { self.hash }
+
+    /// Computes a hash of the bytes identifying the packet, attaches it to the packet and returns it: the same packet
+    /// captured at different points of the network has the same hash, so that the copies can be deduplicated or correlated.
+    /// It covers the addresses, the IPv4 identification, the ports, the TCP sequence numbers and the payload, but not the
+    /// fields that change hop by hop, as the MAC addresses, the TTL and the checksums.
+    pub fn compute_hash(&mut self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325;
+        match &self.network {
+            NetworkHeader::Ipv4(header) => {
+                hash = fnv1a(hash, header.get_src_address().as_bytes());
+                hash = fnv1a(hash, header.get_dest_address().as_bytes());
+                hash = fnv1a(hash, &header.get_identification().to_be_bytes());
+            },
+            NetworkHeader::Ipv6(header) => {
+                hash = fnv1a(hash, header.get_src_address().as_bytes());
+                hash = fnv1a(hash, header.get_dest_address().as_bytes());
+            },
+            NetworkHeader::Arp(header) => {
+                hash = fnv1a(hash, header.get_sender_mac().as_bytes());
+                hash = fnv1a(hash, header.get_sender_ip().as_bytes());
+                hash = fnv1a(hash, header.get_target_ip().as_bytes());
+            }
+        }
+        match &self.transport {
+            Some(TransportHeader::Tcp(header)) => {
+                hash = fnv1a(hash, &header.get_src_port().to_be_bytes());
+                hash = fnv1a(hash, &header.get_dest_port().to_be_bytes());
+                hash = fnv1a(hash, &header.get_seq_number().to_be_bytes());
+                hash = fnv1a(hash, &header.get_ack_number().to_be_bytes());
+            },
+            Some(TransportHeader::Udp(header)) => {
+                hash = fnv1a(hash, &header.get_src_port().to_be_bytes());
+                hash = fnv1a(hash, &header.get_dest_port().to_be_bytes());
+            },
+            Some(TransportHeader::Icmp(header)) => {
+                hash = fnv1a(hash, &[header.get_type(), header.get_code()]);
+            },
+            Some(TransportHeader::Icmpv6(header)) => {
+                hash = fnv1a(hash, &[header.get_type(), header.get_code()]);
+            },
+            Some(TransportHeader::Sctp(header)) => {
+                hash = fnv1a(hash, &header.get_src_port().to_be_bytes());
+                hash = fnv1a(hash, &header.get_dest_port().to_be_bytes());
+                hash = fnv1a(hash, &header.get_verification_tag().to_be_bytes());
+            },
+            None => {}
+        }
+        hash = fnv1a(hash, &self.payload);
+        self.hash = Some(hash);
+        hash
+    }
+}
+
+/// Checks that the data is long enough for a decoder that does not check it by itself.
+fn ensure_len(data: &[u8], min_len: usize, what: &str) -> Result<(), DecodeError> {
+    if data.len() < min_len {
+        return Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: format!("Cannot decode {} because is not long enough.", what)})
+    }
+    Ok(())
+}
+
+/// Decodes an Ethernet frame down to the transport layer.
+///
+/// It is meant for untrusted data, as the frames read from a capture file: whatever the input (empty, truncated or
+/// random bytes), it returns an error instead of panicking.
+pub fn try_parse(data: &[u8]) -> Result<ParsedPacket, DecodeError> {
+    try_parse_slice(data).map(ParsedPacket::from)
+}
+
+/// Decodes an Ethernet frame down to the transport layer as try_parse does, without copying the payload.
+pub fn try_parse_slice(data: &[u8]) -> Result<ParsedSlice<'_>, DecodeError> {
+    let (ethernet_result, eth_payload) = EthernetHeader::decode_slice(data);
+    let ethernet = ethernet_result?;
+
+    let (network, protocol, payload) = match ethernet.get_ether_type() {
+        EtherType::Ipv4 => {
+            let (ipv4_result, ipv4_payload) = Ipv4Header::decode_slice(eth_payload);
+            let ipv4_header = ipv4_result?;
+            let protocol = ipv4_header.get_protocol();
+            (NetworkHeader::Ipv4(ipv4_header), protocol, ipv4_payload)
+        },
+        EtherType::Ipv6 => {
+            ensure_len(eth_payload, 40, "an ipv6 packet")?;
+            let (ipv6_result, ipv6_payload) = Ipv6Header::decode_slice(eth_payload);
+            let ipv6_header = ipv6_result?;
+            let protocol = ipv6_header.get_protocol();
+            (NetworkHeader::Ipv6(ipv6_header), protocol, ipv6_payload)
+        },
+        EtherType::ARP => {
+            let (arp_result, arp_payload) = ArpHeader::decode_slice(eth_payload);
+            (NetworkHeader::Arp(arp_result?), Protocol::Unknown, arp_payload)
+        },
+        EtherType::Other(value) => return Err(unknown_ether_type(value))
+    };
+
+    let (transport, payload) = match protocol {
+        Protocol::TCP => {
+            ensure_len(payload, 20, "a tcp segment")?;
+            let (tcp_result, tcp_payload) = TCPHeader::decode_slice(payload);
+            (Some(TransportHeader::Tcp(tcp_result?)), tcp_payload)
+        },
+        Protocol::UDP => {
+            ensure_len(payload, 8, "an udp datagram")?;
+            let (udp_result, udp_payload) = UDPHeader::decode_slice(payload);
+            (Some(TransportHeader::Udp(udp_result?)), udp_payload)
+        },
+        Protocol::ICMP => {
+            let (icmp_result, icmp_payload) = IcmpHeader::decode_slice(payload);
+            (Some(TransportHeader::Icmp(icmp_result?)), icmp_payload)
+        },
+        Protocol::ICMPv6 => {
+            let (icmpv6_result, icmpv6_payload) = Icmpv6Header::decode_slice(payload);
+            (Some(TransportHeader::Icmpv6(icmpv6_result?)), icmpv6_payload)
+        },
+        Protocol::SCTP => {
+            let (sctp_result, sctp_payload) = SctpHeader::decode_slice(payload);
+            (Some(TransportHeader::Sctp(sctp_result?)), sctp_payload)
+        },
+        // the tunneled packet is left as payload, decode_ip_layer unwraps it
+        Protocol::GRE | Protocol::IPIP | Protocol::Unknown => (None, payload)
+    };
+    Ok(ParsedSlice { ethernet, network, transport, payload })
+}
+
+/// The layers of an Ethernet frame decoded by decode_all. Unlike ParsedPacket the fields are public, so that the whole
+/// stack can be matched by a single pattern.
+#[derive(Debug, Clone)]
+pub struct ParsedLayers {
+    pub ethernet: EthernetHeader,
+    pub ip: NetworkHeader,
+    pub transport: Option<TransportHeader>,
+    pub payload: Vec<u8>,
+}
+
+impl From<ParsedPacket> for ParsedLayers {
+    fn from(packet: ParsedPacket) -> Self {
+        ParsedLayers { ethernet: packet.ethernet, ip: packet.network, transport: packet.transport, payload: packet.payload }
+    }
+}
+
+/// Chains the decoders of the whole stack: the Ethernet header, then the network header selected by the ether type, then
+/// the transport header selected by the protocol. As try_parse, it returns an error instead of panicking on bad input.
+pub fn decode_all(data: &[u8]) -> Result<ParsedLayers, DecodeError> {
+    try_parse(data).map(ParsedLayers::from)
+}
+
+/// The number of nested tunnels decode_ip_layer unwraps before giving up.
+pub const MAX_TUNNEL_DEPTH: usize = 8;
+
+/// Decodes the IP packet at the beginning of data, whose version is given by the ether type of the link layer. A packet
+/// carrying a GRE or an IP-in-IP tunnel is unwrapped, decoding the tunneled packet in turn, so the header returned is the
+/// innermost one, with its payload. At most MAX_TUNNEL_DEPTH tunnels are unwrapped, whatever their kind.
+pub fn decode_ip_layer(ether_type: EtherType, data: Vec<u8>) -> Result<(NetworkHeader, Vec<u8>), DecodeError> {
+    decode_ip_layer_with_limit(ether_type, data, MAX_TUNNEL_DEPTH)
+}
+
+/// Decodes the IP packet as decode_ip_layer does, unwrapping at most max_depth tunnels: a crafted packet nesting more
+/// tunnels is rejected, instead of making the decoder recurse without bound.
+pub fn decode_ip_layer_with_limit(ether_type: EtherType, data: Vec<u8>, max_depth: usize) -> Result<(NetworkHeader, Vec<u8>), DecodeError> {
+    decode_ip_layer_slice_with_limit(ether_type, &data, max_depth).map(|(network, payload)| (network, payload.to_vec()))
+}
+
+/// The zero-copy counterpart of decode_ip_layer, the payload is a sub-slice of the data.
+pub fn decode_ip_layer_slice(ether_type: EtherType, data: &[u8]) -> Result<(NetworkHeader, &[u8]), DecodeError> {
+    decode_ip_layer_slice_with_limit(ether_type, data, MAX_TUNNEL_DEPTH)
+}
+
+/// The zero-copy counterpart of decode_ip_layer_with_limit.
+pub fn decode_ip_layer_slice_with_limit(ether_type: EtherType, data: &[u8], max_depth: usize) -> Result<(NetworkHeader, &[u8]), DecodeError> {
+    let (network, protocol, payload) = match ether_type {
+        EtherType::Ipv4 => {
+            let (ipv4_result, ipv4_payload) = Ipv4Header::decode_slice(data);
+            let ipv4_header = ipv4_result?;
+            let protocol = ipv4_header.get_protocol();
+            (NetworkHeader::Ipv4(ipv4_header), protocol, ipv4_payload)
+        },
+        EtherType::Ipv6 => {
+            let (ipv6_result, ipv6_payload) = Ipv6Header::decode_slice(data);
+            let ipv6_header = ipv6_result?;
+            let protocol = ipv6_header.get_protocol();
+            (NetworkHeader::Ipv6(ipv6_header), protocol, ipv6_payload)
+        },
+        EtherType::ARP => return Err(unknown_ether_type(0x0806)),
+        EtherType::Other(value) => return Err(unknown_ether_type(value))
+    };
+    if protocol != Protocol::GRE && protocol != Protocol::IPIP {
+        return Ok((network, payload))
+    }
+    if max_depth == 0 {
+        return Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: "Cannot decode the packet because it nests too many tunnels.".to_string()})
+    }
+    if protocol == Protocol::IPIP {
+        return decode_ip_layer_slice_with_limit(EtherType::Ipv4, payload, max_depth - 1)
+    }
+    let (gre_result, gre_payload) = GreHeader::decode_slice(payload);
+    decode_ip_layer_slice_with_limit(decode_ether_type(gre_result?.get_protocol_type()), gre_payload, max_depth - 1)
+}
+
+/// Decodes the packet carried by a tunnel through decode_ip_layer_slice_with_limit, so that parse_packet rejects the
+/// packets nesting more than MAX_TUNNEL_DEPTH tunnels, the outer one included. A tunneled protocol that decode_ip_layer
+/// does not know, e.g. an Ethernet frame bridged over GRE, is left undecoded.
+fn check_tunneled(ether_type: EtherType, data: &[u8]) -> Result<(), DecodeError> {
+    match decode_ip_layer_slice_with_limit(ether_type, data, MAX_TUNNEL_DEPTH - 1) {
+        Err(error) if !matches!(error.kind, DecodeErrorKind::UnknownEtherType(_) | DecodeErrorKind::UnknownProtocol(_)) => Err(error),
+        _ => Ok(())
+    }
+}
+
+/// Decodes an Ethernet frame captured on the device into a PacketInfo, describing the remote endpoint of the packet: the
+/// direction is found comparing the source address with the addresses of the device.
+pub fn parse_packet(data: Vec<u8>, device: &Device, ts: TimeVal) -> Result<PacketInfo, DecodeError> {
+    parse_packet_with_linktype(Linktype::ETHERNET, data, device, ts)
+}
+
+/// Decodes a packet captured on the device, whose link layer is selected by the linktype, into a PacketInfo.
+pub fn parse_packet_with_linktype(linktype: Linktype, data: Vec<u8>, device: &Device, ts: TimeVal) -> Result<PacketInfo, DecodeError> {
+    parse_packet_slice(linktype, &data, device, ts)
+}
+
+/// Decodes a packet as parse_packet_with_linktype does, borrowing the data: no layer is copied.
+pub fn parse_packet_slice(linktype: Linktype, data: &[u8], device: &Device, ts: TimeVal) -> Result<PacketInfo, DecodeError> {
+    let (ether_type_result, link_payload) = decode_link_layer_slice(linktype, data);
+    let (src, dest, protocol, direction, ip_payload, dscp, ecn, ttl) = match ether_type_result? {
+        EtherType::Ipv4 => {
+            let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode_slice(link_payload);
+            let header = ipv4_header_result?;
+            let direction = get_direction_from_ipv4(&header, device);
+            (header.get_src_address(), header.get_dest_address(), header.get_protocol(), direction, ipv4_payload,
+             header.get_dscp(), header.get_ecn(), header.get_ttl())
+        },
+        EtherType::Ipv6 => {
+            let (ipv6_header_result, ipv6_payload) = Ipv6Header::decode_slice(link_payload);
+            let header = ipv6_header_result?;
+            if header.get_protocol() == Protocol::Unknown {
+                return Err(DecodeError { kind: DecodeErrorKind::UnknownProtocol(header.get_next_header()), msg: "Unknown lev 4 protocol".to_string() })
+            }
+            let direction = get_direction_from_ipv6(&header, device);
+            (header.get_src_address(), header.get_dest_address(), header.get_protocol(), direction, ipv6_payload,
+             header.get_dscp(), header.get_ecn(), header.get_hop_limit())
+        },
+        EtherType::ARP => return Err(DecodeError { kind: DecodeErrorKind::UnknownEtherType(0x0806), msg: "Cannot decode other level 3 header".to_string() }),
+        EtherType::Other(value) => return Err(unknown_ether_type(value))
+    };
+
+    // ICMP and the tunnels have no ports, their packets are reported on port 0
+    let (src_port, dest_port, payload_len, tcp_header) = match protocol {
+        Protocol::UDP => {
+            let (udp_header_result, udp_payload) = UDPHeader::decode_slice(ip_payload);
+            let udp_header = udp_header_result?;
+            (udp_header.get_src_port(), udp_header.get_dest_port(), udp_payload.len(), None)
+        },
+        Protocol::TCP => {
+            let (tcp_header_result, tcp_payload) = TCPHeader::decode_slice(ip_payload);
+            let tcp_header = tcp_header_result?;
+            (tcp_header.get_src_port(), tcp_header.get_dest_port(), tcp_payload.len(), Some(tcp_header))
+        },
+        Protocol::ICMP => {
+            let (icmp_header_result, icmp_payload) = IcmpHeader::decode_slice(ip_payload);
+            icmp_header_result?;
+            (0, 0, icmp_payload.len(), None)
+        },
+        Protocol::ICMPv6 => {
+            let (icmpv6_header_result, icmpv6_payload) = Icmpv6Header::decode_slice(ip_payload);
+            icmpv6_header_result?;
+            (0, 0, icmpv6_payload.len(), None)
+        },
+        Protocol::SCTP => {
+            let (sctp_header_result, sctp_payload) = SctpHeader::decode_slice(ip_payload);
+            let sctp_header = sctp_header_result?;
+            (sctp_header.get_src_port(), sctp_header.get_dest_port(), sctp_payload.len(), None)
+        },
+        // the traffic of a tunnel is accounted to the tunnel endpoints
+        Protocol::GRE => {
+            let (gre_header_result, gre_payload) = GreHeader::decode_slice(ip_payload);
+            check_tunneled(decode_ether_type(gre_header_result?.get_protocol_type()), gre_payload)?;
+            (0, 0, gre_payload.len(), None)
+        },
+        Protocol::IPIP => {
+            check_tunneled(EtherType::Ipv4, ip_payload)?;
+            (0, 0, ip_payload.len(), None)
+        },
+        // the IPv4 and IPv6 headers already reject the protocols we cannot decode
+        Protocol::Unknown => return Err(DecodeError { kind: DecodeErrorKind::Malformed, msg: "Unknown lev 4 protocol".to_string() })
+    };
+
+    let src = Endpoint::new(src, src_port);
+    let dest = Endpoint::new(dest, dest_port);
+    // the packet describes the remote endpoint: the source of the received packets, the destination of the transmitted ones
+    let remote = match direction {
+        Direction::Received => src.clone(),
+        Direction::Transmitted => dest.clone()
+    };
+    let mut info = PacketInfo::new(remote.get_address(), remote.get_port(), protocol, payload_len, ts);
+    info.set_endpoints(src, dest);
+    if let Some(tcp_header) = tcp_header { info.set_tcp_header(tcp_header) }
+    info.set_direction(direction);
+    info.set_dscp(dscp);
+    info.set_ecn(ecn);
+    info.set_ttl(ttl);
+    Ok(info)
+}
+
+/// The headers of an Ethernet frame read by parse_headers_only, down to the transport layer. It borrows the frame, and
+/// nothing is copied but the few fields it reads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderStack<'a> {
+    ether_type: EtherType,
+    src: Option<IpAddr>,
+    dest: Option<IpAddr>,
+    protocol: Protocol,
+    src_port: Option<u16>,
+    dest_port: Option<u16>,
+    payload: &'a [u8],
+}
+
+impl<'a> HeaderStack<'a> {
+    pub fn get_ether_type(&self) -> EtherType { self.ether_type.clone() }
+    /// The source address of the IP header, None for ARP.
+    pub fn get_src_address(&self) -> Option<IpAddr> { self.src }
+    /// The destination address of the IP header, None for ARP.
+    pub fn get_dest_address(&self) -> Option<IpAddr> { self.dest }
+    pub fn get_protocol(&self) -> Protocol { self.protocol.clone() }
+    pub fn get_src_port(&self) -> Option<u16> { self.src_port }
+    pub fn get_dest_port(&self) -> Option<u16> { self.dest_port }
+    /// The bytes following the last header read, a slice of the frame.
+    pub fn get_payload(&self) -> &'a [u8] { self.payload }
+}
+
+/// Reads the headers of an Ethernet frame down to the transport layer, the fast path for the analyses that need only the
+/// addresses and the ports: unlike try_parse, it neither copies the payload nor formats the addresses as strings. As
+/// try_parse, it never panics, whatever the input.
+pub fn parse_headers_only(data: &[u8]) -> Result<HeaderStack<'_>, DecodeError> {
+    ensure_len(data, 14, "an ethernet packet")?;
+    // an 802.1Q tag moves the ether type 4 bytes forward
+    let header_len = if data[12] == 0x81 && data[13] == 0x00 { 18 } else { 14 };
+    ensure_len(data, header_len, "a vlan tagged ethernet packet")?;
+    let ether_type = decode_ether_type(u16::from_be_bytes([data[header_len - 2], data[header_len - 1]]));
+    let network = &data[header_len..];
+
+    let (src, dest, protocol, transport) = match ether_type {
+        EtherType::Ipv4 => {
+            ensure_len(network, 20, "an ipv4 packet")?;
+            let header_len = (network[0] & 0x0f) as usize * 4;
+            if header_len < 20 || header_len > network.len() {
+                return Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: format!("Invalid ipv4 header length {}", header_len)})
+            }
+            let src = Ipv4Addr::new(network[12], network[13], network[14], network[15]);
+            let dest = Ipv4Addr::new(network[16], network[17], network[18], network[19]);
+            (Some(IpAddr::V4(src)), Some(IpAddr::V4(dest)), network[9], &network[header_len..])
+        },
+        EtherType::Ipv6 => {
+            ensure_len(network, 40, "an ipv6 packet")?;
+            let src: [u8; 16] = network[8..24].try_into().unwrap();
+            let dest: [u8; 16] = network[24..40].try_into().unwrap();
+            let extensions = walk_ipv6_extension_headers(network[6], &network[40..])?;
+            (Some(IpAddr::V6(Ipv6Addr::from(src))), Some(IpAddr::V6(Ipv6Addr::from(dest))), extensions.next_header, &network[40 + extensions.len..])
+        },
+        EtherType::ARP => (None, None, 0, network),
+        EtherType::Other(value) => return Err(unknown_ether_type(value))
+    };
+
+    let (protocol, header_len) = match protocol {
+        0x06 if ether_type != EtherType::ARP => {
+            ensure_len(transport, 20, "a tcp segment")?;
+            let header_len = (transport[12] >> 4) as usize * 4;
+            if header_len < 20 || header_len > transport.len() {
+                return Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: format!("Invalid tcp header length {}", header_len)})
+            }
+            (Protocol::TCP, header_len)
+        },
+        0x11 if ether_type != EtherType::ARP => {
+            ensure_len(transport, 8, "an udp datagram")?;
+            (Protocol::UDP, 8)
+        },
+        0x01 if ether_type == EtherType::Ipv4 => {
+            ensure_len(transport, 4, "an icmp packet")?;
+            (Protocol::ICMP, 4)
+        },
+        0x3a if ether_type == EtherType::Ipv6 => {
+            ensure_len(transport, 4, "an icmpv6 packet")?;
+            (Protocol::ICMPv6, 4)
+        },
+        0x84 if ether_type != EtherType::ARP => {
+            ensure_len(transport, 12, "an sctp packet")?;
+            (Protocol::SCTP, 12)
+        },
+        // as for try_parse, the payload starts with the GRE header
+        0x2f if ether_type != EtherType::ARP => (Protocol::GRE, 0),
+        0x04 if ether_type != EtherType::ARP => (Protocol::IPIP, 0),
+        _ => (Protocol::Unknown, 0)
+    };
+    let (src_port, dest_port) = if protocol == Protocol::TCP || protocol == Protocol::UDP || protocol == Protocol::SCTP {
+        (Some(u16::from_be_bytes([transport[0], transport[1]])), Some(u16::from_be_bytes([transport[2], transport[3]])))
+    } else { (None, None) };
+    Ok(HeaderStack { ether_type, src, dest, protocol, src_port, dest_port, payload: &transport[header_len..] })
 }
 
 #[cfg(test)]
 mod tests {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::collections::HashMap;
     use crate::pkt_parser::{*};
 
+    #[test]
+    fn test_payload_hex() {
+        let payload = [0x16, 0x03, 0x01, 0x00, 0xa5, 0x01];
+        assert_eq!(payload_hex(&payload, 16), "16030100a501");
+        assert_eq!(payload_hex(&payload, 6), "16030100a501");
+        assert_eq!(payload_hex(&payload, 4), "16030100...");
+        assert_eq!(payload_hex(&[], 4), "");
+    }
+
     #[test]
     fn test_ethernet_packet() {
         let data = vec![51, 51, 0, 1, 0, 2, 80, 235, 113, 35, 142, 103, 134, 221, 96, 9, 31, 94, 0, 103, 17, 1, 254, 128, 0, 0, 0, 0, 0, 0, 5, 194, 180, 157, 9, 91, 63, 25, 255, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 2, 2, 34, 2, 35, 0, 103, 0, 211, 1, 228, 89, 38, 0, 8, 0, 2, 12, 31, 0, 1, 0, 14, 0, 1, 0, 1, 42, 94, 58, 157, 80, 235, 113, 35, 142, 103, 0, 3, 0, 12, 10, 80, 235, 113, 0, 0, 0, 0, 0, 0, 0, 0, 0, 39, 0, 17, 0, 15, 68, 69, 83, 75, 84, 79, 80, 45, 83, 86, 65, 65, 84, 84, 52, 0, 16, 0, 14, 0, 0, 1, 55, 0, 8, 77, 83, 70, 84, 32, 53, 46, 48, 0, 6, 0, 8, 0, 17, 0, 23, 0, 24, 0, 39];
@@ -416,4 +1958,1096 @@ mod tests {
         assert_eq!(tcp_header.get_src_port(), 56369);
         assert_eq!(tcp_header.get_dest_port(), 443);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_ipv4_typed_addresses() {
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode(vec![69, 0, 0, 130, 170, 10, 64, 0, 64, 17, 12, 250, 192, 168, 1, 1, 192, 168, 1, 21]);
+        let ipv4_header = ipv4_header_result.unwrap();
+        assert_eq!(ipv4_header.get_dest_ip(), Ipv4Addr::new(192, 168, 1, 21));
+        assert_eq!(ipv4_header.get_src_ip(), Ipv4Addr::new(192, 168, 1, 1));
+        assert_eq!(ipv4_header.get_src_ip().to_string(), ipv4_header.get_src_address());
+
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode(vec![69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92]);
+        let ipv4_header = ipv4_header_result.unwrap();
+        assert_eq!(ipv4_header.get_dest_ip(), Ipv4Addr::new(149, 154, 167, 92));
+        assert!(ipv4_header.get_src_ip().is_private());
+    }
+
+    #[test]
+    fn test_ipv4_ttl() {
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode(vec![69, 0, 0, 130, 170, 10, 64, 0, 64, 17, 12, 250, 192, 168, 1, 1, 192, 168, 1, 21]);
+        assert_eq!(ipv4_header_result.unwrap().get_ttl(), 64);
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode(vec![69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92]);
+        assert_eq!(ipv4_header_result.unwrap().get_ttl(), 128);
+    }
+
+    #[test]
+    fn test_tcp_sequence_numbers() {
+        let (tcp_header_result, _tcp_payload) = TCPHeader::decode(vec![220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0]);
+        let tcp_header = tcp_header_result.unwrap();
+        assert_eq!(tcp_header.get_seq_number(), u32::from_be_bytes([135, 216, 62, 67]));
+        assert_eq!(tcp_header.get_seq_number(), 2279095875);
+        assert_eq!(tcp_header.get_ack_number(), u32::from_be_bytes([24, 80, 57, 27]));
+        assert_eq!(tcp_header.get_ack_number(), 407910683);
+    }
+
+    #[test]
+    fn test_tcp_reset_window() {
+        let (tcp_header_result, _tcp_payload) = TCPHeader::decode(vec![220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0]);
+        let tcp_header = tcp_header_result.unwrap();
+        // the segment is a reset, that advertises no window
+        assert!(tcp_header.get_flags().is_rst());
+        assert_eq!(tcp_header.get_window_size(), 0);
+    }
+
+    #[test]
+    fn test_null_loopback_packet() {
+        // IPv4/UDP packet captured on lo0 of a macOS host (little endian AF_INET header)
+        let data = vec![2, 0, 0, 0, 69, 0, 0, 33, 18, 52, 0, 0, 64, 17, 0, 0, 127, 0, 0, 1, 127, 0, 0, 1, 195, 80, 0, 53, 0, 13, 0, 0, 104, 101, 108, 108, 111];
+        let (null_header_res, null_payload) = NullHeader::decode(data.clone());
+        let null_header = null_header_res.unwrap();
+        assert_eq!(null_header.get_family(), 2);
+        assert_eq!(null_header.get_ether_type(), EtherType::Ipv4);
+
+        let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode(null_payload);
+        let ipv4_header = ipv4_header_result.unwrap();
+        assert_eq!(ipv4_header.get_src_address(), "127.0.0.1".to_string());
+        assert_eq!(ipv4_header.get_dest_address(), "127.0.0.1".to_string());
+        assert_eq!(ipv4_header.get_protocol(), Protocol::UDP);
+
+        let (udp_header_result, _udp_payload) = UDPHeader::decode(ipv4_payload);
+        assert_eq!(udp_header_result.unwrap().get_dest_port(), 53);
+
+        // the same header written by a big endian host, selected through the linktype
+        let mut big_endian = data;
+        big_endian[0..4].copy_from_slice(&[0, 0, 0, 2]);
+        let (ether_type_result, _payload) = decode_link_layer(Linktype::NULL, big_endian);
+        assert_eq!(ether_type_result.unwrap(), EtherType::Ipv4);
+    }
+
+    #[test]
+    fn test_vlan_tagged_frame() {
+        // the segment of test_whole_packet_2 tagged with the VLAN 10, priority 5
+        let data = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 129, 0, 160, 10, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+        let (ethernet_header_result, ethernet_payload) = EthernetHeader::decode(data.clone());
+        let ethernet_header = ethernet_header_result.unwrap();
+        assert_eq!(ethernet_header.get_ether_type(), EtherType::Ipv4);
+        assert_eq!(ethernet_header.get_vlan_id(), Some(10));
+        assert_eq!(ethernet_header.get_vlan_tag().unwrap().priority(), 5);
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode(ethernet_payload);
+        let ipv4_header = ipv4_header_result.unwrap();
+        assert_eq!(ipv4_header.get_dest_address(), "149.154.167.92");
+        assert_eq!(ipv4_header.get_protocol(), Protocol::TCP);
+
+        // a tag without the real ether type
+        assert!(EthernetHeader::decode(Vec::from(&data[..16])).0.is_err());
+        let untagged = [&data[..12], &data[16..]].concat();
+        assert_eq!(EthernetHeader::decode(untagged).0.unwrap().get_vlan_id(), None);
+    }
+
+    #[test]
+    fn test_sll_packet() {
+        // the IPv4/UDP packet of test_sll2_packet, captured on the "any" device of an older kernel
+        let data = vec![0, 4, 0, 1, 0, 6, 80, 235, 113, 35, 142, 103, 0, 0, 8, 0, 69, 0, 0, 33, 18, 52, 0, 0, 64, 17, 0, 0, 192, 168, 1, 21, 192, 168, 1, 1, 195, 80, 0, 53, 0, 13, 0, 0, 104, 101, 108, 108, 111];
+        let (sll_header_res, sll_payload) = SllHeader::decode(data.clone());
+        let sll_header = sll_header_res.unwrap();
+        assert_eq!(sll_header.get_ether_type(), EtherType::Ipv4);
+        assert_eq!(sll_header.get_packet_type(), 4);
+        assert_eq!(sll_header.get_arphrd_type(), 1);
+        assert_eq!(sll_header.get_src_address(), "50eb71238e67".to_string());
+
+        let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode(sll_payload);
+        let ipv4_header = ipv4_header_result.unwrap();
+        assert_eq!(ipv4_header.get_src_address(), "192.168.1.21".to_string());
+        assert_eq!(ipv4_header.get_dest_address(), "192.168.1.1".to_string());
+        assert_eq!(ipv4_header.get_protocol(), Protocol::UDP);
+        let (udp_header_result, _udp_payload) = UDPHeader::decode(ipv4_payload);
+        assert_eq!(udp_header_result.unwrap().get_dest_port(), 53);
+
+        let (ether_type_result, _payload) = decode_link_layer(Linktype::LINUX_SLL, data);
+        assert_eq!(ether_type_result.unwrap(), EtherType::Ipv4);
+        assert!(SllHeader::decode(vec![0, 4, 0, 1, 0, 6]).0.is_err());
+    }
+
+    #[test]
+    fn test_sll2_packet() {
+        // IPv4/UDP packet sent by us on the interface 2, captured on the "any" device
+        let data = vec![8, 0, 0, 0, 0, 0, 0, 2, 0, 1, 4, 6, 80, 235, 113, 35, 142, 103, 0, 0, 69, 0, 0, 33, 18, 52, 0, 0, 64, 17, 0, 0, 192, 168, 1, 21, 192, 168, 1, 1, 195, 80, 0, 53, 0, 13, 0, 0, 104, 101, 108, 108, 111];
+        let (sll2_header_res, sll2_payload) = Sll2Header::decode(data.clone());
+        let sll2_header = sll2_header_res.unwrap();
+        assert_eq!(sll2_header.get_ether_type(), EtherType::Ipv4);
+        assert_eq!(sll2_header.get_interface_index(), 2);
+        assert_eq!(sll2_header.get_packet_type(), 4);
+        assert_eq!(sll2_header.get_src_address(), "50eb71238e67".to_string());
+
+        let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode(sll2_payload);
+        let ipv4_header = ipv4_header_result.unwrap();
+        assert_eq!(ipv4_header.get_src_address(), "192.168.1.21".to_string());
+        assert_eq!(ipv4_header.get_dest_address(), "192.168.1.1".to_string());
+        let (udp_header_result, _udp_payload) = UDPHeader::decode(ipv4_payload);
+        assert_eq!(udp_header_result.unwrap().get_dest_port(), 53);
+
+        let (ether_type_result, _payload) = decode_link_layer(Linktype::LINUX_SLL2, data);
+        assert_eq!(ether_type_result.unwrap(), EtherType::Ipv4);
+        assert!(Sll2Header::decode(vec![8, 0, 0, 0]).0.is_err());
+    }
+
+    #[test]
+    fn test_direction_from_ethernet() {
+        let data = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+        let (ethernet_header_res, _payload) = EthernetHeader::decode(data);
+        let ethernet_header = ethernet_header_res.unwrap();
+
+        assert_eq!(get_direction_from_mac(&ethernet_header, "50:EB:71:23:8E:67"), Some(Direction::Transmitted));
+        assert_eq!(get_direction_from_mac(&ethernet_header, "98006a045520"), Some(Direction::Received));
+        assert_eq!(get_direction_from_mac(&ethernet_header, "020000000001"), None);
+
+        let device = Device { name: "eth0".to_string(), desc: None, addresses: vec![], flags: pcap::DeviceFlags::empty() };
+        let mac_lookup = |device: &Device| if device.name == "eth0" { Some("50:eb:71:23:8e:67".to_string()) } else { None };
+        assert_eq!(get_direction_from_ethernet_with(&ethernet_header, &device, mac_lookup), Some(Direction::Transmitted));
+        let (ethernet_header_res, _payload) = EthernetHeader::decode(vec![80, 235, 113, 35, 142, 103, 152, 0, 106, 4, 85, 32, 8, 0]);
+        assert_eq!(get_direction_from_ethernet_with(&ethernet_header_res.unwrap(), &device, mac_lookup), Some(Direction::Received));
+
+        let unknown_device = Device { name: "not-a-real-interface".to_string(), desc: None, addresses: vec![], flags: pcap::DeviceFlags::empty() };
+        assert_eq!(get_direction_from_ethernet_with(&ethernet_header, &unknown_device, mac_lookup), None);
+        assert_eq!(get_direction_from_ethernet(&ethernet_header, &unknown_device), None);
+    }
+
+    #[test]
+    fn test_direction_from_ethernet_loopback() {
+        // frames on the loopback interface use the all zeros MAC address
+        let data = vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 0, 69, 0, 0, 20, 0, 0, 0, 0, 64, 6, 0, 0, 127, 0, 0, 1, 127, 0, 0, 1];
+        let (ethernet_header_res, _payload) = EthernetHeader::decode(data);
+        let ethernet_header = ethernet_header_res.unwrap();
+        let device = Device { name: "lo".to_string(), desc: None, addresses: vec![], flags: pcap::DeviceFlags::empty() };
+        let mac_lookup = |_device: &Device| Some("00:00:00:00:00:00".to_string());
+        assert_eq!(get_direction_from_ethernet_with(&ethernet_header, &device, mac_lookup), Some(Direction::Transmitted));
+    }
+
+    #[test]
+    fn test_gratuitous_arp() {
+        // gratuitous ARP request sent to broadcast by 192.168.1.21
+        let data = vec![255, 255, 255, 255, 255, 255, 80, 235, 113, 35, 142, 103, 8, 6, 0, 1, 8, 0, 6, 4, 0, 1, 80, 235, 113, 35, 142, 103, 192, 168, 1, 21, 0, 0, 0, 0, 0, 0, 192, 168, 1, 21];
+        let (ethernet_header_res, eth_payload) = EthernetHeader::decode(data);
+        assert_eq!(ethernet_header_res.unwrap().get_ether_type(), EtherType::ARP);
+        let (arp_header_res, _payload) = ArpHeader::decode(eth_payload);
+        let arp_header = arp_header_res.unwrap();
+        assert_eq!(arp_header.get_sender_mac(), "50eb71238e67".to_string());
+        assert_eq!(arp_header.get_sender_ip(), "192.168.1.21".to_string());
+        assert!(arp_header.is_gratuitous());
+
+        // who has 192.168.1.1? tell 192.168.1.21
+        let data = vec![0, 1, 8, 0, 6, 4, 0, 1, 80, 235, 113, 35, 142, 103, 192, 168, 1, 21, 0, 0, 0, 0, 0, 0, 192, 168, 1, 1];
+        let (arp_header_res, _payload) = ArpHeader::decode(data);
+        let arp_header = arp_header_res.unwrap();
+        assert_eq!(arp_header.get_target_ip(), "192.168.1.1".to_string());
+        assert!(!arp_header.is_gratuitous());
+    }
+
+    #[test]
+    fn test_arp_request_and_reply() {
+        // who has 192.168.1.1? tell 192.168.1.21, padded to the minimum frame length
+        let mut data = vec![255, 255, 255, 255, 255, 255, 80, 235, 113, 35, 142, 103, 8, 6, 0, 1, 8, 0, 6, 4, 0, 1, 80, 235, 113, 35, 142, 103, 192, 168, 1, 21, 0, 0, 0, 0, 0, 0, 192, 168, 1, 1];
+        data.extend_from_slice(&[0; 18]);
+        let (_ethernet_header_res, eth_payload) = EthernetHeader::decode(data);
+        let (arp_header_res, padding) = ArpHeader::decode(eth_payload);
+        let arp_header = arp_header_res.unwrap();
+        assert_eq!(arp_header.get_hardware_type(), 1);
+        assert_eq!(arp_header.get_protocol_type(), 0x0800);
+        assert_eq!(arp_header.get_operation(), ArpOperation::Request);
+        assert_eq!(arp_header.get_sender_ip(), "192.168.1.21".to_string());
+        assert_eq!(arp_header.get_target_ip(), "192.168.1.1".to_string());
+        assert_eq!(arp_header.get_target_mac(), "000000000000".to_string());
+        assert_eq!(padding.len(), 18);
+
+        // 192.168.1.1 is at 98:00:6a:04:55:20
+        let data = vec![0, 1, 8, 0, 6, 4, 0, 2, 152, 0, 106, 4, 85, 32, 192, 168, 1, 1, 80, 235, 113, 35, 142, 103, 192, 168, 1, 21];
+        let (arp_header_res, _payload) = ArpHeader::decode(data);
+        let arp_header = arp_header_res.unwrap();
+        assert_eq!(arp_header.get_operation(), ArpOperation::Reply);
+        assert_eq!(arp_header.get_sender_mac(), "98006a045520".to_string());
+        assert_eq!(arp_header.get_sender_ip(), "192.168.1.1".to_string());
+    }
+
+    #[test]
+    fn test_dscp() {
+        // a voice packet marked as Expedited Forwarding (46)
+        let data = vec![69, 184, 0, 28, 0, 0, 64, 0, 64, 17, 0, 0, 192, 168, 1, 21, 192, 168, 1, 1, 19, 196, 19, 196, 0, 8, 0, 0];
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode(data);
+        assert_eq!(ipv4_header_result.unwrap().get_dscp(), 46);
+
+        // the same marking, split across the first two bytes of an IPv6 header
+        let mut data = vec![107, 128, 0, 0, 0, 8, 17, 64];
+        data.extend_from_slice(&[0; 32]);
+        data.extend_from_slice(&[19, 196, 19, 196, 0, 8, 0, 0]);
+        let (ipv6_header_result, _ipv6_payload) = Ipv6Header::decode(data);
+        assert_eq!(ipv6_header_result.unwrap().get_dscp(), 46);
+    }
+
+    #[test]
+    fn test_ipv6_next_header() {
+        // an IPv6/TCP segment whose source address has 0x11, the UDP protocol number, at byte 9
+        let data = vec![96, 0, 0, 0, 0, 20, 6, 64, 254, 17, 0, 0, 0, 0, 0, 0, 82, 235, 113, 255, 254, 35, 142, 103,
+                        42, 0, 20, 80, 64, 9, 8, 33, 0, 0, 0, 0, 0, 0, 32, 14,
+                        220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 2, 250, 240, 0, 0, 0, 0];
+        let (ipv6_header_result, ipv6_payload) = Ipv6Header::decode(data);
+        assert_eq!(ipv6_header_result.unwrap().get_protocol(), Protocol::TCP);
+        let (tcp_header_result, _tcp_payload) = TCPHeader::decode(ipv6_payload);
+        assert_eq!(tcp_header_result.unwrap().get_dest_port(), 443);
+
+        // a truncated header
+        let (ipv6_header_result, data) = Ipv6Header::decode(vec![96, 0, 0, 0, 0, 20, 6, 64, 254, 128]);
+        assert!(ipv6_header_result.is_err());
+        assert_eq!(data.len(), 10);
+    }
+
+    #[test]
+    fn test_ipv6_extension_headers() {
+        // a MLD report carrying the Router Alert option in a Hop-by-Hop header, here followed by UDP
+        let mut data = vec![96, 0, 0, 0, 0, 20, 0, 1];
+        data.extend_from_slice(&[254, 128, 0, 0, 0, 0, 0, 0, 82, 235, 113, 255, 254, 35, 142, 103]);
+        data.extend_from_slice(&[255, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 22]);
+        data.extend_from_slice(&[17, 0, 5, 2, 0, 0, 1, 0]);
+        data.extend_from_slice(&[19, 196, 0, 53, 0, 12, 0, 0, 1, 2, 3, 4]);
+        let (ipv6_header_result, ipv6_payload) = Ipv6Header::decode(data.clone());
+        let ipv6_header = ipv6_header_result.unwrap();
+        assert_eq!(ipv6_header.get_protocol(), Protocol::UDP);
+        assert_eq!(ipv6_header.get_extension_headers(), vec![0]);
+        let (udp_header_result, udp_payload) = UDPHeader::decode(ipv6_payload);
+        assert_eq!(udp_header_result.unwrap().get_dest_port(), 53);
+        assert_eq!(udp_payload, vec![1, 2, 3, 4]);
+
+        // a Hop-by-Hop header announcing more bytes than there are
+        data[41] = 4;
+        let (ipv6_header_result, _data) = Ipv6Header::decode(data);
+        assert!(ipv6_header_result.is_err());
+
+        // the second fragment of a datagram does not carry the transport header
+        let mut data = vec![96, 0, 0, 0, 0, 16, 44, 64];
+        data.extend_from_slice(&[0; 32]);
+        data.extend_from_slice(&[17, 0, 5, 168, 0, 0, 0, 1, 1, 2, 3, 4, 5, 6, 7, 8]);
+        let (ipv6_header_result, ipv6_payload) = Ipv6Header::decode(data);
+        let ipv6_header = ipv6_header_result.unwrap();
+        assert_eq!(ipv6_header.get_protocol(), Protocol::Unknown);
+        assert_eq!(ipv6_header.get_extension_headers(), vec![44]);
+        assert_eq!(ipv6_payload.len(), 8);
+    }
+
+    #[test]
+    fn test_ipv6_payload_length() {
+        // the Hop-by-Hop header and the UDP datagram of test_ipv6_extension_headers
+        let mut data = vec![96, 0, 0, 0, 0, 20, 0, 1];
+        data.extend_from_slice(&[254, 128, 0, 0, 0, 0, 0, 0, 82, 235, 113, 255, 254, 35, 142, 103]);
+        data.extend_from_slice(&[255, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 22]);
+        data.extend_from_slice(&[17, 0, 5, 2, 0, 0, 1, 0]);
+        data.extend_from_slice(&[19, 196, 0, 53, 0, 12, 0, 0, 1, 2, 3, 4]);
+        let captured_len = data.len();
+        let (ipv6_header_result, _ipv6_payload) = Ipv6Header::decode(data.clone());
+        let ipv6_header = ipv6_header_result.unwrap();
+        assert_eq!(ipv6_header.get_payload_length(), 20);
+        assert_eq!(ipv6_header.get_hop_limit(), 1);
+        assert!(!ipv6_header.is_truncated(captured_len));
+
+        // the same datagram clipped by the snaplen
+        let (ipv6_header_result, _ipv6_payload) = Ipv6Header::decode(Vec::from(&data[..54]));
+        assert!(ipv6_header_result.unwrap().is_truncated(54));
+    }
+
+    #[test]
+    fn test_ipv6_addresses() {
+        let mut data = vec![96, 0, 0, 0, 0, 8, 17, 64];
+        data.extend_from_slice(&[254, 128, 0, 0, 0, 0, 0, 0, 82, 235, 113, 255, 254, 35, 142, 103]);
+        data.extend_from_slice(&[32, 1, 13, 184, 0, 10, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        data.extend_from_slice(&[19, 196, 0, 53, 0, 8, 0, 0]);
+        let (ipv6_header_result, _ipv6_payload) = Ipv6Header::decode(data);
+        let ipv6_header = ipv6_header_result.unwrap();
+        assert_eq!(ipv6_header.get_hop_limit(), 64);
+        assert_eq!(ipv6_header.get_src_address(), "fe80::52eb:71ff:fe23:8e67");
+        assert_eq!(ipv6_header.get_dest_address(), "2001:db8:a:100::1");
+        // the addresses round-trip through the standard parser
+        assert_eq!(ipv6_header.get_src_address().parse::<Ipv6Addr>().unwrap(), "fe80::52eb:71ff:fe23:8e67".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(ipv6_header.get_dest_address().parse::<Ipv6Addr>().unwrap(), "2001:db8:a:100::1".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(ipv6_header.get_src_ip(), Ipv6Addr::new(0xfe80, 0, 0, 0, 0x52eb, 0x71ff, 0xfe23, 0x8e67));
+        assert_eq!(ipv6_header.get_dest_ip(), Ipv6Addr::new(0x2001, 0xdb8, 0xa, 0x100, 0, 0, 0, 1));
+    }
+
+    #[test]
+    fn test_ipv6_address_to_string() {
+        let mut loopback = [0; 16];
+        loopback[15] = 1;
+        assert_eq!(utils::ipv6_address_to_string(&loopback), "::1");
+        assert_eq!(utils::ipv6_address_to_string(&[32, 1, 13, 184, 0, 1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6]), "2001:db8:1:2:3:4:5:6");
+        // the longest run of zeros is compressed, a single zero hextet is not
+        assert_eq!(utils::ipv6_address_to_string(&[32, 1, 13, 184, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1]), "2001:db8:0:1::1");
+
+        // the addresses match the ones of the device, so that the direction is detected
+        let address = pcap::Address { addr: "fe80::52eb:71ff:fe23:8e67".parse().unwrap(), netmask: None, broadcast_addr: None, dst_addr: None };
+        let device = Device { name: "eth0".to_string(), desc: None, addresses: vec![address], flags: pcap::DeviceFlags::empty() };
+        let mut data = vec![96, 0, 0, 0, 0, 0, 17, 64];
+        data.extend_from_slice(&[254, 128, 0, 0, 0, 0, 0, 0, 82, 235, 113, 255, 254, 35, 142, 103]);
+        data.extend_from_slice(&[255, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 22]);
+        let ipv6_header = Ipv6Header::decode(data).0.unwrap();
+        assert_eq!(get_direction_from_ipv6(&ipv6_header, &device), Direction::Transmitted);
+    }
+
+    #[test]
+    fn test_short_tcp_segment() {
+        // a segment clipped by the snaplen
+        let (tcp_header_result, data) = TCPHeader::decode(vec![220, 49, 1, 187, 135, 216, 62, 67, 24, 80]);
+        assert!(tcp_header_result.is_err());
+        assert_eq!(data.len(), 10);
+    }
+
+    #[test]
+    fn test_icmp_echo_request() {
+        // ping 192.168.1.1, identifier 1 and sequence number 7
+        let data = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 32, 18, 52, 0, 0, 64, 1, 0, 0, 192, 168, 1, 21, 192, 168, 1, 1,
+                        8, 0, 247, 247, 0, 1, 0, 7, 112, 105, 110, 103];
+        let (_ethernet_header_res, eth_payload) = EthernetHeader::decode(data.clone());
+        let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode(eth_payload);
+        assert_eq!(ipv4_header_result.unwrap().get_protocol(), Protocol::ICMP);
+        let (icmp_header_result, icmp_payload) = IcmpHeader::decode(ipv4_payload);
+        let icmp_header = icmp_header_result.unwrap();
+        assert_eq!(icmp_header.get_type(), 8);
+        assert_eq!(icmp_header.get_code(), 0);
+        assert_eq!(icmp_header.get_checksum(), 0xf7f7);
+        assert_eq!(icmp_payload, vec![0, 1, 0, 7, 112, 105, 110, 103]);
+
+        let packet = try_parse(&data).unwrap();
+        assert!(matches!(packet.get_transport_header(), Some(TransportHeader::Icmp(header)) if header.get_type() == 8));
+        let headers = parse_headers_only(&data).unwrap();
+        assert_eq!((headers.get_protocol(), headers.get_src_port()), (Protocol::ICMP, None));
+    }
+
+    #[test]
+    fn test_tcp_flags() {
+        // the SYN-ACK answering the SYN of test_tcp_options
+        let data = vec![1, 187, 220, 49, 12, 34, 56, 78, 135, 216, 62, 67, 80, 0x12, 255, 255, 0, 0, 0, 0];
+        let (tcp_header_result, _tcp_payload) = TCPHeader::decode(data);
+        let flags = tcp_header_result.unwrap().get_flags();
+        assert!(flags.is_syn());
+        assert!(flags.is_ack());
+        assert!(!flags.is_fin() && !flags.is_rst() && !flags.is_psh() && !flags.is_urg());
+
+        let flags = TcpFlags::from(0x29);
+        assert!(flags.is_fin() && flags.is_psh() && flags.is_urg());
+        assert!(!flags.is_syn() && !flags.is_ack());
+    }
+
+    #[test]
+    fn test_tcp_options() {
+        // a SYN with a data offset of 8 words: MSS 1460, NOP, window scale 8, NOP, NOP, SACK permitted
+        let data = vec![220, 49, 1, 187, 135, 216, 62, 66, 0, 0, 0, 0, 128, 2, 250, 240, 0, 0, 0, 0,
+                        2, 4, 5, 180, 1, 3, 3, 8, 1, 1, 4, 2];
+        let (tcp_header_result, tcp_payload) = TCPHeader::decode(data);
+        let tcp_header = tcp_header_result.unwrap();
+        assert!(tcp_header.get_flags().is_syn());
+        // the window field (250, 240), still unscaled
+        assert_eq!(tcp_header.get_window_size(), 64240);
+        assert!(tcp_payload.is_empty());
+
+        // the same header followed by 5 bytes of data
+        let mut data = vec![220, 49, 1, 187, 135, 216, 62, 67, 0, 0, 0, 0, 128, 24, 250, 240, 0, 0, 0, 0,
+                            1, 1, 8, 10, 0, 0, 0, 1, 0, 0, 0, 2];
+        data.extend_from_slice(b"hello");
+        let (_tcp_header_result, tcp_payload) = TCPHeader::decode(data);
+        assert_eq!(tcp_payload, b"hello");
+
+        // a data offset beyond the end of the segment
+        let (tcp_header_result, _data) = TCPHeader::decode(vec![220, 49, 1, 187, 0, 0, 0, 0, 0, 0, 0, 0, 240, 2, 0, 0, 0, 0, 0, 0]);
+        assert!(tcp_header_result.is_err());
+    }
+
+    #[test]
+    fn test_short_udp_datagram() {
+        let (udp_header_result, data) = UDPHeader::decode(vec![0, 53, 234, 64, 0, 110]);
+        assert!(udp_header_result.is_err());
+        assert_eq!(data.len(), 6);
+
+        let (udp_header_result, udp_payload) = UDPHeader::decode(vec![19, 196, 0, 53, 0, 12, 0, 0, 1, 2, 3, 4]);
+        assert_eq!(udp_header_result.unwrap().get_length() as usize, udp_payload.len() + 8);
+    }
+
+    #[test]
+    fn test_ecn() {
+        // an ECN capable packet marked by a router as Congestion Experienced
+        let data = vec![69, 3, 0, 40, 0, 0, 64, 0, 64, 6, 0, 0, 192, 168, 1, 21, 192, 168, 1, 1];
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode(data);
+        assert_eq!(ipv4_header_result.unwrap().get_ecn(), Ecn::Ce);
+
+        // ECT(0) in the traffic class of an IPv6 header
+        let mut data = vec![96, 32, 0, 0, 0, 8, 17, 64];
+        data.extend_from_slice(&[0; 32]);
+        data.extend_from_slice(&[19, 196, 19, 196, 0, 8, 0, 0]);
+        let (ipv6_header_result, _ipv6_payload) = Ipv6Header::decode(data);
+        assert_eq!(ipv6_header_result.unwrap().get_ecn(), Ecn::Ect0);
+
+        // a segment echoing the congestion, with ECE and ACK set
+        let (tcp_header_result, _tcp_payload) = TCPHeader::decode(vec![1, 187, 220, 49, 0, 0, 0, 1, 0, 0, 0, 1, 80, 0x50, 0, 255, 0, 0, 0, 0]);
+        let flags = tcp_header_result.unwrap().get_flags();
+        assert!(flags.is_ece());
+        assert!(!flags.is_cwr());
+        assert!(flags.is_ack());
+    }
+
+    #[test]
+    fn test_ipv4_header_length() {
+        // IHL 15 announces 60 bytes of header, but only 20 are there
+        let data = vec![79, 0, 0, 20, 0, 0, 64, 0, 64, 17, 0, 0, 192, 168, 1, 21, 192, 168, 1, 1];
+        let (ipv4_header_result, data) = Ipv4Header::decode(data);
+        assert!(ipv4_header_result.is_err());
+        assert_eq!(data.len(), 20);
+        // IHL 4 is shorter than the fixed header
+        let data = vec![68, 0, 0, 20, 0, 0, 64, 0, 64, 17, 0, 0, 192, 168, 1, 21, 192, 168, 1, 1];
+        assert!(Ipv4Header::decode(data).0.is_err());
+
+        // the options are skipped: IHL 6, with a Router Alert option
+        let data = vec![70, 0, 0, 32, 0, 0, 64, 0, 1, 17, 0, 0, 192, 168, 1, 21, 224, 0, 0, 22, 148, 4, 0, 0, 19, 196, 19, 196, 0, 8, 0, 0];
+        let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode(data);
+        assert!(ipv4_header_result.is_ok());
+        assert_eq!(ipv4_payload, vec![19, 196, 19, 196, 0, 8, 0, 0]);
+    }
+
+    #[test]
+    fn test_ipv4_truncated_capture() {
+        // the DNS response of test_whole_packet_1 (130 bytes) captured with a snaplen of 64 bytes
+        let data = vec![80, 235, 113, 35, 142, 103, 152, 0, 106, 4, 85, 32, 8, 0, 69, 0, 0, 130, 170, 10, 64, 0, 64, 17, 12, 250, 192, 168, 1, 1, 192, 168, 1, 21, 0, 53, 234, 64, 0, 110, 71, 245, 212, 212, 129, 131, 0, 1, 0, 0, 0, 1, 0, 0, 4, 119, 112, 97, 100, 4, 104, 111, 109, 101];
+        let (_ethernet_header_res, eth_payload) = EthernetHeader::decode(data);
+        let captured_len = eth_payload.len();
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode(eth_payload);
+        let ipv4_header = ipv4_header_result.unwrap();
+        assert_eq!(ipv4_header.get_total_length(), 130);
+        assert_eq!(captured_len, 50);
+        assert!(ipv4_header.is_truncated(captured_len));
+        assert!(!ipv4_header.is_truncated(130));
+        // the Ethernet padding of a short packet is not a truncation
+        assert!(!ipv4_header.is_truncated(146));
+    }
+
+    #[test]
+    fn test_ipv4_checksum() {
+        // the header of the DNS response of test_whole_packet_1
+        let mut data = vec![69, 0, 0, 130, 170, 10, 64, 0, 64, 17, 12, 250, 192, 168, 1, 1, 192, 168, 1, 21, 0, 53, 234, 64, 0, 8, 0, 0];
+        assert!(Ipv4Header::verify_checksum(&data));
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode_strict(data.clone());
+        let ipv4_header = ipv4_header_result.unwrap();
+        assert_eq!(ipv4_header.get_checksum(), 0x0cfa);
+        assert!(ipv4_header.is_checksum_valid());
+
+        // a bit flipped in the source address
+        data[15] ^= 0x10;
+        assert!(!Ipv4Header::verify_checksum(&data));
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode(data.clone());
+        assert!(!ipv4_header_result.unwrap().is_checksum_valid());
+        let (ipv4_header_result, data) = Ipv4Header::decode_strict(data);
+        assert!(ipv4_header_result.is_err());
+        assert_eq!(data.len(), 28);
+    }
+
+    #[test]
+    fn test_ipv4_fragment_fields() {
+        // the second fragment of a datagram with identification 0xaa0a: MF set and offset 185 * 8 bytes
+        let data = vec![69, 0, 0, 28, 170, 10, 32, 185, 64, 17, 0, 0, 192, 168, 1, 21, 192, 168, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0];
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode(data);
+        let ipv4_header = ipv4_header_result.unwrap();
+        assert_eq!(ipv4_header.get_identification(), 0xaa0a);
+        assert!(ipv4_header.get_flags().is_more_fragments());
+        assert!(!ipv4_header.get_flags().is_dont_fragment());
+        assert!(!ipv4_header.get_flags().is_reserved());
+        assert_eq!(ipv4_header.get_fragment_offset(), 1480);
+        assert!(ipv4_header.is_more_fragments());
+        assert!(ipv4_header.is_fragment());
+
+        // a whole datagram with the don't fragment flag
+        let data = vec![69, 0, 0, 20, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92];
+        let ipv4_header = Ipv4Header::decode(data).0.unwrap();
+        assert!(ipv4_header.get_flags().is_dont_fragment());
+        assert_eq!(ipv4_header.get_fragment_offset(), 0);
+        assert!(!ipv4_header.is_more_fragments());
+        assert!(!ipv4_header.is_fragment());
+    }
+
+    #[test]
+    fn test_header_equality() {
+        let data = vec![69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+        let (first, first_payload) = Ipv4Header::decode(data.clone());
+        let (second, second_payload) = Ipv4Header::decode(data.clone());
+        let first = first.unwrap();
+        assert_eq!(first, second.unwrap());
+        assert_eq!(TCPHeader::decode(first_payload).0.unwrap(), TCPHeader::decode(second_payload).0.unwrap());
+
+        // another TTL makes another header
+        let mut other = data;
+        other[8] = 64;
+        assert_ne!(Ipv4Header::decode(other).0.unwrap(), first);
+    }
+
+    #[test]
+    fn test_display_headers() {
+        let data = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+        let (ethernet_header_result, ethernet_payload) = EthernetHeader::decode(data.clone());
+        assert_eq!(ethernet_header_result.unwrap().to_string(), "50eb71238e67 -> 98006a045520, Ipv4");
+        let (ipv4_header_result, _ipv4_payload) = Ipv4Header::decode(ethernet_payload);
+        assert_eq!(ipv4_header_result.unwrap().to_string(), "192.168.1.21 -> 149.154.167.92, TCP");
+
+        let tagged = [&data[..12], &[129, 0, 160, 10], &data[12..]].concat();
+        assert_eq!(format!("{}", EthernetHeader::decode(tagged).0.unwrap()), "50eb71238e67 -> 98006a045520, Ipv4 (vlan 10)");
+
+        let mut data = vec![96, 0, 0, 0, 0, 8, 17, 64];
+        data.extend_from_slice(&[254, 128, 0, 0, 0, 0, 0, 0, 82, 235, 113, 255, 254, 35, 142, 103]);
+        data.extend_from_slice(&[255, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 22]);
+        data.extend_from_slice(&[19, 196, 0, 53, 0, 8, 0, 0]);
+        let (ipv6_header_result, _ipv6_payload) = Ipv6Header::decode(data);
+        assert_eq!(ipv6_header_result.unwrap().to_string(), "fe80::52eb:71ff:fe23:8e67 -> ff02::16, UDP");
+    }
+
+    #[test]
+    fn test_display_packet_info() {
+        let info = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 517, TimeVal::from(1_705_326_302_123_456));
+        assert_eq!(info.to_string(), "[2024-01-15 13:45:02.123456] TCP 149.154.167.92:443, 517 bytes");
+        let info = PacketInfo::new("fe80::1".to_string(), 546, Protocol::UDP, 0, TimeVal::from(0));
+        assert_eq!(format!("{}", info), "[1970-01-01 00:00:00.000000] UDP [fe80::1]:546, 0 bytes");
+    }
+
+    #[test]
+    fn test_packet_endpoints() {
+        // test_whole_packet_2, a reset sent by the device
+        let data = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+        let address = pcap::Address { addr: "192.168.1.21".parse().unwrap(), netmask: None, broadcast_addr: None, dst_addr: None };
+        let device = Device { name: "eth0".to_string(), desc: None, addresses: vec![address], flags: pcap::DeviceFlags::empty() };
+        let info = parse_packet(data.clone(), &device, TimeVal::from(0)).unwrap();
+        assert_eq!(info.get_direction(), Some(Direction::Transmitted));
+        assert_eq!(info.get_src(), Some(Endpoint::new("192.168.1.21".to_string(), 56369)));
+        assert_eq!(info.get_dest(), Some(Endpoint::new("149.154.167.92".to_string(), 443)));
+        assert_eq!(info.get_local(), info.get_src());
+
+        // the same frame captured on another host was received
+        let device = Device { name: "eth0".to_string(), desc: None, addresses: vec![], flags: pcap::DeviceFlags::empty() };
+        let received = parse_packet(data, &device, TimeVal::from(0)).unwrap();
+        assert_eq!(received.get_direction(), Some(Direction::Received));
+        assert_eq!((received.get_address(), received.get_port()), ("192.168.1.21".to_string(), 56369));
+        assert_eq!(received.get_local(), received.get_dest());
+        assert!(!info.same_flow(&received));
+        assert!(PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 0, TimeVal::from(0)).same_flow(&info));
+    }
+
+    #[test]
+    fn test_direction_reuses_device() {
+        let address = pcap::Address { addr: "192.168.1.21".parse().unwrap(), netmask: None, broadcast_addr: None, dst_addr: None };
+        let device = Device { name: "eth0".to_string(), desc: None, addresses: vec![address], flags: pcap::DeviceFlags::empty() };
+        let sent = Ipv4Header::decode(vec![69, 0, 0, 20, 0, 0, 64, 0, 64, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92]).0.unwrap();
+        let received = Ipv4Header::decode(vec![69, 0, 0, 20, 0, 0, 64, 0, 64, 6, 0, 0, 149, 154, 167, 92, 192, 168, 1, 21]).0.unwrap();
+        for _ in 0..1000 {
+            assert_eq!(get_direction_from_ipv4(&sent, &device), Direction::Transmitted);
+            assert_eq!(get_direction_from_ipv4(&received, &device), Direction::Received);
+        }
+        assert_eq!(device.addresses.len(), 1);
+    }
+
+    #[test]
+    fn test_direction_normalizes_addresses() {
+        let address = pcap::Address { addr: "fe80::1".parse().unwrap(), netmask: None, broadcast_addr: None, dst_addr: None };
+        let device = Device { name: "eth0".to_string(), desc: None, addresses: vec![address], flags: pcap::DeviceFlags::empty() };
+        assert!(is_device_address(&device, "fe80::1"));
+        assert!(is_device_address(&device, "fe80:0:0:0:0:0:0:1"));
+        assert!(is_device_address(&device, "FE80:0000::0001"));
+        assert!(!is_device_address(&device, "fe80::2"));
+        assert!(!is_device_address(&device, "not an address"));
+    }
+
+    #[test]
+    fn test_connection_key() {
+        let packets = vec![
+            PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 517, TimeVal::from(1_000_000)),
+            PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(1_010_000)),
+            PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 1400, TimeVal::from(1_020_000)),
+            PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::UDP, 1200, TimeVal::from(1_030_000)),
+        ];
+        let mut bytes: HashMap<ConnectionKey, usize> = HashMap::new();
+        for info in &packets {
+            *bytes.entry(info.get_connection_key()).or_insert(0) += info.get_byte_transmitted();
+        }
+        assert_eq!(bytes.len(), 3);
+        assert_eq!(bytes[&ConnectionKey::new("149.154.167.92".to_string(), 443, Protocol::TCP)], 1917);
+        assert_eq!(bytes[&ConnectionKey::new("149.154.167.92".to_string(), 443, Protocol::UDP)], 1200);
+        assert_eq!(bytes[&packets[1].get_connection_key()], 110);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_packet_info_json() {
+        let data = vec![220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 0x12, 250, 240, 0, 0, 0, 0];
+        let mut info = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 517, TimeVal::from(1_705_326_302_123_456));
+        info.set_tcp_header(TCPHeader::decode(data).0.unwrap());
+        info.set_direction(Direction::Transmitted);
+        info.set_ttl(64);
+        let json = serde_json::to_string(&info).unwrap();
+        assert!(json.contains("\"protocol\":\"TCP\""));
+        assert!(json.contains("\"direction\":\"Transmitted\""));
+
+        let decoded: PacketInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.get_address(), "149.154.167.92");
+        assert_eq!(decoded.get_port(), 443);
+        assert_eq!(decoded.get_protocol(), Protocol::TCP);
+        assert_eq!(decoded.get_byte_transmitted(), 517);
+        assert_eq!(decoded.get_time_stamp(), TimeVal::from(1_705_326_302_123_456));
+        assert_eq!(decoded.get_direction(), Some(Direction::Transmitted));
+        assert_eq!(decoded.get_ttl(), Some(64));
+        assert_eq!(decoded.get_tcp_header(), info.get_tcp_header());
+    }
+
+    #[test]
+    fn test_same_flow() {
+        let mut request = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 517, TimeVal::from(1_000_000));
+        request.set_direction(Direction::Transmitted);
+        let mut response = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 1400, TimeVal::from(1_020_000));
+        response.set_direction(Direction::Received);
+        assert!(request.same_flow(&response));
+        assert!(response.same_flow(&request));
+
+        let other = PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::UDP, 1200, TimeVal::from(1_030_000));
+        assert!(!request.same_flow(&other));
+        let other = PacketInfo::new("149.154.167.91".to_string(), 443, Protocol::TCP, 1200, TimeVal::from(1_030_000));
+        assert!(!request.same_flow(&other));
+    }
+
+    #[test]
+    fn test_display_as_date() {
+        assert_eq!(TimeVal::from(0).display_as_date(), "1970-01-01 00:00:00.000000");
+        assert_eq!(TimeVal::from(1_705_326_302_123_456).display_as_date(), "2024-01-15 13:45:02.123456");
+        // the microseconds exceeding a second are carried to the seconds
+        assert_eq!(TimeVal { sec: 1_705_326_301, u_sec: 1_000_042 }.display_as_date(), "2024-01-15 13:45:02.000042");
+    }
+
+    #[test]
+    fn test_time_val_ordering() {
+        assert!(TimeVal { sec: 4, u_sec: 1_100_000 } > TimeVal { sec: 5, u_sec: 0 });
+        assert!(TimeVal { sec: 4, u_sec: 999_999 } < TimeVal { sec: 5, u_sec: 0 });
+        assert_eq!(TimeVal { sec: 1_705_326_301, u_sec: 1_000_042 }, TimeVal::from(1_705_326_302_000_042));
+        assert_ne!(TimeVal { sec: 4, u_sec: 1_100_000 }, TimeVal { sec: 5, u_sec: 0 });
+        let mut timestamps = vec![TimeVal { sec: 4, u_sec: 1_100_000 }, TimeVal { sec: 5, u_sec: 0 }, TimeVal { sec: 3, u_sec: 2_500_000 }];
+        timestamps.sort();
+        assert_eq!(timestamps, vec![TimeVal::from(5_000_000), TimeVal::from(5_100_000), TimeVal::from(5_500_000)]);
+    }
+
+    #[test]
+    fn test_time_val_sub() {
+        let start = TimeVal { sec: 4, u_sec: 100 };
+        let end = TimeVal { sec: 5, u_sec: 300 };
+        assert_eq!(end.duration_since(&start), Duration::from_micros(1_000_200));
+        // the microseconds borrow a second
+        let start = TimeVal { sec: 4, u_sec: 900_000 };
+        let end = TimeVal { sec: 5, u_sec: 100_000 };
+        assert_eq!(end.clone() - start.clone(), Duration::from_millis(200));
+        assert_eq!(start - end, Duration::ZERO);
+        assert_eq!(TimeVal::from(1_000_000).duration_since(&TimeVal::from(1_000_000)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_time_val_order() {
+        let mut timestamps = vec![TimeVal { sec: 5, u_sec: 100 }, TimeVal { sec: 4, u_sec: 900_000 }, TimeVal { sec: 5, u_sec: 0 }, TimeVal { sec: 4, u_sec: 1 }];
+        timestamps.sort();
+        assert_eq!(timestamps, vec![TimeVal { sec: 4, u_sec: 1 }, TimeVal { sec: 4, u_sec: 900_000 }, TimeVal { sec: 5, u_sec: 0 }, TimeVal { sec: 5, u_sec: 100 }]);
+        assert!(TimeVal::from(1_000_001) > TimeVal::from(999_999));
+
+        let mut packets = [
+            PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 1400, TimeVal::from(1_020_000)),
+            PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(1_010_000)),
+        ];
+        packets.sort_by_key(|info| info.get_time_stamp());
+        assert_eq!(packets[0].get_port(), 53);
+    }
+
+    #[test]
+    fn test_nanosecond_time_stamps() {
+        let ts = TimeValNanos::new(1_650_000_000, 123_456_789);
+        assert_eq!(TimeValNanos::from_nanos(ts.as_nanos()), ts);
+        assert_eq!(ts.as_nanos(), 1_650_000_000_123_456_789);
+        // the microseconds form truncates, but converts back exactly
+        let micros = TimeVal::from(ts.clone());
+        assert_eq!(micros, TimeVal { sec: 1_650_000_000, u_sec: 123_456 });
+        assert_eq!(TimeValNanos::from(micros.clone()), TimeValNanos::new(1_650_000_000, 123_456_000));
+        let micros_u64: u64 = micros.into();
+        assert_eq!(micros_u64, 1_650_000_000_123_456);
+
+        let mut info = PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(micros_u64));
+        assert_eq!(info.get_precise_time_stamp(), TimeValNanos::new(1_650_000_000, 123_456_000));
+        info.set_hardware_time_stamp(ts.clone());
+        assert_eq!(info.get_precise_time_stamp(), ts);
+    }
+
+    #[test]
+    fn test_time_stamp_to_nanos_overflow() {
+        // the nanoseconds of more than 4294967 microseconds do not fit 32 bits
+        assert_eq!(TimeValNanos::from(TimeVal { sec: 10, u_sec: 4_294_968 }), TimeValNanos::new(14, 294_968_000));
+        assert_eq!(TimeValNanos::from(TimeVal { sec: 10, u_sec: u32::MAX }), TimeValNanos::new(4_304, 967_295_000));
+        assert_eq!(TimeValNanos::from(TimeVal { sec: 10, u_sec: 999_999 }), TimeValNanos::new(10, 999_999_000));
+    }
+
+    #[test]
+    fn test_ipv4_address_cast() {
+        let netmask: Ipv4Addr = "255.255.255.0".parse().unwrap();
+        assert_eq!(get_ipv4_address_cast(&"149.154.167.92".parse().unwrap(), None), AddressCast::Unicast);
+        assert_eq!(get_ipv4_address_cast(&"224.0.0.251".parse().unwrap(), None), AddressCast::Multicast);
+        assert_eq!(get_ipv4_address_cast(&"239.255.255.250".parse().unwrap(), Some(&netmask)), AddressCast::Multicast);
+        assert_eq!(get_ipv4_address_cast(&"255.255.255.255".parse().unwrap(), None), AddressCast::Broadcast);
+        // the subnet broadcast is recognized only knowing the netmask
+        assert_eq!(get_ipv4_address_cast(&"192.168.1.255".parse().unwrap(), Some(&netmask)), AddressCast::Broadcast);
+        assert_eq!(get_ipv4_address_cast(&"192.168.1.255".parse().unwrap(), None), AddressCast::Unicast);
+        assert_eq!(get_ipv4_address_cast(&"192.168.1.21".parse().unwrap(), Some(&netmask)), AddressCast::Unicast);
+        assert_eq!(get_ipv4_address_cast(&"10.0.0.1".parse().unwrap(), Some(&"255.255.255.254".parse().unwrap())), AddressCast::Unicast);
+    }
+
+    #[test]
+    fn test_ipv6_address_cast() {
+        assert_eq!(get_ipv6_address_cast(&"ff02::1:2".parse().unwrap()), AddressCast::Multicast);
+        assert_eq!(get_ipv6_address_cast(&"ff02::1".parse().unwrap()), AddressCast::Multicast);
+        assert_eq!(get_ipv6_address_cast(&"fe80::5c2:b49d:95b:3f19".parse().unwrap()), AddressCast::Unicast);
+        assert_eq!(get_ipv6_address_cast(&"2001:db8::1".parse().unwrap()), AddressCast::Unicast);
+    }
+
+    #[test]
+    fn test_try_parse() {
+        let data = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+        let packet = try_parse(&data).unwrap();
+        assert_eq!(packet.get_ethernet_header().get_ether_type(), EtherType::Ipv4);
+        assert!(matches!(packet.get_network_header(), NetworkHeader::Ipv4(header) if header.get_dest_address() == "149.154.167.92"));
+        assert!(matches!(packet.get_transport_header(), Some(TransportHeader::Tcp(header)) if header.get_dest_port() == 443));
+        assert!(packet.get_payload().is_empty());
+
+        // every truncation of the frame is an error, not a panic
+        for len in 0..data.len() {
+            assert!(try_parse(&data[..len]).is_err(), "truncated at {}", len);
+        }
+    }
+
+    #[test]
+    fn test_decode_all() {
+        let data = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+        match decode_all(&data).unwrap() {
+            ParsedLayers { ethernet, ip: NetworkHeader::Ipv4(ip), transport: Some(TransportHeader::Tcp(tcp)), payload } => {
+                assert_eq!(ethernet.get_src_address(), "50eb71238e67");
+                assert_eq!((ip.get_src_address(), ip.get_dest_address()), ("192.168.1.21".to_string(), "149.154.167.92".to_string()));
+                assert_eq!((tcp.get_src_port(), tcp.get_dest_port()), (56369, 443));
+                assert!(tcp.get_flags().is_rst());
+                assert!(payload.is_empty());
+            },
+            layers => panic!("unexpected layers {:?}", layers)
+        }
+        assert!(decode_all(&data[..30]).is_err());
+    }
+
+    #[test]
+    fn test_parse_headers_only() {
+        // the DNS response of test_whole_packet_1
+        let data = vec![80, 235, 113, 35, 142, 103, 152, 0, 106, 4, 85, 32, 8, 0, 69, 0, 0, 130, 170, 10, 64, 0, 64, 17, 12, 250, 192, 168, 1, 1, 192, 168, 1, 21, 0, 53, 234, 64, 0, 110, 71, 245, 212, 212, 129, 131, 0, 1, 0, 0, 0, 1, 0, 0, 4, 119, 112, 97, 100, 4, 104, 111, 109, 101, 0, 0, 1, 0, 1, 0, 0, 6, 0, 1, 0, 0, 0, 91, 0, 64, 1, 97, 12, 114, 111, 111, 116, 45, 115, 101, 114, 118, 101, 114, 115, 3, 110, 101, 116, 0, 5, 110, 115, 116, 108, 100, 12, 118, 101, 114, 105, 115, 105, 103, 110, 45, 103, 114, 115, 3, 99, 111, 109, 0, 120, 134, 93, 48, 0, 0, 7, 8, 0, 0, 3, 132, 0, 9, 58, 128, 0, 1, 81, 128];
+        let headers = parse_headers_only(&data).unwrap();
+        assert_eq!(headers.get_src_address(), Some("192.168.1.1".parse().unwrap()));
+        assert_eq!(headers.get_dest_address(), Some("192.168.1.21".parse().unwrap()));
+        assert_eq!(headers.get_protocol(), Protocol::UDP);
+        assert_eq!((headers.get_src_port(), headers.get_dest_port()), (Some(53), Some(59968)));
+        // the payload is the very slice of the frame
+        assert_eq!(headers.get_payload().as_ptr(), data[42..].as_ptr());
+        assert_eq!(headers.get_payload().len(), data.len() - 42);
+
+        // the TCP segment of test_whole_packet_2
+        let data = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+        let headers = parse_headers_only(&data).unwrap();
+        assert_eq!(headers.get_dest_address(), Some("149.154.167.92".parse().unwrap()));
+        assert_eq!((headers.get_protocol(), headers.get_dest_port()), (Protocol::TCP, Some(443)));
+        assert_eq!(headers, HeaderStack { ether_type: EtherType::Ipv4, src: Some("192.168.1.21".parse().unwrap()),
+            dest: Some("149.154.167.92".parse().unwrap()), protocol: Protocol::TCP, src_port: Some(56369), dest_port: Some(443), payload: &[] });
+        for len in 0..data.len() {
+            assert!(parse_headers_only(&data[..len]).is_err(), "truncated at {}", len);
+        }
+    }
+
+    /// Checks that parse_headers_only is not slower than try_parse, run it with cargo test --release -- --ignored
+    #[test]
+    #[ignore]
+    fn bench_parse_headers_only() {
+        let data = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+        let iterations = 1_000_000;
+        let start = std::time::Instant::now();
+        for _ in 0..iterations {
+            std::hint::black_box(parse_headers_only(std::hint::black_box(&data)).unwrap());
+        }
+        let headers_only = start.elapsed();
+        let start = std::time::Instant::now();
+        for _ in 0..iterations {
+            std::hint::black_box(try_parse(std::hint::black_box(&data)).unwrap());
+        }
+        let full = start.elapsed();
+        assert!(headers_only <= full, "parse_headers_only: {:?}/packet, try_parse: {:?}/packet", headers_only / iterations, full / iterations);
+    }
+
+    #[test]
+    fn test_packet_hash() {
+        // the same segment seen before and after a router: other MAC addresses, TTL and IP checksum
+        let first = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 42, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 24, 0, 0, 254, 206, 0, 0, 104, 105];
+        let mut second = first.clone();
+        second[0..12].copy_from_slice(&[2, 0, 0, 0, 0, 1, 2, 0, 0, 0, 0, 2]);
+        second[22] = 127;
+        second[24..26].copy_from_slice(&[0x1c, 0x2e]);
+        let mut first = try_parse(&first).unwrap();
+        let mut second = try_parse(&second).unwrap();
+        assert_eq!(first.get_hash(), None);
+        assert_eq!(first.compute_hash(), second.compute_hash());
+        assert_eq!(first.get_hash(), second.get_hash());
+
+        // another payload
+        let mut third = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 42, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 24, 0, 0, 254, 206, 0, 0, 104, 106];
+        assert_ne!(try_parse(&third).unwrap().compute_hash(), first.compute_hash());
+        // another identification
+        third[55] = 105;
+        third[19] = 80;
+        assert_ne!(try_parse(&third).unwrap().compute_hash(), first.compute_hash());
+    }
+
+    #[test]
+    fn test_try_parse_never_panics() {
+        // xorshift, so that the inputs are the same at every run
+        let mut state: u64 = 0x2545f4914f6cdd1d;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let ether_types = [[0x08, 0x00], [0x86, 0xdd], [0x08, 0x06], [0x81, 0x00]];
+        for i in 0..20_000 {
+            let len = (next() % 160) as usize;
+            let mut data: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+            // most of the inputs get a known ether type and transport protocol, to reach the inner decoders
+            if len >= 14 && i % 4 != 0 {
+                data[12..14].copy_from_slice(&ether_types[(next() % 4) as usize]);
+                if len > 23 && i % 2 == 0 { data[23] = [6, 17][(next() % 2) as usize] }
+                if len > 20 && i % 2 == 0 { data[20] = [6, 17][(next() % 2) as usize] }
+            }
+            let _ = try_parse(&data);
+        }
+    }
+
+    #[test]
+    fn test_decode_error_kind() {
+        let (ethernet_header_result, _data) = EthernetHeader::decode(vec![0; 10]);
+        assert_eq!(ethernet_header_result.unwrap_err().kind, DecodeErrorKind::TooShort);
+        // an MPLS frame, whose Ethernet header is decoded but not its network layer
+        let mut data = vec![0; 14];
+        data[12] = 0x88;
+        data[13] = 0x47;
+        assert_eq!(try_parse(&data).unwrap_err().kind, DecodeErrorKind::UnknownEtherType(0x8847));
+        // an IPv4 header carrying ESP
+        let (ipv4_header_result, _data) = Ipv4Header::decode(vec![0x45, 0, 0, 20, 0, 0, 0, 0, 64, 50, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        assert_eq!(ipv4_header_result.unwrap_err().kind, DecodeErrorKind::UnknownProtocol(50));
+    }
+
+    #[test]
+    fn test_other_ether_type() {
+        // an MPLS frame: a label stack entry with label 16 and the bottom of stack bit, then an IPv4 header
+        let mut data = vec![2, 0, 0, 0, 0, 2, 2, 0, 0, 0, 0, 1, 0x88, 0x47, 0, 1, 0x01, 64];
+        data.extend_from_slice(&[69, 0, 0, 20, 0, 0, 64, 0, 64, 6, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        let (ethernet_header_result, ethernet_payload) = EthernetHeader::decode(data.clone());
+        let ethernet_header = ethernet_header_result.unwrap();
+        assert_eq!(ethernet_header.get_ether_type(), EtherType::Other(0x8847));
+        assert_eq!(ethernet_payload.len(), 24);
+        assert!(ethernet_header.to_string().ends_with("Other(34887)"));
+        assert_eq!(parse_headers_only(&data).unwrap_err().kind, DecodeErrorKind::UnknownEtherType(0x8847));
+        let device = Device { name: "eth0".to_string(), desc: None, addresses: Vec::new(), flags: pcap::DeviceFlags::empty() };
+        assert!(parse_packet(data, &device, TimeVal::from(0)).is_err());
+    }
+
+    #[test]
+    fn test_sctp_packet() {
+        // an SCTP packet from port 5000 to port 38412 (NGAP) with verification tag 0x0a0b0c0d and a DATA chunk header
+        let mut data = vec![2, 0, 0, 0, 0, 2, 2, 0, 0, 0, 0, 1, 8, 0];
+        data.extend_from_slice(&[69, 0, 0, 48, 0, 0, 64, 0, 64, 132, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        data.extend_from_slice(&[0x13, 0x88, 0x96, 0x0c, 0x0a, 0x0b, 0x0c, 0x0d, 0, 0, 0, 0]);
+        data.extend_from_slice(&[0, 3, 0, 16, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 60]);
+
+        let (ethernet_header_result, ethernet_payload) = EthernetHeader::decode(data.clone());
+        assert!(ethernet_header_result.is_ok());
+        let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode(ethernet_payload);
+        assert_eq!(ipv4_header_result.unwrap().get_protocol(), Protocol::SCTP);
+        let (sctp_header_result, chunks) = SctpHeader::decode(ipv4_payload);
+        let sctp_header = sctp_header_result.unwrap();
+        assert_eq!(sctp_header.get_src_port(), 5000);
+        assert_eq!(sctp_header.get_dest_port(), 38412);
+        assert_eq!(sctp_header.get_verification_tag(), 0x0a0b0c0d);
+        assert_eq!(chunks.len(), 16);
+
+        let stack = parse_headers_only(&data).unwrap();
+        assert_eq!(stack.get_protocol(), Protocol::SCTP);
+        assert_eq!(stack.get_dest_port(), Some(38412));
+        let device = Device { name: "eth0".to_string(), desc: None, addresses: Vec::new(), flags: pcap::DeviceFlags::empty() };
+        let info = parse_packet(data.clone(), &device, TimeVal::from(0)).unwrap();
+        assert_eq!(info.get_protocol().to_string(), "SCTP");
+        assert_eq!(info.get_port(), 5000);
+        assert!(matches!(try_parse(&data).unwrap().get_transport_header(), Some(TransportHeader::Sctp(header)) if header.get_src_port() == 5000));
+
+        // the next header of IPv6
+        let mut data = vec![96, 0, 0, 0, 0, 12, 132, 64];
+        data.extend_from_slice(&[0; 32]);
+        data.extend_from_slice(&[0x13, 0x88, 0x96, 0x0c, 0x0a, 0x0b, 0x0c, 0x0d, 0, 0, 0, 0]);
+        assert_eq!(Ipv6Header::decode(data).0.unwrap().get_protocol(), Protocol::SCTP);
+        assert!(SctpHeader::decode(vec![0x13, 0x88, 0x96, 0x0c]).0.is_err());
+    }
+
+    #[test]
+    fn test_decode_with_len() {
+        // an IPv4 header with a 4 bytes option (IHL 6), followed by an UDP header
+        let mut data = vec![0x46, 0, 0, 32, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2, 0x94, 0x04, 0, 0];
+        data.extend_from_slice(&[0, 53, 0, 53, 0, 8, 0, 0]);
+        let (ipv4_header_result, consumed, ipv4_payload) = Ipv4Header::decode_with_len(data);
+        assert_eq!(ipv4_header_result.unwrap().get_header_length(), 24);
+        assert_eq!(consumed, 24);
+        let (udp_header_result, consumed, udp_payload) = UDPHeader::decode_with_len(ipv4_payload);
+        assert!(udp_header_result.is_ok());
+        assert_eq!(consumed, 8);
+        assert!(udp_payload.is_empty());
+
+        let (ipv4_header_result, consumed, data) = Ipv4Header::decode_with_len(vec![0x46, 0, 0, 20]);
+        assert!(ipv4_header_result.is_err());
+        assert_eq!(consumed, 0);
+        assert_eq!(data.len(), 4);
+    }
+
+    #[test]
+    fn test_decode_slice() {
+        // Ethernet, IPv4 and TCP headers followed by 4 bytes of payload
+        let mut data = vec![2, 0, 0, 0, 0, 2, 2, 0, 0, 0, 0, 1, 8, 0];
+        data.extend_from_slice(&[0x45, 0, 0, 44, 0, 0, 0x40, 0, 64, 6, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        data.extend_from_slice(&[0xc3, 0x50, 0, 80, 0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x02, 0xff, 0xff, 0, 0, 0, 0]);
+        data.extend_from_slice(b"ping");
+
+        let (ethernet_header_result, ethernet_payload) = EthernetHeader::decode_slice(&data);
+        assert_eq!(ethernet_header_result.unwrap().get_ether_type(), EtherType::Ipv4);
+        let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode_slice(ethernet_payload);
+        assert_eq!(ipv4_header_result.unwrap().get_protocol(), Protocol::TCP);
+        let (tcp_header_result, tcp_payload) = TCPHeader::decode_slice(ipv4_payload);
+        assert_eq!(tcp_header_result.unwrap().get_dest_port(), 80);
+        // the payload is not a copy, but the very bytes of the frame
+        assert_eq!(tcp_payload, b"ping");
+        assert_eq!(tcp_payload.as_ptr(), data[54..].as_ptr());
+
+        // the owned version gives the same headers
+        let (tcp_header_result, tcp_payload) = TCPHeader::decode(ipv4_payload.to_vec());
+        assert_eq!(tcp_header_result.unwrap(), TCPHeader::decode_slice(ipv4_payload).0.unwrap());
+        assert_eq!(tcp_payload, b"ping");
+        let (udp_header_result, data) = UDPHeader::decode(vec![0, 53]);
+        assert!(udp_header_result.is_err());
+        assert_eq!(data, vec![0, 53]);
+    }
+
+    /// Wraps the IPv4 packet in depth IP-in-IP tunnels.
+    fn ip_in_ip(mut packet: Vec<u8>, depth: usize) -> Vec<u8> {
+        for _ in 0..depth {
+            let total_length = (packet.len() + 20) as u16;
+            let mut outer = vec![69, 0, (total_length >> 8) as u8, total_length as u8, 0, 0, 64, 0, 64, 4, 0, 0, 203, 0, 113, 1, 198, 51, 100, 1];
+            outer.extend_from_slice(&packet);
+            packet = outer;
+        }
+        packet
+    }
+
+    #[test]
+    fn test_ip_in_ip_depth_limit() {
+        let inner = vec![69, 0, 0, 28, 0, 0, 64, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2, 0, 53, 0, 53, 0, 8, 0, 0];
+        let (network_header, payload) = decode_ip_layer(EtherType::Ipv4, ip_in_ip(inner.clone(), MAX_TUNNEL_DEPTH)).unwrap();
+        assert!(matches!(network_header, NetworkHeader::Ipv4(header) if header.get_src_address() == "10.0.0.1"));
+        assert_eq!(payload, inner[20..].to_vec());
+        let error = decode_ip_layer(EtherType::Ipv4, ip_in_ip(inner.clone(), MAX_TUNNEL_DEPTH + 1)).unwrap_err();
+        assert_eq!(error.kind, DecodeErrorKind::Malformed);
+        assert!(decode_ip_layer(EtherType::Ipv4, ip_in_ip(inner.clone(), 1000)).is_err());
+        assert!(decode_ip_layer_with_limit(EtherType::Ipv4, ip_in_ip(inner.clone(), 1), 0).is_err());
+
+        // parse_packet accounts the tunnel to its endpoints, but rejects the deep chains as well
+        let device = Device { name: "eth0".to_string(), desc: None, addresses: vec![], flags: pcap::DeviceFlags::empty() };
+        let frame = |depth| {
+            let mut frame = vec![2, 0, 0, 0, 0, 2, 2, 0, 0, 0, 0, 1, 8, 0];
+            frame.extend_from_slice(&ip_in_ip(inner.clone(), depth));
+            frame
+        };
+        let info = parse_packet(frame(MAX_TUNNEL_DEPTH), &device, TimeVal::from(0)).unwrap();
+        assert_eq!(info.get_protocol(), Protocol::IPIP);
+        assert_eq!(info.get_port(), 0);
+        assert_eq!(info.get_byte_transmitted(), 20 * (MAX_TUNNEL_DEPTH - 1) + 28);
+        let error = parse_packet(frame(MAX_TUNNEL_DEPTH + 1), &device, TimeVal::from(0)).unwrap_err();
+        assert_eq!(error.kind, DecodeErrorKind::Malformed);
+        assert!(parse_packet(frame(1000), &device, TimeVal::from(0)).is_err());
+        assert_eq!(parse_headers_only(&frame(1)).unwrap().get_protocol(), Protocol::IPIP);
+    }
+
+    /// A frame of Ethernet, IPv4 and TCP headers followed by a payload of the given length.
+    fn tcp_frame(payload_len: usize) -> Vec<u8> {
+        let total_len = (40 + payload_len) as u16;
+        let mut data = vec![2, 0, 0, 0, 0, 2, 2, 0, 0, 0, 0, 1, 8, 0];
+        data.extend_from_slice(&[0x45, 0, (total_len >> 8) as u8, total_len as u8, 0, 0, 0x40, 0, 64, 6, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        data.extend_from_slice(&[0xc3, 0x50, 0, 80, 0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x02, 0xff, 0xff, 0, 0, 0, 0]);
+        data.resize(54 + payload_len, 0xab);
+        data
+    }
+
+    #[test]
+    fn test_slice_path_copies_no_payload() {
+        let device = Device { name: "eth0".to_string(), desc: None, addresses: vec![], flags: pcap::DeviceFlags::empty() };
+        let small = tcp_frame(4);
+        let large = tcp_frame(1400);
+
+        // the TCP and UDP headers hold no strings, decoding them allocates nothing
+        assert_eq!(allocated_by(|| { TCPHeader::decode_slice(&large[34..]).0.unwrap(); }), 0);
+        assert_eq!(allocated_by(|| { UDPHeader::decode_slice(&[0, 53, 0, 53, 0, 12, 0, 0, 1, 2, 3, 4]).0.unwrap(); }), 0);
+
+        // the other headers allocate their addresses, but nothing depends on the size of the payload
+        let parse_small = allocated_by(|| { try_parse_slice(&small).unwrap(); });
+        let parse_large = allocated_by(|| { assert_eq!(try_parse_slice(&large).unwrap().get_payload().len(), 1400); });
+        assert_eq!(parse_small, parse_large);
+        assert!(parse_large < 1400);
+
+        let ip_small = allocated_by(|| { decode_ip_layer_slice(EtherType::Ipv4, &small[14..]).unwrap(); });
+        let ip_large = allocated_by(|| { decode_ip_layer_slice(EtherType::Ipv4, &large[14..]).unwrap(); });
+        assert_eq!(ip_small, ip_large);
+        assert!(ip_large < 1400);
+
+        let packet_small = allocated_by(|| { parse_packet_slice(Linktype::ETHERNET, &small, &device, TimeVal::from(0u64)).unwrap(); });
+        let packet_large = allocated_by(|| { parse_packet_slice(Linktype::ETHERNET, &large, &device, TimeVal::from(0u64)).unwrap(); });
+        assert_eq!(packet_small, packet_large);
+        assert!(packet_large < 1400);
+
+        // the owned path copies the payload
+        assert!(allocated_by(|| { try_parse(&large).unwrap(); }) >= 1400);
+    }
+
+    thread_local! {
+        static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Counts the bytes allocated by every thread, so that a test can measure its own allocations.
+    struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATED.try_with(|allocated| allocated.set(allocated.get() + layout.size()));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let _ = ALLOCATED.try_with(|allocated| allocated.set(allocated.get() + new_size));
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// The bytes allocated by the current thread while running f.
+    fn allocated_by<F: FnOnce()>(f: F) -> usize {
+        let before = ALLOCATED.with(|allocated| allocated.get());
+        f();
+        ALLOCATED.with(|allocated| allocated.get()) - before
+    }
+}
@@ -0,0 +1,140 @@
+//! ndp
+//! This module decodes the Neighbor Discovery messages carried by ICMPv6 (RFC 4861). They drive address resolution and
+//! autoconfiguration on IPv6 networks, so monitoring them is the IPv6 analog of monitoring ARP.
+
+use crate::pkt_parser::{utils, DecodeError, DecodeErrorKind, Icmpv6Header};
+
+/// The Neighbor Discovery message types.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NdpMessageType {
+    RouterSolicitation,
+    RouterAdvertisement,
+    NeighborSolicitation,
+    NeighborAdvertisement,
+    Redirect,
+}
+
+/// describes a Neighbor Discovery message: the target address (for neighbor solicitations/advertisements and redirects)
+/// and the link layer addresses found in its options.
+#[derive(Debug, Clone)]
+pub struct NdpMessage {
+    message_type: NdpMessageType,
+    flags: u8,
+    target: Option<String>,
+    source_link_layer: Option<String>,
+    target_link_layer: Option<String>,
+}
+
+impl NdpMessage {
+    /// Decodes the body of the ICMPv6 message described by header, that is the payload returned by Icmpv6Header::decode.
+    pub fn decode(header: &Icmpv6Header, body: &[u8]) -> Result<Self, DecodeError> {
+        let (message_type, fixed_len, has_target) = match header.get_type() {
+            133 => (NdpMessageType::RouterSolicitation, 4, false),
+            134 => (NdpMessageType::RouterAdvertisement, 12, false),
+            135 => (NdpMessageType::NeighborSolicitation, 20, true),
+            136 => (NdpMessageType::NeighborAdvertisement, 20, true),
+            // the redirect has also the destination address after the target one
+            137 => (NdpMessageType::Redirect, 36, true),
+            value => return Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: format!("The icmpv6 message is not a neighbor discovery one, received type {}", value)})
+        };
+        if body.len() < fixed_len {
+            return Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode a neighbor discovery message because is not long enough.".to_string()})
+        }
+        let flags = match message_type {
+            NdpMessageType::RouterAdvertisement => body[1],
+            NdpMessageType::NeighborAdvertisement => body[0],
+            _ => 0
+        };
+        let target = if has_target { Some(utils::ipv6_address_to_string(&body[4..20])) } else { None };
+
+        let mut message = NdpMessage { message_type, flags, target, source_link_layer: None, target_link_layer: None };
+        let mut options = &body[fixed_len..];
+        while options.len() >= 2 {
+            let option_len = options[1] as usize * 8;
+            if option_len == 0 || option_len > options.len() {
+                return Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: "Malformed neighbor discovery option.".to_string()})
+            }
+            match options[0] {
+                1 => message.source_link_layer = Some(utils::mac_address_to_string(&options[2..option_len])),
+                2 => message.target_link_layer = Some(utils::mac_address_to_string(&options[2..option_len])),
+                _ => {}
+            }
+            options = &options[option_len..];
+        }
+        Ok(message)
+    }
+
+    pub fn get_message_type(&self) -> NdpMessageType { self.message_type.clone() }
+    /// The target address of neighbor solicitations, advertisements and redirects.
+    pub fn get_target_address(&self) -> Option<String> { self.target.clone() }
+    /// The link layer address of the sender, from the Source Link-Layer Address option.
+    pub fn get_source_link_layer_address(&self) -> Option<String> { self.source_link_layer.clone() }
+    /// The link layer address of the target, from the Target Link-Layer Address option.
+    pub fn get_target_link_layer_address(&self) -> Option<String> { self.target_link_layer.clone() }
+
+    /// The Router flag of a neighbor advertisement, set when the sender is a router.
+    pub fn is_router(&self) -> bool { self.message_type == NdpMessageType::NeighborAdvertisement && self.flags & 0x80 != 0 }
+    /// The Solicited flag of a neighbor advertisement, set for answers to a solicitation.
+    pub fn is_solicited(&self) -> bool { self.message_type == NdpMessageType::NeighborAdvertisement && self.flags & 0x40 != 0 }
+    /// The Override flag of a neighbor advertisement, asking to update existing cache entries.
+    pub fn is_override(&self) -> bool { self.message_type == NdpMessageType::NeighborAdvertisement && self.flags & 0x20 != 0 }
+    /// The Managed address configuration flag of a router advertisement (addresses are given by DHCPv6).
+    pub fn is_managed(&self) -> bool { self.message_type == NdpMessageType::RouterAdvertisement && self.flags & 0x80 != 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pkt_parser::{try_parse, EthernetHeader, Header, Icmpv6Header, Ipv6Header, Protocol, TransportHeader};
+    use crate::pkt_parser::ndp::{*};
+
+    #[test]
+    fn test_neighbor_advertisement() {
+        // solicited advertisement for fe80::52eb:71ff:fe23:8e67, with the target link layer option
+        let data = vec![136, 0, 18, 52, 96, 0, 0, 0, 254, 128, 0, 0, 0, 0, 0, 0, 82, 235, 113, 255, 254, 35, 142, 103, 2, 1, 80, 235, 113, 35, 142, 103];
+        let (icmpv6_header_res, icmpv6_payload) = Icmpv6Header::decode(data);
+        let icmpv6_header = icmpv6_header_res.unwrap();
+        assert_eq!(icmpv6_header.get_type(), 136);
+        assert_eq!(icmpv6_header.get_code(), 0);
+
+        let message = NdpMessage::decode(&icmpv6_header, &icmpv6_payload).unwrap();
+        assert_eq!(message.get_message_type(), NdpMessageType::NeighborAdvertisement);
+        assert_eq!(message.get_target_address(), Some("fe80::52eb:71ff:fe23:8e67".to_string()));
+        assert_eq!(message.get_target_link_layer_address(), Some("50eb71238e67".to_string()));
+        assert_eq!(message.get_source_link_layer_address(), None);
+        assert!(message.is_solicited());
+        assert!(message.is_override());
+        assert!(!message.is_router());
+    }
+
+    #[test]
+    fn test_neighbor_solicitation() {
+        // who has fe80::1? sent by fe80::52eb:71ff:fe23:8e67 to the solicited-node multicast address, with its link layer address
+        let mut data = vec![51, 51, 255, 0, 0, 1, 80, 235, 113, 35, 142, 103, 134, 221];
+        data.extend_from_slice(&[96, 0, 0, 0, 0, 32, 58, 255]);
+        data.extend_from_slice(&[254, 128, 0, 0, 0, 0, 0, 0, 82, 235, 113, 255, 254, 35, 142, 103]);
+        data.extend_from_slice(&[255, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 255, 0, 0, 1]);
+        data.extend_from_slice(&[135, 0, 171, 205, 0, 0, 0, 0, 254, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 80, 235, 113, 35, 142, 103]);
+
+        let (_ethernet_header_res, eth_payload) = EthernetHeader::decode(data.clone());
+        let (ipv6_header_res, ipv6_payload) = Ipv6Header::decode(eth_payload);
+        assert_eq!(ipv6_header_res.unwrap().get_protocol(), Protocol::ICMPv6);
+        let (icmpv6_header_res, icmpv6_payload) = Icmpv6Header::decode(ipv6_payload);
+        let icmpv6_header = icmpv6_header_res.unwrap();
+        assert_eq!(icmpv6_header.get_type(), 135);
+        assert_eq!(icmpv6_header.get_code(), 0);
+        let message = NdpMessage::decode(&icmpv6_header, &icmpv6_payload).unwrap();
+        assert_eq!(message.get_message_type(), NdpMessageType::NeighborSolicitation);
+        assert_eq!(message.get_target_address(), Some("fe80::1".to_string()));
+        assert_eq!(message.get_source_link_layer_address(), Some("50eb71238e67".to_string()));
+
+        let packet = try_parse(&data).unwrap();
+        assert!(matches!(packet.get_transport_header(), Some(TransportHeader::Icmpv6(header)) if header.get_type() == 135));
+    }
+
+    #[test]
+    fn test_not_neighbor_discovery() {
+        // echo request
+        let (icmpv6_header_res, icmpv6_payload) = Icmpv6Header::decode(vec![128, 0, 0, 0, 0, 1, 0, 1]);
+        assert!(NdpMessage::decode(&icmpv6_header_res.unwrap(), &icmpv6_payload).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! routing
+//! This module decodes the IPv6 Routing extension header (next header 43, RFC 8200). It lists the addresses the packet has
+//! to visit before reaching its destination, that is a form of source routing: the type 0 routing header has been
+//! deprecated (RFC 5095) because it allows to bounce traffic between hosts and amplify it, so its presence is suspicious.
+//!
+//! Ipv6Header::decode walks the extension headers and keeps the routing one, that is returned by get_routing_header.
+
+use crate::pkt_parser::{utils, DecodeError, DecodeErrorKind, Header};
+
+/// The value of the next header field announcing a routing header.
+pub const ROUTING_NEXT_HEADER: u8 = 43;
+
+/// The routing types whose data is a plain list of addresses.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoutingType {
+    /// The original source route, deprecated by RFC 5095.
+    SourceRoute,
+    /// The Mobile IPv6 routing header, carrying the home address of the mobile node.
+    MobileIpv6,
+    /// The RPL source route header (RFC 6554), whose addresses are compressed.
+    Rpl,
+    /// The Segment Routing header (RFC 8754).
+    SegmentRouting,
+    Other(u8),
+}
+
+impl From<u8> for RoutingType {
+    fn from(v: u8) -> Self {
+        match v {
+            0 => RoutingType::SourceRoute,
+            2 => RoutingType::MobileIpv6,
+            3 => RoutingType::Rpl,
+            4 => RoutingType::SegmentRouting,
+            v => RoutingType::Other(v)
+        }
+    }
+}
+
+/// describes an IPv6 Routing Header: the protocol following it, the routing type, how many of the listed addresses have
+/// still to be visited and the addresses themselves, when the routing type lists them uncompressed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6RoutingHeader {
+    next_header: u8,
+    routing_type: u8,
+    segments_left: u8,
+    addresses: Vec<String>,
+}
+
+impl Header for Ipv6RoutingHeader {
+    fn decode(data: Vec<u8>) -> (Result<Self, DecodeError>, Vec<u8>) {
+        let len = data.len();
+        if len < 8 { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode an ipv6 routing header because is not long enough.".to_string()}), data) }
+        // the length is given in 8 bytes units, not including the first 8 bytes
+        let header_len = (data[1] as usize + 1) * 8;
+        if len < header_len { return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode an ipv6 routing header because is not long enough.".to_string()}), data) }
+
+        let routing_type = data[2];
+        // the type specific data starts with 4 bytes (reserved, or the segment routing last entry, flags and tag)
+        let addresses = match RoutingType::from(routing_type) {
+            RoutingType::SourceRoute | RoutingType::MobileIpv6 | RoutingType::SegmentRouting => data[8..header_len]
+                .chunks_exact(16)
+                .map(utils::ipv6_address_to_string)
+                .collect(),
+            _ => Vec::new()
+        };
+        (
+            Ok(Ipv6RoutingHeader{next_header: data[0], routing_type, segments_left: data[3], addresses}),
+            Vec::from(&data[header_len..len])
+        )
+    }
+}
+
+impl Ipv6RoutingHeader {
+    /// The protocol number of the header following the routing one.
+    pub fn get_next_header(&self) -> u8 { self.next_header }
+    pub fn get_routing_type(&self) -> RoutingType { RoutingType::from(self.routing_type) }
+    /// The number of listed addresses that the packet has still to visit.
+    pub fn get_segments_left(&self) -> u8 { self.segments_left }
+    /// The addresses listed by the header, empty for the routing types that compress them or are unknown.
+    pub fn get_addresses(&self) -> Vec<String> { self.addresses.clone() }
+
+    /// Returns true for the deprecated type 0 routing header, that should be dropped by the hosts and routers.
+    pub fn is_deprecated(&self) -> bool { self.routing_type == 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pkt_parser::{Header, Ipv6Header, Protocol, UDPHeader};
+    use crate::pkt_parser::routing::{*};
+
+    #[test]
+    fn test_type_0_routing_header() {
+        let first = [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x0a];
+        let second = [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x0b];
+        // ipv6 header announcing a routing header, 8 + 32 + 8 bytes of payload
+        let mut data = vec![96, 0, 0, 0, 0, 48, ROUTING_NEXT_HEADER, 64];
+        data.extend_from_slice(&[0; 32]);
+        // routing header of type 0 followed by UDP, two addresses still to be visited
+        data.extend_from_slice(&[17, 4, 0, 2, 0, 0, 0, 0]);
+        data.extend_from_slice(&first);
+        data.extend_from_slice(&second);
+        data.extend_from_slice(&[19, 196, 0, 53, 0, 8, 0, 0]);
+
+        let (ipv6_header_result, ipv6_payload) = Ipv6Header::decode(data.clone());
+        let ipv6_header = ipv6_header_result.unwrap();
+        assert_eq!(ipv6_header.get_extension_headers(), vec![ROUTING_NEXT_HEADER]);
+        assert_eq!(ipv6_header.get_protocol(), Protocol::UDP);
+        let routing_header = ipv6_header.get_routing_header().unwrap();
+        assert_eq!(routing_header.get_routing_type(), RoutingType::SourceRoute);
+        assert!(routing_header.is_deprecated());
+        assert_eq!(routing_header.get_segments_left(), 2);
+        assert_eq!(routing_header.get_addresses(), vec!["2001:db8::a".to_string(), "2001:db8::b".to_string()]);
+        assert_eq!(routing_header.get_next_header(), 17);
+
+        let (udp_header_result, _udp_payload) = UDPHeader::decode(ipv6_payload);
+        assert_eq!(udp_header_result.unwrap().get_dest_port(), 53);
+
+        // the routing header alone, after the fixed header
+        let (routing_header_result, routing_payload) = Ipv6RoutingHeader::decode(data[40..].to_vec());
+        assert_eq!(routing_header_result.unwrap().get_addresses().len(), 2);
+        assert_eq!(routing_payload.len(), 8);
+    }
+
+    #[test]
+    fn test_truncated_routing_header() {
+        // the header announces 16 bytes of addresses, but only 8 are there
+        let (routing_header_result, data) = Ipv6RoutingHeader::decode(vec![17, 2, 0, 1, 0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(routing_header_result.is_err());
+        assert_eq!(data.len(), 16);
+
+        // the mobile ipv6 routing header is not deprecated
+        let mut data = vec![17, 2, 2, 1, 0, 0, 0, 0];
+        data.extend_from_slice(&[0; 16]);
+        let (routing_header_result, _payload) = Ipv6RoutingHeader::decode(data);
+        let routing_header = routing_header_result.unwrap();
+        assert_eq!(routing_header.get_routing_type(), RoutingType::MobileIpv6);
+        assert!(!routing_header.is_deprecated());
+        assert_eq!(routing_header.get_addresses().len(), 1);
+    }
+}
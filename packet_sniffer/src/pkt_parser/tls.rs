@@ -0,0 +1,113 @@
+//! tls
+//! This module reads the few clear text fields of a TLS connection worth reporting. The ClientHello, the first message
+//! sent by the client, carries the Server Name Indication extension (RFC 6066): the name of the host the client wants to
+//! reach, that tells which site a connection to port 443 is for.
+
+/// The content type of the records carrying handshake messages.
+const HANDSHAKE_CONTENT_TYPE: u8 = 22;
+/// The handshake type of the ClientHello.
+const CLIENT_HELLO: u8 = 1;
+/// The type of the Server Name Indication extension.
+const SERVER_NAME_EXTENSION: u16 = 0;
+
+/// A cursor over the bytes of a message, whose reads fail past its end.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len { return None }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(taken)
+    }
+
+    fn read_u8(&mut self) -> Option<u8> { self.take(1).map(|b| b[0]) }
+    fn read_u16(&mut self) -> Option<u16> { self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]])) }
+    fn read_u24(&mut self) -> Option<usize> { self.take(3).map(|b| u32::from_be_bytes([0, b[0], b[1], b[2]]) as usize) }
+
+    /// Reads a vector prefixed by its length, written in len_bytes bytes.
+    fn read_vector(&mut self, len_bytes: usize) -> Option<&'a [u8]> {
+        let len = match len_bytes {
+            1 => self.read_u8()? as usize,
+            2 => self.read_u16()? as usize,
+            _ => self.read_u24()?
+        };
+        self.take(len)
+    }
+}
+
+/// Returns the server name requested by the ClientHello at the beginning of payload, that is the first TCP segment sent
+/// by the client. It returns None if the payload is not a ClientHello, if it has no name or if it is truncated.
+pub fn client_hello_server_name(payload: &[u8]) -> Option<String> {
+    let mut record = Reader { data: payload };
+    if record.read_u8()? != HANDSHAKE_CONTENT_TYPE { return None }
+    let _version = record.read_u16()?;
+    let _record_len = record.read_u16()?;
+    if record.read_u8()? != CLIENT_HELLO { return None }
+    let _handshake_len = record.read_u24()?;
+    let _client_version = record.read_u16()?;
+    let _random = record.take(32)?;
+    let _session_id = record.read_vector(1)?;
+    let _cipher_suites = record.read_vector(2)?;
+    let _compression_methods = record.read_vector(1)?;
+
+    let mut extensions = Reader { data: record.read_vector(2)? };
+    while let Some(extension_type) = extensions.read_u16() {
+        let extension = extensions.read_vector(2)?;
+        if extension_type != SERVER_NAME_EXTENSION { continue }
+        let mut names = Reader { data: Reader { data: extension }.read_vector(2)? };
+        while let Some(name_type) = names.read_u8() {
+            let name = names.read_vector(2)?;
+            // the only name type defined is the host name
+            if name_type == 0 {
+                return String::from_utf8(name.to_vec()).ok()
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::pkt_parser::tls::{*};
+
+    /// Builds a ClientHello asking for the given server name.
+    pub(crate) fn client_hello(server_name: &str) -> Vec<u8> {
+        let name = server_name.as_bytes();
+        let mut extensions = vec![0, 0];
+        extensions.extend_from_slice(&((name.len() + 5) as u16).to_be_bytes());
+        extensions.extend_from_slice(&((name.len() + 3) as u16).to_be_bytes());
+        extensions.push(0);
+        extensions.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        extensions.extend_from_slice(name);
+        // supported versions, after the name
+        extensions.extend_from_slice(&[0, 43, 0, 3, 2, 3, 4]);
+
+        let mut hello = vec![3, 3];
+        hello.extend_from_slice(&[0x5a; 32]);
+        hello.extend_from_slice(&[0, 0, 2, 0x13, 0x01, 1, 0]);
+        hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        hello.extend_from_slice(&extensions);
+
+        let mut record = vec![HANDSHAKE_CONTENT_TYPE, 3, 1];
+        record.extend_from_slice(&((hello.len() + 4) as u16).to_be_bytes());
+        record.push(CLIENT_HELLO);
+        record.extend_from_slice(&(hello.len() as u32).to_be_bytes()[1..]);
+        record.extend_from_slice(&hello);
+        record
+    }
+
+    #[test]
+    fn test_client_hello_server_name() {
+        let hello = client_hello("example.com");
+        assert_eq!(client_hello_server_name(&hello), Some("example.com".to_string()));
+        // every truncation fails gracefully
+        for len in 0..hello.len() {
+            assert_eq!(client_hello_server_name(&hello[..len]), None);
+        }
+        // an application data record
+        assert_eq!(client_hello_server_name(&[23, 3, 3, 0, 1, 0]), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! trailer
+//! This module strips the hardware timestamp that some switches (e.g. Arista 7150) append at the end of the frames they
+//! forward to a monitoring port, just before the FCS. The timestamp is taken by the switch when the frame is received, so
+//! it is far more accurate than the capture time assigned by the host running the sniffer.
+//!
+//! Vendors use different formats and some NICs drop the FCS, so both the format and the position of the trailer are
+//! configurable.
+
+use crate::pkt_parser::{DecodeError, DecodeErrorKind, TimeValNanos};
+
+/// The encodings of the timestamp trailer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrailerFormat {
+    /// 32 bits of seconds followed by 32 bits of nanoseconds, both big endian (the Arista UTC format).
+    SecondsNanos,
+    /// 64 bits of nanoseconds since the epoch, big endian.
+    Nanos64,
+}
+
+impl TrailerFormat {
+    /// Returns the length of the trailer in bytes.
+    pub fn get_len(&self) -> usize {
+        match self {
+            TrailerFormat::SecondsNanos | TrailerFormat::Nanos64 => 8
+        }
+    }
+}
+
+/// describes where the timestamp trailer is placed in the frames and how it is encoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimestampTrailer {
+    format: TrailerFormat,
+    offset: usize,
+}
+
+impl TimestampTrailer {
+    /// Creates a trailer followed by the 4 bytes of the FCS.
+    pub fn new(format: TrailerFormat) -> Self {
+        TimestampTrailer { format, offset: 4 }
+    }
+
+    pub fn get_format(&self) -> TrailerFormat { self.format }
+    /// Returns how many bytes follow the trailer at the end of the frame.
+    pub fn get_offset(&self) -> usize { self.offset }
+    /// Sets how many bytes follow the trailer, 4 when the FCS has been kept and 0 when it has been dropped by the NIC.
+    pub fn set_offset(&mut self, offset: usize) { self.offset = offset }
+
+    /// Parses the timestamp at the end of the frame and returns it, together with the frame without the trailer
+    /// (the bytes following it, as the FCS, are kept). If the trailer cannot be found the frame is returned unchanged.
+    pub fn strip(&self, data: Vec<u8>) -> (Result<TimeValNanos, DecodeError>, Vec<u8>) {
+        let len = data.len();
+        let trailer_len = self.format.get_len();
+        // the trailer cannot overlap the ethernet header
+        if len < 14 + trailer_len + self.offset {
+            return (Err(DecodeError{kind: DecodeErrorKind::TooShort, msg: "Cannot decode the timestamp trailer because the frame is not long enough.".to_string()}), data)
+        }
+        let start = len - self.offset - trailer_len;
+        let trailer = &data[start..start + trailer_len];
+
+        let ts = match self.format {
+            TrailerFormat::SecondsNanos => {
+                let sec = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+                let n_sec = u32::from_be_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+                if n_sec >= 1_000_000_000 {
+                    return (Err(DecodeError{kind: DecodeErrorKind::Malformed, msg: format!("Invalid timestamp trailer, received {} nanoseconds", n_sec)}), data)
+                }
+                TimeValNanos::new(sec, n_sec)
+            },
+            TrailerFormat::Nanos64 => {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(trailer);
+                TimeValNanos::from_nanos(u64::from_be_bytes(bytes))
+            }
+        };
+        let mut frame = Vec::from(&data[..start]);
+        frame.extend_from_slice(&data[start + trailer_len..]);
+        (Ok(ts), frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pkt_parser::{EtherType, EthernetHeader, Header};
+    use crate::pkt_parser::trailer::{*};
+
+    fn frame() -> Vec<u8> {
+        vec![80, 235, 113, 35, 142, 103, 152, 0, 106, 4, 85, 32, 8, 0, 69, 0, 0, 20, 0, 0, 64, 0, 64, 17, 0, 0, 192, 168, 1, 1, 192, 168, 1, 21]
+    }
+
+    #[test]
+    fn test_arista_trailer() {
+        let mut data = frame();
+        // 1650000000.123456789, then the FCS
+        data.extend_from_slice(&[98, 89, 0, 128, 7, 91, 205, 21]);
+        data.extend_from_slice(&[222, 173, 190, 239]);
+
+        let (ts_result, stripped) = TimestampTrailer::new(TrailerFormat::SecondsNanos).strip(data);
+        let ts = ts_result.unwrap();
+        assert_eq!(ts.get_sec(), 1_650_000_000);
+        assert_eq!(ts.get_n_sec(), 123_456_789);
+        let mut expected = frame();
+        expected.extend_from_slice(&[222, 173, 190, 239]);
+        assert_eq!(stripped, expected);
+
+        let (ethernet_header_res, _payload) = EthernetHeader::decode(stripped);
+        assert_eq!(ethernet_header_res.unwrap().get_ether_type(), EtherType::Ipv4);
+    }
+
+    #[test]
+    fn test_trailer_without_fcs() {
+        let mut data = frame();
+        data.extend_from_slice(&1_650_000_000_123_456_789u64.to_be_bytes());
+
+        let mut trailer = TimestampTrailer::new(TrailerFormat::Nanos64);
+        trailer.set_offset(0);
+        let (ts_result, stripped) = trailer.strip(data);
+        assert_eq!(ts_result.unwrap(), TimeValNanos::new(1_650_000_000, 123_456_789));
+        assert_eq!(stripped, frame());
+
+        // a frame without a valid trailer is left untouched
+        let mut data = frame();
+        data.extend_from_slice(&[98, 89, 0, 128, 255, 255, 255, 255]);
+        let mut trailer = TimestampTrailer::new(TrailerFormat::SecondsNanos);
+        trailer.set_offset(0);
+        let (ts_result, stripped) = trailer.strip(data.clone());
+        assert!(ts_result.is_err());
+        assert_eq!(stripped, data);
+    }
+}
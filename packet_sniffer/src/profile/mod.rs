@@ -0,0 +1,189 @@
+//! profile
+//! This module keeps together everything needed to configure a sniffing, so that a capture can be repeated with the very
+//! same settings: the device, the BPF expression and the address filter selecting the packets, the size of the capture
+//! buffer, how the frames are parsed (the timestamp trailer to strip) and how the report is aggregated (the file and the
+//! interval between two saves).
+//!
+//! With the `serde` feature a CaptureProfile can be saved to and loaded from a JSON file.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use crate::filter::{AddressFilter, FilterError, Subnet};
+use crate::pkt_parser::trailer::TimestampTrailer;
+use crate::sniffer::{Sniffer, SnifferError, DEFAULT_BUFFER_SIZE};
+
+/// A custom error returned when a profile cannot be read, written or applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileError {
+    pub msg: String
+}
+
+impl Display for ProfileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Profile error: {}", self.msg)
+    }
+}
+
+impl From<SnifferError> for ProfileError {
+    fn from(error: SnifferError) -> Self { ProfileError { msg: error.to_string() } }
+}
+
+impl From<FilterError> for ProfileError {
+    fn from(error: FilterError) -> Self { ProfileError { msg: error.msg } }
+}
+
+/// describes the configuration of a sniffing. The subnets of the address filter are kept as text, e.g. "10.0.0.0/8".
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CaptureProfile {
+    device: Option<String>,
+    bpf_filter: Option<String>,
+    allow: Vec<String>,
+    deny: Vec<String>,
+    buffer_size: i32,
+    timestamp_trailer: Option<TimestampTrailer>,
+    report_file: Option<String>,
+    time_interval: u64,
+}
+
+impl Default for CaptureProfile {
+    fn default() -> Self {
+        CaptureProfile::new()
+    }
+}
+
+impl CaptureProfile {
+    /// Creates a profile without device and filters, using the default buffer size and saving the report only when asked.
+    pub fn new() -> Self {
+        CaptureProfile { device: None, bpf_filter: None, allow: Vec::new(), deny: Vec::new(), buffer_size: DEFAULT_BUFFER_SIZE,
+            timestamp_trailer: None, report_file: None, time_interval: 0 }
+    }
+
+    pub fn get_device(&self) -> Option<String> { self.device.clone() }
+    /// Sets the name of the device to sniff, as listed by Sniffer::list_devices.
+    pub fn set_device(&mut self, device: Option<String>) { self.device = device }
+    pub fn get_bpf_filter(&self) -> Option<String> { self.bpf_filter.clone() }
+    pub fn set_bpf_filter(&mut self, bpf_filter: Option<String>) { self.bpf_filter = bpf_filter }
+    pub fn get_buffer_size(&self) -> i32 { self.buffer_size }
+    pub fn set_buffer_size(&mut self, buffer_size: i32) { self.buffer_size = buffer_size }
+    /// Returns the timestamp trailer appended to the frames by the switch mirroring the traffic, if any.
+    pub fn get_timestamp_trailer(&self) -> Option<TimestampTrailer> { self.timestamp_trailer }
+    pub fn set_timestamp_trailer(&mut self, timestamp_trailer: Option<TimestampTrailer>) { self.timestamp_trailer = timestamp_trailer }
+    pub fn get_report_file(&self) -> Option<String> { self.report_file.clone() }
+    pub fn set_report_file(&mut self, report_file: Option<String>) { self.report_file = report_file }
+    /// Returns the seconds between two saves of the report, 0 if it is saved only when the sniffing stops.
+    pub fn get_time_interval(&self) -> u64 { self.time_interval }
+    pub fn set_time_interval(&mut self, time_interval: u64) { self.time_interval = time_interval }
+
+    /// Returns the address filter described by the profile.
+    pub fn get_address_filter(&self) -> Result<AddressFilter, FilterError> {
+        let mut filter = AddressFilter::new();
+        for subnet in &self.allow { filter.allow(subnet.parse::<Subnet>()?); }
+        for subnet in &self.deny { filter.deny(subnet.parse::<Subnet>()?); }
+        Ok(filter)
+    }
+
+    pub fn set_address_filter(&mut self, filter: &AddressFilter) {
+        self.allow = filter.get_allowed().iter().map(|subnet| subnet.to_string()).collect();
+        self.deny = filter.get_denied().iter().map(|subnet| subnet.to_string()).collect();
+    }
+
+    /// Configures the sniffer as described by the profile.
+    pub fn apply(&self, sniffer: &mut Sniffer) -> Result<(), ProfileError> {
+        if let Some(name) = &self.device {
+            let device = Sniffer::list_devices()?.into_iter().find(|device| &device.name == name)
+                .ok_or_else(|| ProfileError { msg: format!("The device {} does not exist", name) })?;
+            sniffer.attach(device)?;
+        }
+        sniffer.set_bpf_filter(self.bpf_filter.clone())?;
+        sniffer.set_address_filter(self.get_address_filter()?);
+        sniffer.set_buffer_size(self.buffer_size)?;
+        sniffer.set_timestamp_trailer(self.timestamp_trailer);
+        if let Some(report_file) = &self.report_file {
+            sniffer.set_file(report_file.clone())?;
+        }
+        sniffer.set_time_interval(self.time_interval);
+        Ok(())
+    }
+
+    /// Creates a sniffer configured as described by the profile.
+    pub fn build_sniffer(&self) -> Result<Sniffer, ProfileError> {
+        let mut sniffer = Sniffer::new();
+        self.apply(&mut sniffer)?;
+        Ok(sniffer)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, ProfileError> {
+        serde_json::to_string_pretty(self).map_err(|e| ProfileError { msg: e.to_string() })
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, ProfileError> {
+        serde_json::from_str(json).map_err(|e| ProfileError { msg: e.to_string() })
+    }
+
+    /// Saves the profile to the given file, as JSON.
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: &std::path::Path) -> Result<(), ProfileError> {
+        std::fs::write(path, self.to_json()?).map_err(|e| ProfileError { msg: e.to_string() })
+    }
+
+    /// Loads a profile saved by save.
+    #[cfg(feature = "serde")]
+    pub fn load(path: &std::path::Path) -> Result<Self, ProfileError> {
+        let json = std::fs::read_to_string(path).map_err(|e| ProfileError { msg: e.to_string() })?;
+        CaptureProfile::from_json(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::profile::{*};
+    use crate::pkt_parser::trailer::TrailerFormat;
+
+    fn profile() -> CaptureProfile {
+        let mut filter = AddressFilter::new();
+        filter.allow("192.168.1.0/24".parse().unwrap());
+        filter.deny("192.168.1.1".parse().unwrap());
+        let mut profile = CaptureProfile::new();
+        profile.set_address_filter(&filter);
+        profile.set_buffer_size(8 * 1024 * 1024);
+        let mut trailer = TimestampTrailer::new(TrailerFormat::Nanos64);
+        trailer.set_offset(0);
+        profile.set_timestamp_trailer(Some(trailer));
+        profile.set_time_interval(30);
+        profile
+    }
+
+    #[test]
+    fn test_build_sniffer() {
+        let profile = profile();
+        assert_eq!(profile.get_address_filter().unwrap().to_bpf(&[]), Some("(net 192.168.1.0/24) and not (host 192.168.1.1)".to_string()));
+        let sniffer = profile.build_sniffer().unwrap();
+        assert_eq!(sniffer.get_buffer_size(), 8 * 1024 * 1024);
+        assert_eq!(sniffer.get_timestamp_trailer().unwrap().get_format(), TrailerFormat::Nanos64);
+        assert_eq!(sniffer.get_time_interval(), 30);
+
+        let mut profile = CaptureProfile::new();
+        profile.set_buffer_size(0);
+        assert!(profile.build_sniffer().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_profile_round_trip() {
+        let profile = profile();
+        // the file name is unique, so that concurrent runs of the tests do not share it
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        let path = std::env::temp_dir().join(format!("packet_sniffer_profile_{}_{}.json", std::process::id(), nanos));
+        profile.save(&path).unwrap();
+        let loaded = CaptureProfile::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, profile);
+        assert_eq!(loaded.build_sniffer().unwrap().get_buffer_size(), 8 * 1024 * 1024);
+        assert_eq!(loaded.get_timestamp_trailer().unwrap().get_offset(), 0);
+
+        assert!(CaptureProfile::from_json("{\"device\": 3}").is_err());
+    }
+}
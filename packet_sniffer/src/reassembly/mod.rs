@@ -0,0 +1,497 @@
+//! reassembly
+//! This module rebuilds the datagrams that have been fragmented at the IP layer. The reassembler works on fragments
+//! described by their offset and payload, so it can be used both for IPv4 and IPv6 fragments.
+//!
+//! Fragmentation is also a well known way to evade an IDS, so the reassembler checks every fragment and raises an
+//! alert, instead of silently reassembling, when it finds:
+//! - overlapping fragments (the datagram is discarded)
+//! - fragments not aligned to 8 bytes that are not the last one (the datagram is discarded)
+//! - tiny fragments, too small to hold a transport header
+//! - fragments ending past the end of the datagram, given by the last fragment, and last fragments giving different ends
+//!   (the datagram is discarded)
+//!
+//! Datagrams that are never completed would keep their fragments in memory forever, so the buffered bytes are capped both
+//! in total and for every single datagram. When a cap is hit the oldest incomplete datagrams are evicted, and counted.
+//!
+//! The module also rebuilds the byte stream sent by an endpoint of a TCP connection (TcpStream): the segments are put back
+//! in order and the retransmitted bytes are delivered only once, so that the application messages can be read from it.
+
+use std::collections::HashMap;
+use crate::pkt_parser::{Ipv4Header, Protocol};
+
+/// Identifies the fragments belonging to the same original datagram.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FragmentKey {
+    src: String,
+    dest: String,
+    protocol: Protocol,
+    identification: u32,
+}
+
+impl FragmentKey {
+    pub fn new(src: String, dest: String, protocol: Protocol, identification: u32) -> Self {
+        FragmentKey { src, dest, protocol, identification }
+    }
+
+    pub fn get_src_address(&self) -> String { self.src.clone() }
+    pub fn get_dest_address(&self) -> String { self.dest.clone() }
+    pub fn get_protocol(&self) -> Protocol { self.protocol.clone() }
+    pub fn get_identification(&self) -> u32 { self.identification }
+}
+
+/// describes a single fragment: its offset in bytes inside the original payload, the more fragments flag and its data.
+#[derive(Debug, Clone)]
+pub struct Fragment {
+    key: FragmentKey,
+    offset: usize,
+    more_fragments: bool,
+    data: Vec<u8>,
+}
+
+impl Fragment {
+    pub fn new(key: FragmentKey, offset: usize, more_fragments: bool, data: Vec<u8>) -> Self {
+        Fragment { key, offset, more_fragments, data }
+    }
+
+    /// Describes the IPv4 packet as a fragment, given its header and the payload returned by Ipv4Header::decode. The
+    /// Ethernet padding following the packet is left out. It returns None if the packet is a whole datagram.
+    pub fn from_ipv4(header: &Ipv4Header, mut payload: Vec<u8>) -> Option<Self> {
+        if !header.is_fragment() { return None }
+        payload.truncate((header.get_total_length() as usize).saturating_sub(header.get_header_length()));
+        let key = FragmentKey::new(header.get_src_address(), header.get_dest_address(), header.get_protocol(), header.get_identification() as u32);
+        Some(Fragment::new(key, header.get_fragment_offset() as usize, header.is_more_fragments(), payload))
+    }
+
+    pub fn get_key(&self) -> FragmentKey { self.key.clone() }
+    pub fn get_offset(&self) -> usize { self.offset }
+    pub fn is_more_fragments(&self) -> bool { self.more_fragments }
+    pub fn get_data(&self) -> &[u8] { &self.data }
+}
+
+/// The suspicious fragmentation patterns reported by the reassembler.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FragmentAlert {
+    /// The fragment overlaps one already received, conflicting is true if the overlapping bytes differ.
+    Overlap { key: FragmentKey, offset: usize, conflicting: bool },
+    /// A fragment, other than the last one, smaller than the configured minimum size.
+    TinyFragment { key: FragmentKey, offset: usize, len: usize },
+    /// A fragment, other than the last one, whose length is not a multiple of 8 bytes.
+    UnalignedFragment { key: FragmentKey, offset: usize, len: usize },
+    /// A fragment ending past the end of the datagram, or a second last fragment giving a different end.
+    InconsistentLength { key: FragmentKey, offset: usize, len: usize },
+}
+
+#[derive(Debug, Clone, Default)]
+struct PendingDatagram {
+    fragments: Vec<(usize, Vec<u8>)>,
+    total_len: Option<usize>,
+    received: usize,
+    /// The order of arrival of the first fragment, used to find the oldest datagram.
+    sequence: u64,
+}
+
+impl PendingDatagram {
+    /// Looks for a fragment overlapping the given range, returning whether the shared bytes differ.
+    fn find_overlap(&self, offset: usize, data: &[u8]) -> Option<bool> {
+        let end = offset + data.len();
+        self.fragments.iter()
+            .find(|(o, d)| *o < end && offset < *o + d.len())
+            .map(|(o, d)| {
+                let start = usize::max(*o, offset);
+                let stop = usize::min(*o + d.len(), end);
+                d[start - o..stop - o] != data[start - offset..stop - offset]
+            })
+    }
+
+    /// Returns true if the fragment does not fit in the datagram: it ends past the end given by the last fragment or, if
+    /// it is the last one, another last fragment or a fragment already received end elsewhere.
+    fn is_inconsistent(&self, offset: usize, len: usize, more_fragments: bool) -> bool {
+        let end = offset + len;
+        match (self.total_len, more_fragments) {
+            (Some(total_len), true) => end > total_len,
+            (Some(total_len), false) => end != total_len,
+            (None, true) => false,
+            (None, false) => self.fragments.iter().any(|(o, d)| o + d.len() > end)
+        }
+    }
+
+    /// The datagram is complete when its fragments cover it from the first to the last byte, without holes.
+    fn is_complete(&self) -> bool {
+        let total_len = match self.total_len {
+            Some(total_len) => total_len,
+            None => return false
+        };
+        let mut ranges = self.fragments.iter().map(|(o, d)| (*o, o + d.len())).collect::<Vec<(usize, usize)>>();
+        ranges.sort();
+        let mut covered = 0;
+        for (start, end) in ranges {
+            if start > covered { return false }
+            covered = usize::max(covered, end);
+        }
+        covered == total_len
+    }
+
+    fn assemble(mut self) -> Vec<u8> {
+        self.fragments.sort_by_key(|(offset, _)| *offset);
+        self.fragments.into_iter().flat_map(|(_, data)| data).collect()
+    }
+}
+
+/// Collects the fragments and returns the original payload once all of them have been received.
+#[derive(Debug, Clone)]
+pub struct FragmentReassembler {
+    pending: HashMap<FragmentKey, PendingDatagram>,
+    alerts: Vec<FragmentAlert>,
+    min_fragment_size: usize,
+    max_buffered_bytes: usize,
+    max_datagram_bytes: usize,
+    buffered_bytes: usize,
+    next_sequence: u64,
+    evicted: usize,
+}
+
+impl Default for FragmentReassembler {
+    fn default() -> Self {
+        FragmentReassembler::new()
+    }
+}
+
+impl FragmentReassembler {
+    /// Creates a new reassembler, fragments smaller than 20 bytes (they cannot hold a TCP header) are considered tiny.
+    /// It buffers at most 4 MiB in total and 64 KiB, the largest IP datagram, for every datagram.
+    pub fn new() -> Self {
+        FragmentReassembler {
+            pending: HashMap::new(), alerts: Vec::new(), min_fragment_size: 20,
+            max_buffered_bytes: 4 * 1024 * 1024, max_datagram_bytes: 65535, buffered_bytes: 0, next_sequence: 0, evicted: 0
+        }
+    }
+
+    pub fn get_min_fragment_size(&self) -> usize { self.min_fragment_size }
+    pub fn set_min_fragment_size(&mut self, size: usize) { self.min_fragment_size = size }
+    pub fn get_max_buffered_bytes(&self) -> usize { self.max_buffered_bytes }
+    /// Sets the maximum number of bytes buffered for all the incomplete datagrams.
+    pub fn set_max_buffered_bytes(&mut self, bytes: usize) { self.max_buffered_bytes = bytes }
+    pub fn get_max_datagram_bytes(&self) -> usize { self.max_datagram_bytes }
+    /// Sets the maximum number of bytes buffered for a single datagram, a datagram growing over it is evicted.
+    pub fn set_max_datagram_bytes(&mut self, bytes: usize) { self.max_datagram_bytes = bytes }
+
+    /// Adds a fragment, returning the reassembled payload if it was the missing one.
+    /// Overlapping or misaligned fragments discard the whole datagram and raise an alert.
+    pub fn push(&mut self, fragment: Fragment) -> Option<Vec<u8>> {
+        let Fragment { key, offset, more_fragments, data } = fragment;
+        let len = data.len();
+
+        if more_fragments && len % 8 != 0 {
+            self.alerts.push(FragmentAlert::UnalignedFragment { key: key.clone(), offset, len });
+            self.remove(&key);
+            return None;
+        }
+        if more_fragments && len < self.min_fragment_size {
+            self.alerts.push(FragmentAlert::TinyFragment { key: key.clone(), offset, len });
+        }
+
+        let datagram_bytes = self.pending.get(&key).map(|p| p.received).unwrap_or(0);
+        if datagram_bytes + len > self.max_datagram_bytes {
+            if self.remove(&key).is_some() { self.evicted += 1 }
+            return None;
+        }
+        while self.buffered_bytes + len > self.max_buffered_bytes {
+            if !self.evict_oldest() { return None }
+        }
+
+        let sequence = self.next_sequence;
+        let pending = self.pending.entry(key.clone()).or_insert_with(|| PendingDatagram { sequence, ..Default::default() });
+        if pending.sequence == sequence { self.next_sequence += 1 }
+        if let Some(conflicting) = pending.find_overlap(offset, &data) {
+            self.alerts.push(FragmentAlert::Overlap { key: key.clone(), offset, conflicting });
+            self.remove(&key);
+            return None;
+        }
+        if pending.is_inconsistent(offset, len, more_fragments) {
+            self.alerts.push(FragmentAlert::InconsistentLength { key: key.clone(), offset, len });
+            self.remove(&key);
+            return None;
+        }
+        if !more_fragments {
+            pending.total_len = Some(offset + len);
+        }
+        pending.received += len;
+        pending.fragments.push((offset, data));
+        self.buffered_bytes += len;
+
+        if pending.is_complete() {
+            self.remove(&key).map(|p| p.assemble())
+        } else { None }
+    }
+
+    /// Removes a datagram, releasing its buffered bytes.
+    fn remove(&mut self, key: &FragmentKey) -> Option<PendingDatagram> {
+        let pending = self.pending.remove(key)?;
+        self.buffered_bytes -= pending.received;
+        Some(pending)
+    }
+
+    /// Evicts the incomplete datagram that started first, returning false if there is nothing to evict.
+    fn evict_oldest(&mut self) -> bool {
+        let oldest = self.pending.iter().min_by_key(|(_, p)| p.sequence).map(|(key, _)| key.clone());
+        match oldest {
+            Some(key) => {
+                self.remove(&key);
+                self.evicted += 1;
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Returns the number of datagrams still waiting for some fragments.
+    pub fn get_pending_count(&self) -> usize { self.pending.len() }
+
+    /// Returns the bytes currently buffered for the incomplete datagrams.
+    pub fn get_buffered_bytes(&self) -> usize { self.buffered_bytes }
+
+    /// Returns how many incomplete datagrams have been evicted because of the memory caps.
+    pub fn get_evicted_count(&self) -> usize { self.evicted }
+
+    pub fn get_alerts(&self) -> &[FragmentAlert] { &self.alerts }
+
+    /// Returns the alerts raised so far, removing them from the reassembler.
+    pub fn take_alerts(&mut self) -> Vec<FragmentAlert> { std::mem::take(&mut self.alerts) }
+}
+
+/// Rebuilds the bytes sent by one endpoint of a TCP connection. The segments arriving out of order are kept until the
+/// missing ones arrive, at most max_buffered_bytes of them: when the cap is hit they are dropped, and the stream has a gap
+/// that the receiver will eventually fill with the retransmissions.
+#[derive(Debug, Clone)]
+pub struct TcpStream {
+    next_seq: Option<u32>,
+    pending: Vec<(u32, Vec<u8>)>,
+    buffered_bytes: usize,
+    max_buffered_bytes: usize,
+    retransmitted_bytes: usize,
+}
+
+impl Default for TcpStream {
+    fn default() -> Self {
+        TcpStream::new()
+    }
+}
+
+impl TcpStream {
+    /// Creates a stream that buffers at most 1 MiB of out of order segments.
+    pub fn new() -> Self {
+        TcpStream { next_seq: None, pending: Vec::new(), buffered_bytes: 0, max_buffered_bytes: 1024 * 1024, retransmitted_bytes: 0 }
+    }
+
+    pub fn get_max_buffered_bytes(&self) -> usize { self.max_buffered_bytes }
+    pub fn set_max_buffered_bytes(&mut self, bytes: usize) { self.max_buffered_bytes = bytes }
+
+    /// Sequence numbers wrap around, so a is after b if the distance from b to a is less than half of the space.
+    fn is_after(a: u32, b: u32) -> bool {
+        (a.wrapping_sub(b) as i32) > 0
+    }
+
+    /// Adds a segment, given its sequence number and whether it carries the SYN flag, returning the bytes that are now in
+    /// order after the ones already returned. Without the SYN the stream starts from the first segment seen.
+    pub fn push(&mut self, seq: u32, syn: bool, data: &[u8]) -> Vec<u8> {
+        // the SYN takes up one sequence number
+        let seq = if syn { seq.wrapping_add(1) } else { seq };
+        let next_seq = *self.next_seq.get_or_insert(seq);
+        if data.is_empty() { return Vec::new() }
+        let end = seq.wrapping_add(data.len() as u32);
+        if !TcpStream::is_after(end, next_seq) {
+            self.retransmitted_bytes += data.len();
+            return Vec::new()
+        }
+        if TcpStream::is_after(seq, next_seq) {
+            if self.buffered_bytes + data.len() <= self.max_buffered_bytes {
+                self.buffered_bytes += data.len();
+                self.pending.push((seq, data.to_vec()));
+            }
+            return Vec::new()
+        }
+
+        let skipped = next_seq.wrapping_sub(seq) as usize;
+        self.retransmitted_bytes += skipped;
+        let mut stream = data[skipped..].to_vec();
+        let mut next_seq = end;
+        // the buffered segments that now follow the stream are appended, the ones it already covers are dropped
+        while let Some(index) = self.pending.iter().position(|(seq, _)| !TcpStream::is_after(*seq, next_seq)) {
+            let (seq, data) = self.pending.swap_remove(index);
+            self.buffered_bytes -= data.len();
+            let end = seq.wrapping_add(data.len() as u32);
+            let skipped = next_seq.wrapping_sub(seq) as usize;
+            if TcpStream::is_after(end, next_seq) {
+                self.retransmitted_bytes += skipped;
+                stream.extend_from_slice(&data[skipped..]);
+                next_seq = end;
+            } else { self.retransmitted_bytes += data.len() }
+        }
+        self.next_seq = Some(next_seq);
+        stream
+    }
+
+    /// Returns the bytes of out of order segments waiting for the missing ones.
+    pub fn get_buffered_bytes(&self) -> usize { self.buffered_bytes }
+
+    /// Returns the bytes received more than once, that have been delivered only the first time.
+    pub fn get_retransmitted_bytes(&self) -> usize { self.retransmitted_bytes }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reassembly::{*};
+    use crate::pkt_parser::Header;
+
+    fn key() -> FragmentKey {
+        FragmentKey::new("192.168.1.21".to_string(), "192.168.1.1".to_string(), Protocol::UDP, 0xaa0a)
+    }
+
+    #[test]
+    fn test_reassembly_out_of_order() {
+        let mut reassembler = FragmentReassembler::new();
+        assert_eq!(reassembler.push(Fragment::new(key(), 24, false, vec![3; 10])), None);
+        assert_eq!(reassembler.get_pending_count(), 1);
+        let payload = reassembler.push(Fragment::new(key(), 0, true, vec![1; 24])).unwrap();
+        assert_eq!(payload.len(), 34);
+        assert_eq!(&payload[20..28], &[1, 1, 1, 1, 3, 3, 3, 3]);
+        assert_eq!(reassembler.get_pending_count(), 0);
+        assert!(reassembler.get_alerts().is_empty());
+
+        // a duplicated fragment overlaps with the same data
+        assert_eq!(reassembler.push(Fragment::new(key(), 0, true, vec![1; 24])), None);
+        assert_eq!(reassembler.push(Fragment::new(key(), 0, true, vec![1; 24])), None);
+        assert_eq!(reassembler.take_alerts(), vec![FragmentAlert::Overlap { key: key(), offset: 0, conflicting: false }]);
+        assert!(reassembler.get_alerts().is_empty());
+    }
+
+    #[test]
+    fn test_ipv4_fragments() {
+        // a 12 bytes UDP datagram in two fragments, the second one padded to the minimum Ethernet frame
+        let first = vec![69, 0, 0, 28, 170, 10, 32, 0, 64, 17, 0, 0, 192, 168, 1, 21, 192, 168, 1, 1, 19, 196, 0, 53, 0, 12, 0, 0];
+        let mut second = vec![69, 0, 0, 24, 170, 10, 0, 1, 64, 17, 0, 0, 192, 168, 1, 21, 192, 168, 1, 1, 1, 2, 3, 4];
+        second.extend_from_slice(&[0; 22]);
+
+        let mut reassembler = FragmentReassembler::new();
+        reassembler.set_min_fragment_size(0);
+        let mut datagram = None;
+        for packet in [first, second] {
+            let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode(packet);
+            let fragment = Fragment::from_ipv4(&ipv4_header_result.unwrap(), ipv4_payload).unwrap();
+            assert_eq!(fragment.get_key(), key());
+            datagram = reassembler.push(fragment);
+        }
+        assert_eq!(datagram, Some(vec![19, 196, 0, 53, 0, 12, 0, 0, 1, 2, 3, 4]));
+
+        // a whole datagram
+        let (ipv4_header_result, ipv4_payload) = Ipv4Header::decode(vec![69, 0, 0, 20, 134, 79, 64, 0, 128, 17, 0, 0, 192, 168, 1, 21, 192, 168, 1, 1]);
+        assert!(Fragment::from_ipv4(&ipv4_header_result.unwrap(), ipv4_payload).is_none());
+    }
+
+    #[test]
+    fn test_overlapping_fragments_are_flagged() {
+        let mut reassembler = FragmentReassembler::new();
+        assert_eq!(reassembler.push(Fragment::new(key(), 0, true, vec![0x41; 32])), None);
+        // the second fragment rewrites the last 8 bytes of the first one with different data
+        assert_eq!(reassembler.push(Fragment::new(key(), 24, false, vec![0x42; 16])), None);
+
+        assert_eq!(reassembler.get_alerts(), &[FragmentAlert::Overlap { key: key(), offset: 24, conflicting: true }]);
+        assert_eq!(reassembler.get_pending_count(), 0);
+    }
+
+    #[test]
+    fn test_reassembly_waits_for_the_holes() {
+        let mut reassembler = FragmentReassembler::new();
+        reassembler.set_min_fragment_size(0);
+        assert_eq!(reassembler.push(Fragment::new(key(), 0, true, vec![1; 8])), None);
+        assert_eq!(reassembler.push(Fragment::new(key(), 24, false, vec![4; 10])), None);
+        assert_eq!(reassembler.push(Fragment::new(key(), 16, true, vec![3; 8])), None);
+        // the bytes from 8 to 16 are still missing
+        assert_eq!(reassembler.get_pending_count(), 1);
+        let payload = reassembler.push(Fragment::new(key(), 8, true, vec![2; 8])).unwrap();
+        assert_eq!(payload.len(), 34);
+        assert_eq!(&payload[6..18], &[1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3]);
+        assert!(reassembler.get_alerts().is_empty());
+    }
+
+    #[test]
+    fn test_fragment_past_the_end_is_flagged() {
+        let mut reassembler = FragmentReassembler::new();
+        // the received bytes add up to the length of the datagram, but the second fragment lies past its end
+        assert_eq!(reassembler.push(Fragment::new(key(), 24, false, vec![3; 10])), None);
+        assert_eq!(reassembler.push(Fragment::new(key(), 40, true, vec![1; 24])), None);
+        assert_eq!(reassembler.take_alerts(), vec![FragmentAlert::InconsistentLength { key: key(), offset: 40, len: 24 }]);
+        assert_eq!(reassembler.get_pending_count(), 0);
+        assert_eq!(reassembler.get_buffered_bytes(), 0);
+
+        // the same fragments in the other order
+        assert_eq!(reassembler.push(Fragment::new(key(), 40, true, vec![1; 24])), None);
+        assert_eq!(reassembler.push(Fragment::new(key(), 24, false, vec![3; 10])), None);
+        assert_eq!(reassembler.take_alerts(), vec![FragmentAlert::InconsistentLength { key: key(), offset: 24, len: 10 }]);
+        assert_eq!(reassembler.get_pending_count(), 0);
+    }
+
+    #[test]
+    fn test_conflicting_last_fragment_is_flagged() {
+        let mut reassembler = FragmentReassembler::new();
+        assert_eq!(reassembler.push(Fragment::new(key(), 24, false, vec![3; 10])), None);
+        // a second last fragment moving the end of the datagram
+        assert_eq!(reassembler.push(Fragment::new(key(), 40, false, vec![5; 8])), None);
+        assert_eq!(reassembler.get_alerts(), &[FragmentAlert::InconsistentLength { key: key(), offset: 40, len: 8 }]);
+        assert_eq!(reassembler.get_pending_count(), 0);
+        // the first fragment alone does not complete the datagram
+        assert_eq!(reassembler.push(Fragment::new(key(), 0, true, vec![1; 24])), None);
+    }
+
+    #[test]
+    fn test_memory_caps_evict_oldest() {
+        let mut reassembler = FragmentReassembler::new();
+        reassembler.set_max_buffered_bytes(1024);
+        reassembler.set_max_datagram_bytes(512);
+        let key = |id| FragmentKey::new("192.168.1.21".to_string(), "192.168.1.1".to_string(), Protocol::UDP, id);
+
+        // datagrams that are never completed
+        for id in 0..20 {
+            assert_eq!(reassembler.push(Fragment::new(key(id), 0, true, vec![0; 256])), None);
+            assert!(reassembler.get_buffered_bytes() <= 1024);
+        }
+        assert_eq!(reassembler.get_pending_count(), 4);
+        assert_eq!(reassembler.get_evicted_count(), 16);
+        assert_eq!(reassembler.push(Fragment::new(key(0), 256, false, vec![0; 8])), None);
+        // the newest datagrams are still there
+        assert_eq!(reassembler.push(Fragment::new(key(19), 256, false, vec![0; 8])).unwrap().len(), 264);
+
+        // a single datagram cannot grow over its own cap
+        assert_eq!(reassembler.push(Fragment::new(key(18), 256, true, vec![0; 256])), None);
+        assert_eq!(reassembler.push(Fragment::new(key(18), 512, false, vec![0; 8])), None);
+        assert_eq!(reassembler.get_evicted_count(), 18);
+    }
+
+    #[test]
+    fn test_tiny_and_unaligned_fragments_are_flagged() {
+        let mut reassembler = FragmentReassembler::new();
+        assert_eq!(reassembler.push(Fragment::new(key(), 0, true, vec![0; 8])), None);
+        assert_eq!(reassembler.push(Fragment::new(key(), 8, true, vec![0; 13])), None);
+
+        assert_eq!(reassembler.get_alerts(), &[
+            FragmentAlert::TinyFragment { key: key(), offset: 0, len: 8 },
+            FragmentAlert::UnalignedFragment { key: key(), offset: 8, len: 13 },
+        ]);
+    }
+
+    #[test]
+    fn test_tcp_stream() {
+        let mut stream = TcpStream::new();
+        assert!(stream.push(1000, true, &[]).is_empty());
+        assert_eq!(stream.push(1001, false, b"GET / "), b"GET / ");
+        // out of order, then the missing segment and a retransmission overlapping it
+        assert!(stream.push(1012, false, b"1.1\r\n").is_empty());
+        assert_eq!(stream.get_buffered_bytes(), 5);
+        assert_eq!(stream.push(1007, false, b"HTTP/"), b"HTTP/1.1\r\n");
+        assert!(stream.push(1001, false, b"GET / HTTP/").is_empty());
+        assert_eq!(stream.push(1015, false, b"\r\nHost"), b"Host");
+
+        assert_eq!(stream.get_buffered_bytes(), 0);
+        assert_eq!(stream.get_retransmitted_bytes(), 11 + 2);
+    }
+}
@@ -0,0 +1,125 @@
+//! report
+//! This module aggregates the captured packets into a traffic report, the core of the summary written by the sniffer.
+//!
+//! The packets are grouped by connection (see ConnectionKey), and for every connection the report keeps:
+//! - the bytes transmitted and the number of packets
+//! - the timestamp of the first and of the last packet seen
+//!
+//! With the `serde` feature the report can be written as JSON.
+
+use std::collections::HashMap;
+use crate::flow::ConnectionKey;
+use crate::pkt_parser::{PacketInfo, TimeVal};
+
+/// describes the traffic of a single connection in a TrafficReport.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectionStats {
+    bytes: usize,
+    packets: usize,
+    first_seen: TimeVal,
+    last_seen: TimeVal,
+}
+
+impl ConnectionStats {
+    fn new(info: &PacketInfo) -> Self {
+        ConnectionStats { bytes: info.get_byte_transmitted(), packets: 1, first_seen: info.get_time_stamp(), last_seen: info.get_time_stamp() }
+    }
+
+    /// Accounts a packet of the connection, the packets may be added out of order.
+    fn update(&mut self, info: &PacketInfo) {
+        self.bytes += info.get_byte_transmitted();
+        self.packets += 1;
+        let ts = info.get_time_stamp();
+        if ts < self.first_seen { self.first_seen = ts.clone() }
+        if ts > self.last_seen { self.last_seen = ts }
+    }
+
+    pub fn get_bytes(&self) -> usize { self.bytes }
+    pub fn get_packets(&self) -> usize { self.packets }
+    pub fn get_first_seen(&self) -> TimeVal { self.first_seen.clone() }
+    pub fn get_last_seen(&self) -> TimeVal { self.last_seen.clone() }
+}
+
+/// Aggregates a stream of packets by connection.
+#[derive(Debug, Clone, Default)]
+pub struct TrafficReport {
+    connections: HashMap<ConnectionKey, ConnectionStats>,
+}
+
+impl TrafficReport {
+    pub fn new() -> Self {
+        TrafficReport::default()
+    }
+
+    pub fn add(&mut self, info: PacketInfo) {
+        self.connections.entry(info.get_connection_key())
+            .and_modify(|stats| stats.update(&info))
+            .or_insert_with(|| ConnectionStats::new(&info));
+    }
+
+    pub fn get(&self, key: &ConnectionKey) -> Option<&ConnectionStats> { self.connections.get(key) }
+
+    /// Iterates over the connections of the report, in no particular order.
+    pub fn entries(&self) -> impl Iterator<Item = (&ConnectionKey, &ConnectionStats)> { self.connections.iter() }
+
+    /// Renders the report as a JSON array with an object per connection, holding its key and its stats.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        #[derive(serde::Serialize)]
+        struct Entry<'a> {
+            connection: &'a ConnectionKey,
+            stats: &'a ConnectionStats,
+        }
+        let entries: Vec<Entry> = self.entries().map(|(connection, stats)| Entry { connection, stats }).collect();
+        serde_json::to_string(&entries)
+    }
+
+    pub fn len(&self) -> usize { self.connections.len() }
+    pub fn is_empty(&self) -> bool { self.connections.is_empty() }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::report::{*};
+    use crate::pkt_parser::Protocol;
+
+    #[test]
+    fn test_traffic_report() {
+        let mut report = TrafficReport::new();
+        report.add(PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 517, TimeVal::from(1_000_000)));
+        report.add(PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(1_010_000)));
+        report.add(PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 1400, TimeVal::from(1_020_000)));
+        assert_eq!(report.len(), 2);
+
+        let tcp = report.get(&ConnectionKey::new("149.154.167.92".to_string(), 443, Protocol::TCP)).unwrap();
+        assert_eq!((tcp.get_bytes(), tcp.get_packets()), (1917, 2));
+        assert_eq!((tcp.get_first_seen(), tcp.get_last_seen()), (TimeVal::from(1_000_000), TimeVal::from(1_020_000)));
+        let udp = report.get(&ConnectionKey::new("192.168.1.1".to_string(), 53, Protocol::UDP)).unwrap();
+        assert_eq!((udp.get_bytes(), udp.get_packets()), (110, 1));
+        assert_eq!((udp.get_first_seen(), udp.get_last_seen()), (TimeVal::from(1_010_000), TimeVal::from(1_010_000)));
+        assert_eq!(report.entries().map(|(_, stats)| stats.get_packets()).sum::<usize>(), 3);
+
+        // a late packet does not move the last timestamp back
+        report.add(PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 90, TimeVal::from(1_005_000)));
+        let udp = report.get(&ConnectionKey::new("192.168.1.1".to_string(), 53, Protocol::UDP)).unwrap();
+        assert_eq!((udp.get_first_seen(), udp.get_last_seen()), (TimeVal::from(1_005_000), TimeVal::from(1_010_000)));
+
+        // a timestamp whose microseconds exceed a second is later than it looks
+        report.add(PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 90, TimeVal { sec: 0, u_sec: 1_020_000 }));
+        let udp = report.get(&ConnectionKey::new("192.168.1.1".to_string(), 53, Protocol::UDP)).unwrap();
+        assert_eq!(udp.get_last_seen(), TimeVal::from(1_020_000));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_report_json() {
+        let mut report = TrafficReport::new();
+        report.add(PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(1_010_000)));
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json[0]["connection"]["address"], "192.168.1.1");
+        assert_eq!(json[0]["connection"]["protocol"], "UDP");
+        assert_eq!(json[0]["stats"]["bytes"], 110);
+        assert_eq!(json[0]["stats"]["packets"], 1);
+    }
+}
@@ -0,0 +1,199 @@
+//! resolve
+//! This module, enabled by the `reverse-dns` feature, translates the addresses of the remote hosts into names through
+//! reverse DNS (PTR) lookups, so that reports can show hostnames instead of bare IPs.
+//!
+//! Lookups can be slow, so they never run on the capture path: the ReverseDnsCache answers immediately with what it
+//! already knows (falling back to the address itself) and resolves the unknown addresses in background threads.
+
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+use crate::flow::HostStats;
+
+/// The PtrResolver trait define how a single address is translated into a name. It returns None if the address has no name.
+pub trait PtrResolver: Send + Sync {
+    fn lookup(&self, address: &IpAddr) -> Option<String>;
+}
+
+/// A resolver backed by the resolver of the operating system (getnameinfo).
+#[cfg(unix)]
+#[derive(Debug, Clone, Default)]
+pub struct SystemResolver;
+
+#[cfg(unix)]
+impl PtrResolver for SystemResolver {
+    fn lookup(&self, address: &IpAddr) -> Option<String> {
+        use std::ffi::CStr;
+        use std::mem;
+
+        let mut host = [0 as libc::c_char; 1025];
+        // SAFETY: the socket addresses are fully initialized before being passed, and the host buffer length is the real one.
+        let result = unsafe {
+            match address {
+                IpAddr::V4(v4) => {
+                    let mut socket_address: libc::sockaddr_in = mem::zeroed();
+                    socket_address.sin_family = libc::AF_INET as libc::sa_family_t;
+                    socket_address.sin_addr = libc::in_addr { s_addr: u32::from_ne_bytes(v4.octets()) };
+                    libc::getnameinfo(&socket_address as *const libc::sockaddr_in as *const libc::sockaddr,
+                                      mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+                                      host.as_mut_ptr(), host.len() as libc::socklen_t,
+                                      std::ptr::null_mut(), 0, libc::NI_NAMEREQD)
+                },
+                IpAddr::V6(v6) => {
+                    let mut socket_address: libc::sockaddr_in6 = mem::zeroed();
+                    socket_address.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                    socket_address.sin6_addr = libc::in6_addr { s6_addr: v6.octets() };
+                    libc::getnameinfo(&socket_address as *const libc::sockaddr_in6 as *const libc::sockaddr,
+                                      mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t,
+                                      host.as_mut_ptr(), host.len() as libc::socklen_t,
+                                      std::ptr::null_mut(), 0, libc::NI_NAMEREQD)
+                }
+            }
+        };
+        if result != 0 { return None }
+        // SAFETY: getnameinfo succeeded, so the buffer holds a nul terminated string.
+        let name = unsafe { CStr::from_ptr(host.as_ptr()) };
+        name.to_str().ok().map(|n| n.to_string())
+    }
+}
+
+/// A cache of PTR lookups, shared between the threads that resolve the addresses and the ones that read the names.
+pub struct ReverseDnsCache {
+    resolver: Arc<dyn PtrResolver>,
+    timeout: Duration,
+    names: Arc<Mutex<HashMap<IpAddr, Option<String>>>>,
+    pending: Arc<Mutex<HashSet<IpAddr>>>,
+}
+
+impl ReverseDnsCache {
+    /// Creates a new cache using the given resolver, lookups have a default timeout of 2 seconds.
+    pub fn new<R: PtrResolver + 'static>(resolver: R) -> Self {
+        ReverseDnsCache { resolver: Arc::new(resolver), timeout: Duration::from_secs(2),
+            names: Arc::new(Mutex::new(HashMap::new())), pending: Arc::new(Mutex::new(HashSet::new())) }
+    }
+
+    ///Returns the maximum time waited for a single lookup.
+    pub fn get_timeout(&self) -> Duration { self.timeout }
+
+    ///Sets the maximum time waited for a single lookup, after it the address is considered without a name.
+    pub fn set_timeout(&mut self, timeout: Duration) { self.timeout = timeout }
+
+    /// Returns the name of the address if it is already known, without blocking. Unknown addresses are scheduled for a
+    /// background lookup, and the address itself is returned in the meantime (and also when the resolution fails).
+    pub fn get_hostname(&self, address: &str) -> String {
+        let ip = match address.parse::<IpAddr>() {
+            Ok(ip) => ip,
+            Err(_) => return address.to_string()
+        };
+        if let Some(name) = self.names.lock().unwrap().get(&ip) {
+            return name.clone().unwrap_or_else(|| address.to_string());
+        }
+        if self.pending.lock().unwrap().insert(ip) {
+            let resolver = self.resolver.clone();
+            let timeout = self.timeout;
+            let names = self.names.clone();
+            let pending = self.pending.clone();
+            thread::spawn(move || {
+                let name = lookup_with_timeout(resolver, ip, timeout);
+                names.lock().unwrap().insert(ip, name);
+                pending.lock().unwrap().remove(&ip);
+            });
+        }
+        address.to_string()
+    }
+
+    /// Returns the name of the address, waiting for the lookup (at most for the timeout) if it is not cached yet.
+    pub fn resolve(&self, address: &str) -> String {
+        let ip = match address.parse::<IpAddr>() {
+            Ok(ip) => ip,
+            Err(_) => return address.to_string()
+        };
+        let cached = self.names.lock().unwrap().get(&ip).cloned();
+        let name = match cached {
+            Some(name) => name,
+            None => {
+                let name = lookup_with_timeout(self.resolver.clone(), ip, self.timeout);
+                self.names.lock().unwrap().insert(ip, name.clone());
+                name
+            }
+        };
+        name.unwrap_or_else(|| address.to_string())
+    }
+
+    /// Attaches to every host the name known so far, without blocking: unresolved hosts keep their address and will
+    /// get their name in a later call.
+    pub fn annotate(&self, hosts: &mut [HostStats]) {
+        for host in hosts.iter_mut() {
+            let name = self.get_hostname(&host.get_address());
+            if name != host.get_address() {
+                host.set_hostname(name);
+            }
+        }
+    }
+}
+
+/// Runs the lookup in its own thread, so that a resolver ignoring the timeout cannot block the caller.
+fn lookup_with_timeout(resolver: Arc<dyn PtrResolver>, ip: IpAddr, timeout: Duration) -> Option<String> {
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        let _ = tx.send(resolver.lookup(&ip));
+    });
+    rx.recv_timeout(timeout).ok().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resolve::{*};
+    use crate::flow::FlowTable;
+    use crate::pkt_parser::{PacketInfo, Protocol, TimeVal};
+
+    struct StubResolver;
+
+    impl PtrResolver for StubResolver {
+        fn lookup(&self, address: &IpAddr) -> Option<String> {
+            match address.to_string().as_str() {
+                "192.168.1.1" => Some("router.home".to_string()),
+                "10.0.0.1" => {
+                    thread::sleep(Duration::from_secs(5));
+                    Some("slow.home".to_string())
+                },
+                _ => None
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_with_stub_resolver() {
+        let mut cache = ReverseDnsCache::new(StubResolver);
+        cache.set_timeout(Duration::from_millis(200));
+        assert_eq!(cache.resolve("192.168.1.1"), "router.home".to_string());
+        // no name and timed out lookups fall back to the address
+        assert_eq!(cache.resolve("192.168.1.21"), "192.168.1.21".to_string());
+        assert_eq!(cache.resolve("10.0.0.1"), "10.0.0.1".to_string());
+    }
+
+    #[test]
+    fn test_annotate_hosts() {
+        let mut table = FlowTable::new();
+        table.update(&PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(1_000_000)));
+        table.update(&PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 40, TimeVal::from(1_000_200)));
+        let cache = ReverseDnsCache::new(StubResolver);
+
+        // the first call does not block, so the names are not known yet
+        let mut hosts = table.hosts();
+        cache.annotate(&mut hosts);
+        assert_eq!(hosts[1].get_hostname(), "192.168.1.1".to_string());
+
+        let mut attempts = 0;
+        while cache.get_hostname("192.168.1.1") == "192.168.1.1" && attempts < 100 {
+            thread::sleep(Duration::from_millis(10));
+            attempts += 1;
+        }
+        cache.annotate(&mut hosts);
+        assert_eq!(hosts[1].get_hostname(), "router.home".to_string());
+        assert_eq!(hosts[0].get_hostname(), "149.154.167.92".to_string());
+    }
+}
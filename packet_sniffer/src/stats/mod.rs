@@ -0,0 +1,282 @@
+//! stats
+//! This module contains some collectors that summarize the traffic seen during a sniffing in a set of counters.
+//!
+//! The collectors available are:
+//! - PriorityBreakdown: traffic by 802.1Q Priority Code Point, to verify QoS marking
+//! - FrameSizeHistogram: frames by size, as the RMON counters of NICs and switches, telling jumbo frames apart
+//! - CaptureMetrics: the overall counters of a sniffing, rendered in the Prometheus text format to be scraped
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use crate::pkt_parser::{DecodeError, DecodeErrorKind, EthernetHeader, PacketInfo};
+
+/// Packets and bytes counted for a single class of traffic.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TrafficCounter {
+    packets: usize,
+    bytes: usize,
+}
+
+impl TrafficCounter {
+    pub(crate) fn add(&mut self, bytes: usize) {
+        self.packets += 1;
+        self.bytes += bytes;
+    }
+
+    pub fn get_packets(&self) -> usize { self.packets }
+    pub fn get_bytes(&self) -> usize { self.bytes }
+}
+
+/// Breaks down the traffic by the 8 priority classes of the VLAN tag. Untagged frames are counted on their own.
+#[derive(Debug, Clone, Default)]
+pub struct PriorityBreakdown {
+    classes: [TrafficCounter; 8],
+    untagged: TrafficCounter,
+}
+
+impl PriorityBreakdown {
+    pub fn new() -> Self {
+        PriorityBreakdown::default()
+    }
+
+    /// Accounts a frame, whose total length is frame_len, to its priority class.
+    pub fn update(&mut self, header: &EthernetHeader, frame_len: usize) {
+        match header.get_vlan_tag() {
+            Some(tag) => self.classes[tag.priority() as usize].add(frame_len),
+            None => self.untagged.add(frame_len)
+        }
+    }
+
+    /// Returns the counter of the given priority class (0-7).
+    pub fn get_class(&self, priority: u8) -> Option<TrafficCounter> { self.classes.get(priority as usize).copied() }
+    pub fn get_untagged(&self) -> TrafficCounter { self.untagged }
+}
+
+/// The size classes of the frames, the ones of RMON (RFC 2819) followed by the frames over the standard maximum size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameSizeBucket {
+    UpTo64,
+    From65To127,
+    From128To255,
+    From256To511,
+    From512To1023,
+    From1024To1518,
+    /// Frames over the standard size but not jumbo, such as the VLAN tagged ones and the "baby giants" of tunnels.
+    From1519To2047,
+    Jumbo,
+}
+
+impl FrameSizeBucket {
+    /// All the buckets, from the smallest frames to the biggest.
+    pub const ALL: [FrameSizeBucket; 8] = [
+        FrameSizeBucket::UpTo64, FrameSizeBucket::From65To127, FrameSizeBucket::From128To255, FrameSizeBucket::From256To511,
+        FrameSizeBucket::From512To1023, FrameSizeBucket::From1024To1518, FrameSizeBucket::From1519To2047, FrameSizeBucket::Jumbo,
+    ];
+
+    pub fn from_len(frame_len: usize) -> Self {
+        match frame_len {
+            0..=64 => FrameSizeBucket::UpTo64,
+            65..=127 => FrameSizeBucket::From65To127,
+            128..=255 => FrameSizeBucket::From128To255,
+            256..=511 => FrameSizeBucket::From256To511,
+            512..=1023 => FrameSizeBucket::From512To1023,
+            1024..=1518 => FrameSizeBucket::From1024To1518,
+            1519..=2047 => FrameSizeBucket::From1519To2047,
+            _ => FrameSizeBucket::Jumbo
+        }
+    }
+
+    /// Returns true for the frames bigger than the standard Ethernet maximum size of 1518 bytes.
+    pub fn is_oversize(&self) -> bool {
+        matches!(self, FrameSizeBucket::From1519To2047 | FrameSizeBucket::Jumbo)
+    }
+}
+
+/// Counts the frames by size, to get their distribution. The length of the whole frame is used, as captured.
+#[derive(Debug, Clone, Default)]
+pub struct FrameSizeHistogram {
+    buckets: [TrafficCounter; 8],
+}
+
+impl FrameSizeHistogram {
+    pub fn new() -> Self {
+        FrameSizeHistogram::default()
+    }
+
+    pub fn update(&mut self, frame_len: usize) {
+        let bucket = FrameSizeBucket::from_len(frame_len);
+        self.buckets[bucket as usize].add(frame_len);
+    }
+
+    pub fn get_bucket(&self, bucket: FrameSizeBucket) -> TrafficCounter { self.buckets[bucket as usize] }
+
+    /// Returns the counters of all the buckets, in the order of FrameSizeBucket::ALL.
+    pub fn iter(&self) -> impl Iterator<Item = (FrameSizeBucket, TrafficCounter)> + '_ {
+        FrameSizeBucket::ALL.iter().map(move |bucket| (*bucket, self.get_bucket(*bucket)))
+    }
+
+    /// Returns the frames bigger than 1518 bytes, that are jumbo frames or frames over the standard size.
+    pub fn get_oversize(&self) -> TrafficCounter {
+        let mut total = TrafficCounter::default();
+        for (_, counter) in self.iter().filter(|(bucket, _)| bucket.is_oversize()) {
+            total.packets += counter.packets;
+            total.bytes += counter.bytes;
+        }
+        total
+    }
+}
+
+/// The overall counters of a sniffing: the packets and bytes decoded, by protocol, the packets that could not be decoded
+/// and the ones that have been dropped, by the filters or before reaching the sniffer.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureMetrics {
+    totals: TrafficCounter,
+    protocols: BTreeMap<String, TrafficCounter>,
+    decode_errors: BTreeMap<String, usize>,
+    filtered: usize,
+    kernel_dropped: u32,
+    interface_dropped: u32,
+    connections: usize,
+}
+
+impl CaptureMetrics {
+    pub fn new() -> Self {
+        CaptureMetrics::default()
+    }
+
+    /// Accounts a decoded packet.
+    pub fn update(&mut self, info: &PacketInfo) {
+        self.totals.add(info.get_byte_transmitted());
+        self.protocols.entry(info.get_protocol().to_string()).or_default().add(info.get_byte_transmitted());
+    }
+
+    /// Accounts a packet that could not be decoded: the truncated packets are told apart from the ones carrying
+    /// protocols the decoder does not support and from the malformed ones.
+    pub fn add_decode_error(&mut self, error: &DecodeError) {
+        let category = match error.kind {
+            DecodeErrorKind::TooShort => "truncated",
+            DecodeErrorKind::UnknownEtherType(_) | DecodeErrorKind::UnknownProtocol(_) => "unsupported",
+            DecodeErrorKind::Malformed => "malformed",
+        };
+        *self.decode_errors.entry(category.to_string()).or_insert(0) += 1;
+    }
+
+    /// Accounts a packet dropped by the filters.
+    pub fn add_filtered(&mut self) { self.filtered += 1 }
+
+    /// Sets the packets dropped before reaching the sniffer, as reported by pcap: because its buffer was full and by
+    /// the network interface.
+    pub fn set_capture_drops(&mut self, kernel_dropped: u32, interface_dropped: u32) {
+        self.kernel_dropped = kernel_dropped;
+        self.interface_dropped = interface_dropped;
+    }
+
+    /// Sets the number of connections in the report, each one an address, port and protocol.
+    pub fn set_connections(&mut self, connections: usize) { self.connections = connections }
+
+    pub fn get_totals(&self) -> TrafficCounter { self.totals }
+    pub fn get_protocol(&self, protocol: &str) -> TrafficCounter { self.protocols.get(protocol).copied().unwrap_or_default() }
+    pub fn get_decode_errors(&self, category: &str) -> usize { self.decode_errors.get(category).copied().unwrap_or(0) }
+    pub fn get_filtered(&self) -> usize { self.filtered }
+    pub fn get_connections(&self) -> usize { self.connections }
+
+    /// Renders the counters in the Prometheus text exposition format.
+    pub fn prometheus_metrics(&self) -> String {
+        let mut result = String::new();
+        let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+            let _ = writeln!(result, "# HELP packet_sniffer_{} {}", name, help);
+            let _ = writeln!(result, "# TYPE packet_sniffer_{} {}", name, kind);
+            for (labels, value) in samples {
+                let _ = writeln!(result, "packet_sniffer_{}{} {}", name, labels, value);
+            }
+        };
+        family("packets_total", "counter", "Packets decoded.", vec![(String::new(), self.totals.get_packets().to_string())]);
+        family("bytes_total", "counter", "Bytes decoded, by level 4 protocol.", self.protocols.iter()
+            .map(|(protocol, counter)| (format!("{{protocol=\"{}\"}}", protocol), counter.get_bytes().to_string()))
+            .collect());
+        family("decode_errors_total", "counter", "Packets that could not be decoded, by category.", self.decode_errors.iter()
+            .map(|(category, count)| (format!("{{category=\"{}\"}}", category), count.to_string()))
+            .collect());
+        family("connections", "gauge", "Connections in the report, by remote address, port and protocol.", vec![(String::new(), self.connections.to_string())]);
+        family("dropped_packets_total", "counter", "Packets dropped, by reason.", vec![
+            ("{reason=\"filter\"}".to_string(), self.filtered.to_string()),
+            ("{reason=\"kernel\"}".to_string(), self.kernel_dropped.to_string()),
+            ("{reason=\"interface\"}".to_string(), self.interface_dropped.to_string()),
+        ]);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::{*};
+    use crate::pkt_parser::{EtherType, Header, Protocol, TimeVal};
+
+    #[test]
+    fn test_priority_breakdown() {
+        // the frame of test_whole_packet_2, tagged on VLAN 10 with priority 5
+        let tagged = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 129, 0, 160, 10, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+        let untagged = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+        let (tagged_len, untagged_len) = (tagged.len(), untagged.len());
+
+        let (tagged_header_res, tagged_payload) = EthernetHeader::decode(tagged);
+        let tagged_header = tagged_header_res.unwrap();
+        let tag = tagged_header.get_vlan_tag().unwrap();
+        assert_eq!(tag.priority(), 5);
+        assert_eq!(tag.get_id(), 10);
+        assert_eq!(tagged_header.get_ether_type(), EtherType::Ipv4);
+        assert_eq!(tagged_payload[0], 69);
+
+        let (untagged_header_res, _payload) = EthernetHeader::decode(untagged);
+        let untagged_header = untagged_header_res.unwrap();
+        assert_eq!(untagged_header.get_vlan_tag(), None);
+
+        let mut breakdown = PriorityBreakdown::new();
+        breakdown.update(&tagged_header, tagged_len);
+        breakdown.update(&tagged_header, tagged_len);
+        breakdown.update(&untagged_header, untagged_len);
+        assert_eq!(breakdown.get_class(5).unwrap().get_packets(), 2);
+        assert_eq!(breakdown.get_class(5).unwrap().get_bytes(), 2 * tagged_len);
+        assert_eq!(breakdown.get_class(0).unwrap().get_bytes(), 0);
+        assert_eq!(breakdown.get_untagged().get_bytes(), untagged_len);
+        assert_eq!(breakdown.get_class(8), None);
+    }
+
+    #[test]
+    fn test_frame_size_histogram() {
+        let mut histogram = FrameSizeHistogram::new();
+        for frame_len in [60, 64, 65, 127, 128, 590, 1024, 1514, 1518, 1522, 2047, 2048, 9000] {
+            histogram.update(frame_len);
+        }
+        let packets: Vec<usize> = histogram.iter().map(|(_, counter)| counter.get_packets()).collect();
+        assert_eq!(packets, vec![2, 2, 1, 0, 1, 3, 2, 2]);
+        assert_eq!(histogram.get_bucket(FrameSizeBucket::Jumbo).get_bytes(), 11048);
+        assert_eq!(histogram.get_bucket(FrameSizeBucket::UpTo64).get_bytes(), 124);
+        assert_eq!(histogram.get_oversize().get_packets(), 4);
+        assert_eq!(FrameSizeBucket::from_len(0), FrameSizeBucket::UpTo64);
+    }
+
+    #[test]
+    fn test_prometheus_metrics() {
+        let mut metrics = CaptureMetrics::new();
+        metrics.update(&PacketInfo::new("192.168.1.1".to_string(), 53, Protocol::UDP, 110, TimeVal::from(1_000_000)));
+        metrics.update(&PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 1400, TimeVal::from(1_000_100)));
+        metrics.update(&PacketInfo::new("149.154.167.92".to_string(), 443, Protocol::TCP, 600, TimeVal::from(1_000_200)));
+        let (ethernet_header_res, _payload) = EthernetHeader::decode(vec![0; 10]);
+        metrics.add_decode_error(&ethernet_header_res.unwrap_err());
+        metrics.add_filtered();
+        metrics.set_capture_drops(7, 0);
+        metrics.set_connections(2);
+
+        let text = metrics.prometheus_metrics();
+        for name in ["packets_total", "bytes_total", "decode_errors_total", "connections", "dropped_packets_total"] {
+            assert!(text.contains(&format!("# TYPE packet_sniffer_{} ", name)), "missing {}", name);
+        }
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines.contains(&"packet_sniffer_packets_total 3"));
+        assert!(lines.contains(&"packet_sniffer_bytes_total{protocol=\"TCP\"} 2000"));
+        assert!(lines.contains(&"packet_sniffer_decode_errors_total{category=\"truncated\"} 1"));
+        assert!(lines.contains(&"packet_sniffer_dropped_packets_total{reason=\"kernel\"} 7"));
+        assert!(lines.contains(&"packet_sniffer_connections 2"));
+    }
+}
@@ -0,0 +1,37 @@
+use std::net::{IpAddr, Ipv4Addr};
+use pcap::{Address, Device, DeviceFlags};
+use packet_sniffer::pkt_parser::{parse_packet, Direction, Endpoint, Protocol, TimeVal};
+
+/// A device whose only address is the one of the local host in the sample packets.
+fn device() -> Device {
+    let address = Address { addr: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 21)), netmask: None, broadcast_addr: None, dst_addr: None };
+    Device { name: "eth0".to_string(), desc: None, addresses: vec![address], flags: DeviceFlags::empty() }
+}
+
+#[test]
+fn parse_transmitted_tcp_segment() {
+    // test_whole_packet_2: a reset sent by 192.168.1.21:56369 to 149.154.167.92:443
+    let data = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+    let info = parse_packet(data, &device(), TimeVal::from(1_000_000)).unwrap();
+    assert_eq!(info.get_address(), "149.154.167.92");
+    assert_eq!(info.get_port(), 443);
+    assert_eq!(info.get_protocol(), Protocol::TCP);
+    assert_eq!(info.get_direction(), Some(Direction::Transmitted));
+    assert_eq!(info.get_ttl(), Some(128));
+    assert!(info.get_tcp_header().unwrap().get_flags().is_rst());
+    // the Ethernet padding is not counted as payload
+    assert_eq!(info.get_byte_transmitted(), 0);
+}
+
+#[test]
+fn parse_received_udp_datagram() {
+    // test_whole_packet_1: a DNS response sent by 192.168.1.1 to the local host
+    let data = vec![80, 235, 113, 35, 142, 103, 152, 0, 106, 4, 85, 32, 8, 0, 69, 0, 0, 130, 170, 10, 64, 0, 64, 17, 12, 250, 192, 168, 1, 1, 192, 168, 1, 21, 0, 53, 234, 64, 0, 110, 71, 245, 212, 212, 129, 131, 0, 1, 0, 0, 0, 1, 0, 0, 4, 119, 112, 97, 100, 4, 104, 111, 109, 101, 0, 0, 1, 0, 1, 0, 0, 6, 0, 1, 0, 0, 0, 91, 0, 64, 1, 97, 12, 114, 111, 111, 116, 45, 115, 101, 114, 118, 101, 114, 115, 3, 110, 101, 116, 0, 5, 110, 115, 116, 108, 100, 12, 118, 101, 114, 105, 115, 105, 103, 110, 45, 103, 114, 115, 3, 99, 111, 109, 0, 120, 134, 93, 48, 0, 0, 7, 8, 0, 0, 3, 132, 0, 9, 58, 128, 0, 1, 81, 128];
+    let info = parse_packet(data, &device(), TimeVal::from(1_000_000)).unwrap();
+    assert_eq!((info.get_address(), info.get_port(), info.get_protocol()), ("192.168.1.1".to_string(), 53, Protocol::UDP));
+    assert_eq!(info.get_direction(), Some(Direction::Received));
+    assert_eq!(info.get_local(), Some(Endpoint::new("192.168.1.21".to_string(), 59968)));
+    assert_eq!(info.get_byte_transmitted(), 102);
+
+    assert!(parse_packet(vec![0; 10], &device(), TimeVal::from(0)).is_err());
+}
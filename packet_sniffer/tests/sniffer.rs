@@ -1,5 +1,10 @@
 use packet_sniffer::sniffer::{RunStatus, Sniffer, SnifferError};
 use packet_sniffer::sniffer::SnifferError::{UserError, UserWarning};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+use pcap::{Address, Device, DeviceFlags, Linktype};
+use packet_sniffer::offline::PcapFileSource;
+use packet_sniffer::pkt_parser::{Direction, PacketInfo, Protocol, TimeVal};
 
 #[test]
 fn init_status_is_stop() {
@@ -115,7 +120,7 @@ fn run_while_already_sniffing() {
 
 #[test]
 fn resume_without_running() {
-    let mut sniffer = Sniffer::new();
+    let sniffer = Sniffer::new();
     let res = sniffer.resume();
     assert_eq!(sniffer.get_status(), RunStatus::Stop);
     assert!(res.is_err());
@@ -153,7 +158,7 @@ fn resume_while_running() {
 
 #[test]
 fn pause_without_running(){
-    let mut sniffer = Sniffer::new();
+    let sniffer = Sniffer::new();
     let res = sniffer.pause();
     assert_eq!(sniffer.get_status(), RunStatus::Stop);
     assert!(res.is_err());
@@ -190,4 +195,76 @@ fn pause_while_already_paused() {
             panic!("Repeat the test");
         }
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn set_buffer_size() {
+    let mut sniffer = Sniffer::new();
+    assert_eq!(sniffer.get_buffer_size(), packet_sniffer::sniffer::DEFAULT_BUFFER_SIZE);
+    assert!(sniffer.set_buffer_size(16 * 1024 * 1024).is_ok());
+    assert_eq!(sniffer.get_buffer_size(), 16 * 1024 * 1024);
+    assert_eq!(sniffer.set_buffer_size(0).unwrap_err(), UserError("The buffer size must be positive ...".to_string()));
+    assert!(sniffer.set_buffer_size(-1).is_err());
+    assert_eq!(sniffer.get_buffer_size(), 16 * 1024 * 1024);
+
+    // the size is set on the pcap handle
+    let options = sniffer.get_capture_options();
+    assert_eq!(options.get_buffer_size(), 16 * 1024 * 1024);
+    assert!(options.get_promisc());
+    assert_eq!(options.get_timeout(), None);
+}
+
+#[test]
+fn start_offline_calls_back_every_packet() {
+    let source = PcapFileSource::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/sample.pcap")).unwrap();
+    let linktype = source.get_linktype();
+    let address = Address { addr: "192.168.1.21".parse().unwrap(), netmask: None, broadcast_addr: None, dst_addr: None };
+    let device = Device { name: "eth0".to_string(), desc: None, addresses: vec![address], flags: DeviceFlags::empty() };
+    let (tx, rx) = channel();
+    let mut sniffer = Sniffer::new();
+    let handle = sniffer.start_offline(source.map_while(Result::ok), linktype, device, move |info| tx.send(info).unwrap()).unwrap();
+    handle.join().unwrap();
+    assert_eq!(sniffer.get_status(), RunStatus::Stop);
+
+    let packets: Vec<PacketInfo> = rx.iter().collect();
+    assert_eq!(packets.len(), 2);
+    assert_eq!((packets[0].get_address(), packets[0].get_port(), packets[0].get_protocol()), ("149.154.167.92".to_string(), 443, Protocol::TCP));
+    assert_eq!(packets[0].get_direction(), Some(Direction::Transmitted));
+    assert_eq!(packets[0].get_time_stamp(), TimeVal::from(1657968204419346));
+    assert_eq!((packets[1].get_address(), packets[1].get_port(), packets[1].get_protocol()), ("192.168.1.1".to_string(), 53, Protocol::UDP));
+}
+
+#[test]
+fn paused_capture_drops_packets() {
+    let frame = vec![152, 0, 106, 4, 85, 32, 80, 235, 113, 35, 142, 103, 8, 0, 69, 0, 0, 40, 134, 79, 64, 0, 128, 6, 0, 0, 192, 168, 1, 21, 149, 154, 167, 92, 220, 49, 1, 187, 135, 216, 62, 67, 24, 80, 57, 27, 80, 20, 0, 0, 254, 206, 0, 0];
+    let device = Device { name: "eth0".to_string(), desc: None, addresses: vec![], flags: DeviceFlags::empty() };
+    let (frames_tx, frames_rx) = channel();
+    let (packets_tx, packets_rx) = channel();
+    let mut sniffer = Sniffer::new();
+    let handle = sniffer.start_offline(frames_rx.into_iter(), Linktype::ETHERNET, device.clone(), move |info| packets_tx.send(info).unwrap()).unwrap();
+    let timeout = Duration::from_secs(5);
+
+    frames_tx.send((frame.clone(), TimeVal::from(1))).unwrap();
+    assert_eq!(packets_rx.recv_timeout(timeout).unwrap().get_time_stamp(), TimeVal::from(1));
+    // a second capture cannot start while the first one is running
+    let error = sniffer.start_offline(std::iter::empty(), Linktype::ETHERNET, device, |_info| {}).unwrap_err();
+    assert_eq!(error, UserWarning("Another scanning is already running ...".to_string()));
+
+    sniffer.pause().unwrap();
+    assert_eq!(sniffer.get_status(), RunStatus::Wait);
+    frames_tx.send((frame.clone(), TimeVal::from(2))).unwrap();
+    frames_tx.send((frame.clone(), TimeVal::from(3))).unwrap();
+    assert!(packets_rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+    // the packets captured while paused are not delivered after the resume
+    sniffer.resume().unwrap();
+    frames_tx.send((frame.clone(), TimeVal::from(4))).unwrap();
+    assert_eq!(packets_rx.recv_timeout(timeout).unwrap().get_time_stamp(), TimeVal::from(4));
+
+    sniffer.stop().unwrap();
+    frames_tx.send((frame, TimeVal::from(5))).unwrap();
+    handle.join().unwrap();
+    assert!(packets_rx.recv_timeout(Duration::from_millis(200)).is_err());
+    assert_eq!(sniffer.get_status(), RunStatus::Stop);
+    assert!(sniffer.stop().is_err());
+}